@@ -1,13 +1,25 @@
 use std::io::Result;
 
 fn main() -> Result<()> {
-    prost_build::compile_protos(
-        &[
-            "src/message/wrappers.proto",
-            "src/message/system.proto",
-            "src/actor/address.proto",
-        ],
-        &["src/"],
-    )?;
+    // Address types are compiled on their own, since they are mapped to an extern path (and not
+    // generated) when compiling the protobuf files that import them.
+    prost_build::Config::new().compile_protos(&["src/actor/address.proto"], &["src/"])?;
+
+    prost_build::Config::new()
+        // Generated code is included into modules that do not mirror the protobuf package
+        // structure, so cross-package references must be mapped explicitly.
+        .extern_path(".actor.proto", "crate::actor::proto")
+        .compile_protos(
+            &[
+                "src/message/wrappers.proto",
+                "src/message/system.proto",
+                "src/patterns/circuit_breaker.proto",
+                "src/patterns/router.proto",
+                "src/patterns/scatter_gather.proto",
+                "src/patterns/throttle.proto",
+                "src/patterns/work_pulling.proto",
+            ],
+            &["src/"],
+        )?;
     Ok(())
 }
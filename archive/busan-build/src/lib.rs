@@ -0,0 +1,391 @@
+//! `busan-build` compiles protobuf definitions into Rust types that are ready to be used as
+//! busan messages. It is a thin wrapper around [`prost_build`] that:
+//!
+//!   + Derives `busan::Message` for every generated message type
+//!   + Derives `busan::Message` for every `oneof`, making it a message family of the containing
+//!     message (see `busan_derive::Message`)
+//!   + Generates a `register_messages` function for every protobuf package, which registers the
+//!     package's types with the [busan message registry][registry]
+//!   + Optionally writes the compiled file descriptor set, which can be used for reflection
+//!
+//! [registry]: https://docs.rs/busan/latest/busan/message/registry/index.html
+//!
+//! ## Example `build.rs`
+//!
+//! ```rust,no_run
+//! fn main() -> std::io::Result<()> {
+//!     busan_build::compile_protos(&["src/hello_world.proto"], &["src/"])
+//! }
+//! ```
+//!
+//! The generated code is included in the same way as with `prost_build`:
+//!
+//! ```rust,ignore
+//! mod proto {
+//!     include!(concat!(env!("OUT_DIR"), "/hello_world.rs"));
+//! }
+//!
+//! fn main() {
+//!     // Make the message types available for decoding by name
+//!     proto::register_messages();
+//! }
+//! ```
+
+use heck::{ToSnakeCase, ToUpperCamelCase};
+use prost::Message;
+use prost_types::{DescriptorProto, FileDescriptorSet};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs};
+
+/// Compile `.proto` files into Rust message types with the default [`Builder`] configuration.
+///
+/// This is the `busan-build` equivalent of [`prost_build::compile_protos`] and takes the same
+/// arguments.
+pub fn compile_protos(protos: &[impl AsRef<Path>], includes: &[impl AsRef<Path>]) -> Result<()> {
+    Builder::new().compile_protos(protos, includes)
+}
+
+/// Configuration for compiling protobuf definitions into busan messages.
+///
+/// Additional code generation options can be set on the underlying [`prost_build::Config`]
+/// through [`Builder::prost_config`].
+pub struct Builder {
+    prost_config: prost_build::Config,
+    out_dir: Option<PathBuf>,
+    register_messages: bool,
+    file_descriptor_set_path: Option<PathBuf>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self {
+            prost_config: prost_build::Config::new(),
+            out_dir: None,
+            register_messages: true,
+            file_descriptor_set_path: None,
+        }
+    }
+
+    /// Access the underlying `prost_build` configuration for setting any options not directly
+    /// exposed by the `Builder`.
+    pub fn prost_config(&mut self) -> &mut prost_build::Config {
+        &mut self.prost_config
+    }
+
+    /// Set the directory generated code is written to. Defaults to the `OUT_DIR` environment
+    /// variable set by cargo.
+    pub fn out_dir(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.out_dir = Some(path.into());
+        self
+    }
+
+    /// Enable or disable generation of `register_messages` functions (enabled by default).
+    pub fn register_messages(&mut self, enabled: bool) -> &mut Self {
+        self.register_messages = enabled;
+        self
+    }
+
+    /// Write the compiled `FileDescriptorSet` to `path`. This is not written by default.
+    pub fn file_descriptor_set_path(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.file_descriptor_set_path = Some(path.into());
+        self
+    }
+
+    /// Compile `.proto` files into Rust files. See [`compile_protos`] for details.
+    pub fn compile_protos(
+        &mut self,
+        protos: &[impl AsRef<Path>],
+        includes: &[impl AsRef<Path>],
+    ) -> Result<()> {
+        let out_dir = match &self.out_dir {
+            Some(path) => path.clone(),
+            None => env::var_os("OUT_DIR")
+                .map(PathBuf::from)
+                .ok_or_else(|| Error::other("OUT_DIR is not set"))?,
+        };
+
+        let descriptor_path = out_dir.join("busan-descriptor-set.bin");
+        run_protoc(protos, includes, &descriptor_path)?;
+        let bytes = fs::read(&descriptor_path)?;
+        let fds = FileDescriptorSet::decode(bytes.as_slice())
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        if let Some(path) = &self.file_descriptor_set_path {
+            fs::copy(&descriptor_path, path)?;
+        }
+
+        // Collect the set of generated message types (per output file) before handing the
+        // descriptors off to prost.
+        let mut packages: BTreeMap<String, Vec<MessageType>> = BTreeMap::new();
+        for file in fds.file.iter() {
+            let file_name = prost_build::Module::from_protobuf_package_name(file.package())
+                .to_file_name_or("_");
+            let messages = packages.entry(file_name).or_default();
+            for message in file.message_type.iter() {
+                collect_messages(file.package(), "", message, messages);
+            }
+        }
+
+        self.prost_config
+            .out_dir(&out_dir)
+            .message_attribute(".", "#[derive(::busan::Message)]");
+
+        // Oneofs are generated as an enum in a module named after the message. These are derived
+        // as a message family of the containing message type.
+        for message in packages.values().flatten() {
+            for oneof in message.oneofs.iter() {
+                self.prost_config.enum_attribute(
+                    format!(".{}.{}", message.proto_name, oneof),
+                    format!(
+                        "#[derive(::busan::Message)] #[busan(family = \"super::{}\", field = \"{}\")]",
+                        message.type_name(),
+                        to_snake(oneof)
+                    ),
+                );
+            }
+        }
+        self.prost_config.compile_fds(fds)?;
+
+        if self.register_messages {
+            for (file_name, messages) in packages.iter() {
+                let path = out_dir.join(file_name);
+                if messages.is_empty() || !path.exists() {
+                    continue;
+                }
+                let mut generated = fs::read_to_string(&path)?;
+                generated.push_str(&registration_fn(messages));
+                fs::write(&path, generated)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A generated message type, identified by its full protobuf name and the path to the Rust
+/// type relative to the generated module.
+struct MessageType {
+    proto_name: String,
+    rust_path: String,
+    /// Names of the (non-synthetic) oneofs declared in the message
+    oneofs: Vec<String>,
+}
+
+impl MessageType {
+    /// The name of the Rust type, without any module prefix
+    fn type_name(&self) -> &str {
+        self.rust_path.rsplit("::").next().unwrap()
+    }
+}
+
+fn collect_messages(
+    proto_prefix: &str,
+    rust_prefix: &str,
+    message: &DescriptorProto,
+    messages: &mut Vec<MessageType>,
+) {
+    // Map entries are synthesized by protoc and are not generated as Rust types
+    if message
+        .options
+        .as_ref()
+        .and_then(|o| o.map_entry)
+        .unwrap_or(false)
+    {
+        return;
+    }
+
+    let proto_name = if proto_prefix.is_empty() {
+        message.name().to_string()
+    } else {
+        format!("{}.{}", proto_prefix, message.name())
+    };
+    // proto3 `optional` fields are implemented as a synthetic oneof, which prost generates as
+    // a plain `Option` field rather than an enum.
+    let oneofs = message
+        .oneof_decl
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| {
+            !message
+                .field
+                .iter()
+                .any(|f| f.oneof_index == Some(*index as i32) && f.proto3_optional())
+        })
+        .map(|(_, oneof)| oneof.name().to_string())
+        .collect();
+    messages.push(MessageType {
+        proto_name: proto_name.clone(),
+        rust_path: format!("{}{}", rust_prefix, to_upper_camel(message.name())),
+        oneofs,
+    });
+
+    // Nested types are generated in a module named after the parent message
+    let nested_prefix = format!("{}{}::", rust_prefix, to_snake(message.name()));
+    for nested in message.nested_type.iter() {
+        collect_messages(&proto_name, &nested_prefix, nested, messages);
+    }
+}
+
+fn registration_fn(messages: &[MessageType]) -> String {
+    let mut generated = String::new();
+    generated.push_str(
+        "/// Register all message types of this package with the busan message registry.\n",
+    );
+    generated.push_str("pub fn register_messages() {\n");
+    for message in messages {
+        let _ = writeln!(
+            generated,
+            "    ::busan::message::registry::register::<{}>(\"{}\");",
+            message.rust_path, message.proto_name
+        );
+    }
+    generated.push_str("}\n");
+    generated
+}
+
+fn run_protoc(
+    protos: &[impl AsRef<Path>],
+    includes: &[impl AsRef<Path>],
+    descriptor_path: &Path,
+) -> Result<()> {
+    let mut cmd = Command::new(prost_build::protoc_from_env());
+    cmd.arg("--include_imports")
+        .arg("--include_source_info")
+        .arg("-o")
+        .arg(descriptor_path);
+    for include in includes {
+        if include.as_ref().exists() {
+            cmd.arg("-I").arg(include.as_ref());
+        }
+    }
+    if let Some(protoc_include) = prost_build::protoc_include_from_env() {
+        cmd.arg("-I").arg(protoc_include);
+    }
+    for proto in protos {
+        cmd.arg(proto.as_ref());
+    }
+
+    let output = cmd.output()?;
+    if !output.status.success() {
+        return Err(Error::other(format!(
+            "protoc failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(())
+}
+
+/// Mirrors the type naming used by prost (`prost_build::ident::to_upper_camel`)
+fn to_upper_camel(s: &str) -> String {
+    let mut ident = s.to_upper_camel_case();
+    if ident == "Self" {
+        ident += "_";
+    }
+    ident
+}
+
+/// Mirrors the module naming used by prost (`prost_build::ident::to_snake`)
+fn to_snake(s: &str) -> String {
+    let mut ident = s.to_snake_case();
+    match ident.as_str() {
+        "as" | "break" | "const" | "continue" | "else" | "enum" | "false" | "fn" | "for" | "if"
+        | "impl" | "in" | "let" | "loop" | "match" | "mod" | "move" | "mut" | "pub" | "ref"
+        | "return" | "static" | "struct" | "trait" | "true" | "type" | "unsafe" | "use"
+        | "where" | "while" | "dyn" | "abstract" | "become" | "box" | "do" | "final" | "macro"
+        | "override" | "priv" | "typeof" | "unsized" | "virtual" | "yield" | "async" | "await"
+        | "try" => ident.insert_str(0, "r#"),
+        "self" | "super" | "extern" | "crate" => ident += "_",
+        _ => (),
+    }
+    ident
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_types::{FieldDescriptorProto, MessageOptions, OneofDescriptorProto};
+
+    fn descriptor(name: &str, nested: Vec<DescriptorProto>) -> DescriptorProto {
+        DescriptorProto {
+            name: Some(name.to_string()),
+            nested_type: nested,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_collect_nested_messages() {
+        let map_entry = DescriptorProto {
+            name: Some("LabelsEntry".to_string()),
+            options: Some(MessageOptions {
+                map_entry: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let message = descriptor("outer_type", vec![descriptor("Inner", vec![]), map_entry]);
+
+        let mut messages = vec![];
+        collect_messages("my.pkg", "", &message, &mut messages);
+
+        let names: Vec<_> = messages
+            .iter()
+            .map(|m| (m.proto_name.as_str(), m.rust_path.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("my.pkg.outer_type", "OuterType"),
+                ("my.pkg.outer_type.Inner", "outer_type::Inner"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_oneofs() {
+        let field = |name: &str, oneof_index: i32, proto3_optional: bool| FieldDescriptorProto {
+            name: Some(name.to_string()),
+            oneof_index: Some(oneof_index),
+            proto3_optional: Some(proto3_optional),
+            ..Default::default()
+        };
+        let oneof = |name: &str| OneofDescriptorProto {
+            name: Some(name.to_string()),
+            ..Default::default()
+        };
+        let message = DescriptorProto {
+            name: Some("Commands".to_string()),
+            field: vec![
+                field("start", 0, false),
+                field("stop", 0, false),
+                field("label", 1, true),
+            ],
+            oneof_decl: vec![oneof("command"), oneof("_label")],
+            ..Default::default()
+        };
+
+        let mut messages = vec![];
+        collect_messages("my.pkg", "", &message, &mut messages);
+        assert_eq!(messages[0].oneofs, vec!["command".to_string()]);
+        assert_eq!(messages[0].type_name(), "Commands");
+    }
+
+    #[test]
+    fn test_registration_fn() {
+        let generated = registration_fn(&[MessageType {
+            proto_name: "hello_world.init".to_string(),
+            rust_path: "Init".to_string(),
+            oneofs: vec![],
+        }]);
+        assert!(generated
+            .contains("::busan::message::registry::register::<Init>(\"hello_world.init\");"));
+    }
+}
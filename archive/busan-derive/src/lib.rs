@@ -2,14 +2,46 @@ extern crate proc_macro;
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use syn::{parse_macro_input, Data, DataEnum, DeriveInput, Fields, Lit, Meta, NestedMeta};
 
-#[proc_macro_derive(Message)]
+/// Derive `busan::Message` for a prost generated type.
+///
+/// When applied to a message (struct), this implements the `Message` trait. When applied to a
+/// prost `oneof` enum, the enum is treated as a _message family_ and the wrapping message must be
+/// identified with the `busan` attribute:
+///
+/// ```rust,ignore
+/// #[derive(Clone, PartialEq, ::prost::Oneof, busan::Message)]
+/// #[busan(family = "super::Commands", field = "command")]
+/// pub enum Command { ... }
+/// ```
+///
+/// For a family, `ToMessage<Family>` is implemented for the enum and for the type of every
+/// message variant (`#[prost(message, ...)]`), so that any single message variant can be sent and
+/// will be wrapped into the family message. Since these types are messages themselves and can also
+/// be sent as-is, the family may need to be named when sending (e.g.
+/// `ctx.send::<Commands, _>(&addr, start)`). Scalar, string, bytes and enumeration variants only
+/// convert through the enum, so sending such values on their own (e.g. a `u64` as a
+/// `U64Wrapper`) is unaffected by the family. `busan-build` adds the attribute automatically for
+/// all generated `oneof`s.
+#[proc_macro_derive(Message, attributes(busan))]
 pub fn message(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
-    let name = &ast.ident;
 
-    let expanded = quote! {
+    let expanded = match &ast.data {
+        Data::Enum(data) => match message_family(&ast, data) {
+            Ok(expanded) => expanded,
+            Err(err) => err.to_compile_error(),
+        },
+        _ => message_impl(&ast),
+    };
+
+    proc_macro::TokenStream::from(expanded)
+}
+
+fn message_impl(ast: &DeriveInput) -> proc_macro2::TokenStream {
+    let name = &ast.ident;
+    quote! {
         impl ::busan::message::Message for #name {
             fn as_any(&self) -> &dyn ::std::any::Any {
                 self
@@ -22,8 +54,130 @@ pub fn message(input: TokenStream) -> TokenStream {
             fn merge2(&mut self, bytes: &[u8]) -> Result<(), prost::DecodeError> {
                 prost::Message::merge(self, bytes)
             }
+
+            fn clear2(&mut self) {
+                prost::Message::clear(self)
+            }
+
+            fn clone_box(&self) -> Box<dyn ::busan::message::Message> {
+                Box::new(::std::clone::Clone::clone(self))
+            }
         }
-    };
+    }
+}
 
-    proc_macro::TokenStream::from(expanded)
+fn message_family(ast: &DeriveInput, data: &DataEnum) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &ast.ident;
+    let (family, field) = family_attribute(ast)?;
+
+    // Collect the (single, unnamed) type of each message variant. Types that appear in more than
+    // one variant are ambiguous and do not get a `ToMessage` implementation. Other variants (e.g.
+    // `u64` or `String`) already convert to the common wrapper types, which would make sending
+    // them ambiguous, so they don't get one either.
+    let mut variants = vec![];
+    for variant in data.variants.iter() {
+        match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let ty = &fields.unnamed.first().unwrap().ty;
+                variants.push((&variant.ident, ty, is_message_variant(variant)));
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "message family variants must contain exactly one unnamed field",
+                ))
+            }
+        }
+    }
+    let type_key = |ty: &syn::Type| quote!(#ty).to_string();
+    let variant_impls = variants
+        .iter()
+        .filter(|(_, ty, is_message)| {
+            *is_message
+                && variants
+                    .iter()
+                    .filter(|(_, other, _)| type_key(other) == type_key(ty))
+                    .count()
+                    == 1
+        })
+        .map(|(ident, ty, _)| {
+            quote! {
+                impl ::busan::message::ToMessage<#family> for #ty {
+                    fn to_message(self) -> #family {
+                        #family {
+                            #field: ::std::option::Option::Some(#name::#ident(self)),
+                            ..::std::default::Default::default()
+                        }
+                    }
+                }
+            }
+        });
+
+    Ok(quote! {
+        impl ::busan::message::ToMessage<#family> for #name {
+            fn to_message(self) -> #family {
+                #family {
+                    #field: ::std::option::Option::Some(self),
+                    ..::std::default::Default::default()
+                }
+            }
+        }
+
+        #(#variant_impls)*
+
+        impl #name {
+            /// Extract the family variant from a received message. Returns `None` if the message
+            /// is not of the family type or if no variant is set.
+            pub fn from_message(msg: &dyn ::busan::message::Message) -> ::std::option::Option<&#name> {
+                msg.as_any().downcast_ref::<#family>()?.#field.as_ref()
+            }
+        }
+    })
+}
+
+/// Returns true if the variant holds a message, i.e. is annotated with `#[prost(message, ...)]`.
+fn is_message_variant(variant: &syn::Variant) -> bool {
+    variant
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("prost"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(list)) => Some(list),
+            _ => None,
+        })
+        .any(|list| {
+            list.nested.iter().any(|nested| {
+                matches!(nested, NestedMeta::Meta(Meta::Path(path)) if path.is_ident("message"))
+            })
+        })
+}
+
+/// Parse `#[busan(family = "path::To::Message", field = "field_name")]`
+fn family_attribute(ast: &DeriveInput) -> syn::Result<(syn::Path, syn::Ident)> {
+    let mut family = None;
+    let mut field = None;
+    for attr in ast.attrs.iter().filter(|a| a.path.is_ident("busan")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(syn::Error::new_spanned(meta, "expected #[busan(...)]")),
+        };
+        for nested in list.nested.iter() {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) => match (&nv.lit, nv.path.get_ident()) {
+                    (Lit::Str(s), Some(key)) if key == "family" => family = Some(s.parse()?),
+                    (Lit::Str(s), Some(key)) if key == "field" => field = Some(s.parse()?),
+                    _ => return Err(syn::Error::new_spanned(nv, "unknown busan attribute")),
+                },
+                _ => return Err(syn::Error::new_spanned(nested, "unknown busan attribute")),
+            }
+        }
+    }
+
+    match (family, field) {
+        (Some(family), Some(field)) => Ok((family, field)),
+        _ => Err(syn::Error::new_spanned(
+            &ast.ident,
+            "deriving Message for an enum requires #[busan(family = \"...\", field = \"...\")]",
+        )),
+    }
 }
@@ -0,0 +1,128 @@
+//! Tests for deriving `Message` on prost `oneof` enums (message families). These live outside of
+//! the crate since a proc-macro crate cannot use its own derives.
+
+use busan::config::ActorSystemConfig;
+use busan::message::common_types::{StringWrapper, U64Wrapper};
+use busan::message::{Message, ToMessage};
+use busan::ActorSystem;
+use std::time::Duration;
+
+#[derive(Clone, PartialEq, prost::Message, busan::Message)]
+pub struct Start {
+    #[prost(string, tag = "1")]
+    pub name: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message, busan::Message)]
+pub struct Stop {
+    #[prost(bool, tag = "1")]
+    pub force: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message, busan::Message)]
+pub struct Commands {
+    #[prost(uint32, tag = "1")]
+    pub id: u32,
+    #[prost(oneof = "Command", tags = "2, 3, 4, 5, 6")]
+    pub command: Option<Command>,
+}
+
+/// `Stop` is only used by a single variant, `Start` is shared by two variants and is therefore
+/// ambiguous. `Delay` and `Name` are not messages.
+#[derive(Clone, PartialEq, prost::Oneof, busan::Message)]
+#[busan(family = "Commands", field = "command")]
+pub enum Command {
+    #[prost(message, tag = "2")]
+    Start(Start),
+    #[prost(message, tag = "3")]
+    Restart(Start),
+    #[prost(message, tag = "4")]
+    Stop(Stop),
+    #[prost(uint64, tag = "5")]
+    Delay(u64),
+    #[prost(string, tag = "6")]
+    Name(String),
+}
+
+/// Only compiles if `T` can be converted into the family message.
+fn to_commands<T: ToMessage<Commands>>(value: T) -> Commands {
+    value.to_message()
+}
+
+#[test]
+fn test_family_to_message() {
+    let msg = to_commands(Command::Restart(Start {
+        name: "restart".to_string(),
+    }));
+    assert_eq!(
+        msg.command,
+        Some(Command::Restart(Start {
+            name: "restart".to_string()
+        }))
+    );
+    assert_eq!(msg.id, 0);
+}
+
+#[test]
+fn test_unique_variant_to_message() {
+    let msg = to_commands(Stop { force: true });
+    assert_eq!(msg.command, Some(Command::Stop(Stop { force: true })));
+}
+
+#[test]
+fn test_scalar_variants_send_as_wrappers() {
+    // Only compiles if `u64` and `String` have no `ToMessage<Commands>` impl, which would make the
+    // message type of the sends below ambiguous.
+    let system = ActorSystem::init(ActorSystemConfig::default());
+    let inbox = system.handle().inbox().unwrap();
+    inbox.send(inbox.address(), 5u64).unwrap();
+    inbox.send(inbox.address(), "name".to_string()).unwrap();
+
+    let timeout = Duration::from_secs(5);
+    let msg = inbox.receive(timeout).unwrap();
+    assert_eq!(msg.as_any().downcast_ref::<U64Wrapper>().unwrap().value, 5);
+    let msg = inbox.receive(timeout).unwrap();
+    assert_eq!(
+        msg.as_any().downcast_ref::<StringWrapper>().unwrap().value,
+        "name"
+    );
+    drop(inbox);
+    system.shutdown();
+
+    // Scalar variants are wrapped by sending the enum
+    let msg = to_commands(Command::Delay(5));
+    assert_eq!(msg.command, Some(Command::Delay(5)));
+}
+
+#[test]
+fn test_shared_variant_is_sent_as_is() {
+    // `Start` has no `ToMessage<Commands>` impl since it is ambiguous which variant it belongs
+    // to, so it only converts to itself.
+    let start = Start {
+        name: "start".to_string(),
+    };
+    let msg: Start = start.clone().to_message();
+    assert_eq!(msg, start);
+
+    // The variant has to be picked explicitly instead.
+    let msg = to_commands(Command::Start(start.clone()));
+    assert_eq!(msg.command, Some(Command::Start(start)));
+}
+
+#[test]
+fn test_from_message() {
+    let msg: Box<dyn Message> = Box::new(Commands {
+        id: 1,
+        command: Some(Command::Delay(10)),
+    });
+    assert_eq!(
+        Command::from_message(msg.as_ref()),
+        Some(&Command::Delay(10))
+    );
+
+    let empty: Box<dyn Message> = Box::new(Commands::default());
+    assert_eq!(Command::from_message(empty.as_ref()), None);
+
+    let other: Box<dyn Message> = Box::new(Stop { force: false });
+    assert_eq!(Command::from_message(other.as_ref()), None);
+}
@@ -1,9 +1,6 @@
 use std::io::Result;
 
 fn main() -> Result<()> {
-    let mut config = prost_build::Config::new();
-    config
-        .type_attribute(".", "#[derive(::busan::Message)]")
-        .compile_protos(&["src/hello_world.proto"], &["src/"])?;
+    busan_build::compile_protos(&["src/hello_world.proto"], &["src/"])?;
     Ok(())
 }
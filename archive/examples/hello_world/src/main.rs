@@ -5,6 +5,10 @@ use busan::message::Message;
 use busan::system::ActorSystem;
 
 fn main() {
+    // Register our generated message types with the message registry. This isn't needed to
+    // send or receive messages, but allows for messages to be decoded by their protobuf name.
+    proto::register_messages();
+
     // Create a default actor system. This is the root of what will house and run our
     // actors, similar to a thread-pool for threads (but more sophisticated).
     let mut system = ActorSystem::init(ActorSystemConfig::default());
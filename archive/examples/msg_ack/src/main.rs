@@ -2,8 +2,11 @@
 //! as the previous message has been acknowledged. Since message ACK's are based on
 //! receipt in the actor's queue and not a signal for message processing, this isn't
 //! a real load balancer.
-use busan::actor::{Actor, ActorAddress, ActorInit, Context};
-use busan::config::{ActorSystemConfig, ExecutorConfig};
+//!
+//! Workers block while processing work, so they are spawned in a pinned executor group
+//! where each worker runs on a thread of its own.
+use busan::actor::{Actor, ActorAddress, ActorInit, Context, SpawnOptions};
+use busan::config::{ActorSystemConfig, ExecutorConfig, ExecutorGroupConfig};
 use busan::message::common_types::{I32Wrapper, U32Wrapper};
 use busan::message::system::Ack;
 use busan::message::Message;
@@ -56,7 +59,13 @@ impl Actor for Distributor {
         // soon as the worker is ready.
         self.workers = (0..=(self.worker_count))
             .map(|_| {
-                let worker = ctx.spawn_child::<Worker, _, _>("worker", 0).await_unwrap();
+                let worker = ctx
+                    .spawn_child_with::<Worker, _, _>(
+                        "worker",
+                        0,
+                        SpawnOptions::new().with_group("workers"),
+                    )
+                    .await_unwrap();
                 self.send_work(&mut ctx, &worker);
                 worker
             })
@@ -117,9 +126,11 @@ fn main() {
 
     let mut system = ActorSystem::init(ActorSystemConfig {
         executor_config: ExecutorConfig {
-            num_executors: 10,
+            num_executors: 2,
+            groups: vec![ExecutorGroupConfig::pinned("workers")],
             ..ExecutorConfig::default()
         },
+        ..ActorSystemConfig::default()
     });
     system.spawn_root_actor::<Distributor, _, _>("distributor", 10u32);
     system.await_shutdown();
@@ -1,12 +1,23 @@
-use crate::actor::{ActorAddress, BehaviorSet, Envelope, SenderType};
+use crate::actor::{
+    validate_segment, ActorAddress, ActorSelection, BehaviorSet, Envelope, PathPattern, Schedule,
+    SenderType, ServiceKey,
+};
 use crate::error::BusanError;
-use crate::executor::ExecutorCommands;
+use crate::executor::dispatch;
+use crate::executor::placement::PlacementStrategy;
+use crate::executor::{ExecutorCommands, ScheduledMessage};
+use crate::message::system::BlockingTaskFailed;
 use crate::message::{Message, ToMessage};
 use crate::system::RuntimeManagerRef;
 use crate::util::lib_macros::channel_send;
+use crate::util::rng::Rng;
 use crate::util::CommandChannel;
-use crossbeam_channel::Receiver;
-use log::{trace, warn};
+use crossbeam_channel::{bounded, Receiver};
+use log::warn;
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Trait that defines the behavior of an actor. This is the primary interface that must be
 /// implemented when defining an actor.
@@ -69,6 +80,35 @@ pub trait Actor: Send {
     {
         self
     }
+
+    /// Returns the actor as an async actor, if it is an [`AsyncActor`](crate::actor::AsyncActor).
+    #[cfg(feature = "tokio")]
+    #[doc(hidden)]
+    fn as_async(&mut self) -> Option<&mut dyn crate::actor::DynAsyncActor> {
+        None
+    }
+}
+
+/// Returns true if `actor` is an async actor, which can only be run by async executors.
+pub(crate) fn is_async(actor: &mut dyn Actor) -> bool {
+    #[cfg(feature = "tokio")]
+    return actor.as_async().is_some();
+    #[cfg(not(feature = "tokio"))]
+    {
+        let _ = actor;
+        false
+    }
+}
+
+/// The message of a panic, if it has one.
+fn panic_reason(payload: &(dyn Any + Send)) -> String {
+    if let Some(reason) = payload.downcast_ref::<&str>() {
+        reason.to_string()
+    } else if let Some(reason) = payload.downcast_ref::<String>() {
+        reason.clone()
+    } else {
+        "unknown panic".to_string()
+    }
 }
 
 /// ActorInit defines a method of construction for an actor that takes an initialization
@@ -89,6 +129,7 @@ pub mod cell_state {
     use super::CellState;
 
     const SHUTDOWN: CellState = 0b0000_0001;
+    const STARTED: CellState = 0b0000_0010;
 
     /// Check if the cell is in a shutdown state
     pub fn is_shutdown(state: CellState) -> bool {
@@ -100,17 +141,32 @@ pub mod cell_state {
     pub fn set_shutdown(state: &mut CellState) {
         *state |= SHUTDOWN;
     }
+
+    /// Check if `before_start` has been called for the actor of the cell
+    pub fn is_started(state: CellState) -> bool {
+        state & STARTED == STARTED
+    }
+
+    /// Mark the actor of the cell as started. Actors moved to another executor keep their
+    /// state, so they are not started again.
+    pub fn set_started(state: &mut CellState) {
+        *state |= STARTED;
+    }
 }
 
 /// [`ActorCell`] is the wrapper to the user-defined actor, wrapping the mailbox parent references,
 /// and other actor-related information that is useful internally. This is primarily an internal
-/// interface, but is exposed for user-provided executors or extensions.
-/// <!-- TODO: Is this actually useful for extension or does it need to be opened up more? -->
+/// interface, but is exposed for user-provided executors or extensions (see
+/// [`ExecutorFactory`](crate::executor::ExecutorFactory)).
 pub struct ActorCell {
     pub(crate) actor: Box<dyn Actor>,
     pub(crate) mailbox: Receiver<Envelope>,
+    /// Scheduling state of the actor on its executor, shared with its mailbox
+    pub(crate) schedule: Arc<Schedule>,
     pub(crate) address: ActorAddress,
     pub(crate) children: Vec<ActorAddress>,
+    /// Counter used to give each child a unique name (see [`ActorAddress::new_child`])
+    pub(crate) next_child_id: usize,
     pub(crate) parent: Option<ActorAddress>,
     pub(crate) state: CellState,
     pub(crate) ack_nonce: u32,
@@ -121,20 +177,115 @@ impl ActorCell {
     pub(crate) fn new(
         actor: Box<dyn Actor>,
         mailbox: Receiver<Envelope>,
+        schedule: Arc<Schedule>,
         address: ActorAddress,
         parent: Option<ActorAddress>,
     ) -> Self {
         Self {
             actor,
             mailbox,
+            schedule,
             address,
             children: Vec::new(),
+            next_child_id: 0,
             parent,
             state: 0,
             ack_nonce: 0,
             behavior: BehaviorSet::empty(),
         }
     }
+
+    /// The address of the actor.
+    pub fn address(&self) -> &ActorAddress {
+        &self.address
+    }
+
+    /// Returns true if messages are queued for the actor.
+    pub fn has_pending_messages(&self) -> bool {
+        !self.mailbox.is_empty()
+    }
+
+    /// Start the actor by calling its `before_start` hook. Called once the cell has been assigned
+    /// to the executor, before any messages are processed. Actors that have been moved from
+    /// another executor are not started again.
+    pub fn start(
+        &mut self,
+        runtime_manager: &RuntimeManagerRef,
+        command_channel: &CommandChannel<ExecutorCommands>,
+    ) {
+        dispatch::start_actor(self, runtime_manager, command_channel);
+    }
+
+    /// Schedule the actor on the run queue of its executor if messages were sent to it while it
+    /// was being assigned. Must be called once the executor can run the actor, i.e. after the
+    /// cell has been stored.
+    pub fn schedule_pending(&self) {
+        if self.has_pending_messages() {
+            self.schedule.force_schedule();
+        } else {
+            // An actor moved from another executor may still be marked as scheduled there
+            self.schedule.deschedule(&self.mailbox);
+        }
+    }
+
+    /// Process up to `throughput` messages of the actor. Returns `true` if the actor has messages
+    /// left, in which case it remains scheduled and the executor must run it again. Otherwise the
+    /// actor is scheduled again once a new message arrives.
+    pub fn run_turn(
+        &mut self,
+        runtime_manager: &RuntimeManagerRef,
+        command_channel: &CommandChannel<ExecutorCommands>,
+        throughput: usize,
+    ) -> bool {
+        dispatch::run_turn(self, runtime_manager, command_channel, throughput)
+    }
+
+    /// Process the next message of the actor, see [`run_turn`](Self::run_turn).
+    pub fn process_next(
+        &mut self,
+        runtime_manager: &RuntimeManagerRef,
+        command_channel: &CommandChannel<ExecutorCommands>,
+    ) -> bool {
+        self.run_turn(runtime_manager, command_channel, 1)
+    }
+
+    /// Begin stopping the actor by calling its `before_stop` hook. The runtime manager stops the
+    /// children of the actor and then sends `ShutdownActorComplete` to the executor.
+    pub fn stop(
+        &mut self,
+        runtime_manager: &RuntimeManagerRef,
+        command_channel: &CommandChannel<ExecutorCommands>,
+    ) {
+        dispatch::stop_actor(self, runtime_manager, command_channel);
+    }
+
+    /// Finish stopping the actor by calling its `after_stop` hook. Messages still queued for the
+    /// actor are sent to dead letters.
+    pub fn complete_stop(mut self, runtime_manager: &RuntimeManagerRef) {
+        dispatch::complete_stop(&mut self, runtime_manager);
+    }
+
+    /// A context of the actor for interacting with the actor system outside of the actor's own
+    /// hooks, e.g. to send messages on behalf of the actor. The sender of the context is the
+    /// system.
+    pub fn context<'a>(
+        &'a mut self,
+        runtime_manager: &'a RuntimeManagerRef,
+        command_channel: &'a CommandChannel<ExecutorCommands>,
+    ) -> Context<'a> {
+        static SYSTEM_SENDER: SenderType = SenderType::System;
+        Context {
+            address: &self.address,
+            runtime_manager,
+            executor_command_channel: command_channel,
+            parent: &self.parent,
+            children: &mut self.children,
+            next_child_id: &mut self.next_child_id,
+            sender: &SYSTEM_SENDER,
+            cell_state: &mut self.state,
+            ack_nonce: &mut self.ack_nonce,
+        }
+    }
 }
 
 /// Debug macro for serializing and deserializing a message. The goal is to reduce
@@ -146,6 +297,8 @@ macro_rules! debug_serialize_msg {
     ($msg:expr) => {
         if cfg!(debug_assertions) {
             let bytes = $msg.encode_to_vec2();
+            // Merging appends to repeated fields, so the message must be cleared first
+            $msg.clear2();
             $msg.merge2(bytes.as_slice()).unwrap();
             $msg
         } else {
@@ -153,6 +306,7 @@ macro_rules! debug_serialize_msg {
         }
     };
 }
+pub(crate) use debug_serialize_msg;
 
 /// Actor context object used for performing actions that interact with the running
 /// actor-system, such as spawning new actors and sending messages.
@@ -162,6 +316,7 @@ pub struct Context<'a> {
     pub(crate) executor_command_channel: &'a CommandChannel<ExecutorCommands>,
     pub(crate) parent: &'a Option<ActorAddress>,
     pub(crate) children: &'a mut Vec<ActorAddress>,
+    pub(crate) next_child_id: &'a mut usize,
     pub(crate) sender: &'a SenderType,
     pub(crate) cell_state: &'a mut CellState,
     pub(crate) ack_nonce: &'a mut u32,
@@ -183,15 +338,51 @@ impl Context<'_> {
         name: &str,
         init_msg: T,
     ) -> ActorSpawnHandle {
-        let address = ActorAddress::new_child(self.address, name, self.children.len());
+        self.spawn_child_with::<A, T, M>(name, init_msg, SpawnOptions::default())
+    }
+
+    /// Create a new (child) actor with [`SpawnOptions`], such as a placement strategy to use
+    /// instead of the system's. See [`spawn_child`](Self::spawn_child).
+    pub fn spawn_child_with<
+        A: ActorInit<Init = M> + Actor + 'static,
+        T: ToMessage<M>,
+        M: Message,
+    >(
+        &mut self,
+        name: &str,
+        init_msg: T,
+        options: SpawnOptions,
+    ) -> ActorSpawnHandle {
+        if let Err(e) = validate_segment(0, name) {
+            let (sender, ready_channel) = bounded(1);
+            channel_send!(sender, Err(BusanError::InvalidName(e)));
+            return ActorSpawnHandle {
+                ready_channel,
+                runtime_manager: self.runtime_manager.clone(),
+            };
+        }
+        let address = ActorAddress::new_child(self.address, name, *self.next_child_id);
+        *self.next_child_id += 1;
         self.children.push(address.clone());
         let ready_channel = self.runtime_manager.assign_actor(
             Box::new(A::init(init_msg.to_message())),
+            name,
             address,
             Some(self.address.clone()),
+            options,
         );
 
-        ActorSpawnHandle { ready_channel }
+        ActorSpawnHandle {
+            ready_channel,
+            runtime_manager: self.runtime_manager.clone(),
+        }
+    }
+
+    /// Resolve the mailbox of the address if it is not already resolved, or if the actor has
+    /// stopped since it was resolved. This is a blocking call to the runtime manager if the
+    /// address needs to be resolved. Returns `false` if the address could not be resolved.
+    pub(crate) fn resolve(&self, addr: &ActorAddress) -> bool {
+        self.runtime_manager.resolve(addr)
     }
 
     // TODO: Document
@@ -199,31 +390,104 @@ impl Context<'_> {
     pub fn send_message(
         &self,
         addr: &ActorAddress,
-        mut message: Box<dyn Message>,
+        message: Box<dyn Message>,
         ack_nonce: Option<u32>,
     ) {
-        // Validate that the address is resolved (this is a blocking call to the runtime
-        // manager if unresolved).
-        if !addr.is_resolved() {
-            trace!("Resolving address: {}", addr);
-            match self.runtime_manager.resolve_address(addr) {
-                Some(resolved) => {
-                    addr.set_mailbox(resolved);
-                }
-                _ => {
-                    todo!("Send message to dead letter queue");
-                }
-            }
-        }
+        self.send_envelope(addr, Some(self.address.clone()), message, ack_nonce);
+    }
 
-        // We should _either_ have a resolved address _OR_ the message should have been
-        // forwarded to the dead letter queue.
-        debug_assert!(addr.is_resolved(), "Address {} is not resolved", addr);
+    /// Forward a message to another actor, preserving the sender of the message currently
+    /// being processed. Replies from the receiving actor will go directly to the original
+    /// sender rather than the forwarding actor.
+    pub fn forward(&self, addr: &ActorAddress, message: Box<dyn Message>) {
+        self.send_envelope(addr, self.sender_address().cloned(), message, None);
+    }
 
+    /// Send a message to `addr` once `delay` has elapsed. The message is delivered by the
+    /// executor of the current actor and is dropped if the receiver has stopped by then.
+    pub fn send_after<M: Message + 'static, T: ToMessage<M>>(
+        &self,
+        addr: &ActorAddress,
+        message: T,
+        delay: Duration,
+    ) {
+        if !self.resolve(addr) {
+            warn!("{}: unable to schedule message for {}", self.address, addr);
+            return;
+        }
+        let mut message: Box<dyn Message> = Box::new(message.to_message());
         let message = debug_serialize_msg!(message);
+        channel_send!(
+            self.executor_command_channel,
+            ExecutorCommands::ScheduleMessage(ScheduledMessage {
+                deadline: self.now() + delay,
+                receiver: addr.clone(),
+                sender: Some(self.address.clone()),
+                payload: message,
+            })
+        );
+    }
+
+    /// Run `f` on the blocking thread pool of the system and send its result to the current actor
+    /// once it returns. Use this for synchronous work (e.g. blocking I/O or libraries without an
+    /// async API), which would otherwise block the executor and the other actors on it. The
+    /// result is sent with the actor as its sender and is dropped if the actor has stopped by
+    /// then. If `f` panics, a [`BlockingTaskFailed`] message is sent instead.
+    ///
+    /// Up to [`ExecutorConfig::blocking_threads`](crate::config::ExecutorConfig::blocking_threads)
+    /// closures run at once, further closures wait for a thread to become available.
+    /// Deterministic systems run `f` right away, on the thread driving the system.
+    pub fn spawn_blocking<M, T, F>(&self, f: F)
+    where
+        M: Message + 'static,
+        T: ToMessage<M>,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let address = self.address.clone();
+        let runtime_manager = self.runtime_manager.clone();
+        self.runtime_manager.spawn_blocking(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| f().to_message()));
+            let mut message: Box<dyn Message> = match result {
+                Ok(message) => Box::new(message),
+                Err(payload) => {
+                    let reason = panic_reason(payload.as_ref());
+                    warn!("{}: blocking task panicked: {}", address, reason);
+                    Box::new(BlockingTaskFailed { reason })
+                }
+            };
+            let message = debug_serialize_msg!(message);
+            runtime_manager.deliver(&address, Some(address.clone()), message, None);
+        });
+    }
+
+    /// Send a message on behalf of `sender`. This is [`Context::forward`] for messages that
+    /// were received earlier than the current message (e.g. queued messages).
+    pub(crate) fn send_from(
+        &self,
+        addr: &ActorAddress,
+        sender: Option<&ActorAddress>,
+        message: Box<dyn Message>,
+    ) {
+        self.send_envelope(addr, sender.cloned(), message, None);
+    }
 
-        // Send the message to the resolved address
-        addr.send(Some(self.address.clone()), message, ack_nonce);
+    /// Hand a message to the dead letter queue, as if it had been sent to `addr` by the sender
+    /// of the current message.
+    pub(crate) fn dead_letter(&self, addr: &ActorAddress, message: Box<dyn Message>) {
+        self.runtime_manager
+            .dead_letter(addr, self.sender_address().cloned(), message);
+    }
+
+    fn send_envelope(
+        &self,
+        addr: &ActorAddress,
+        sender: Option<ActorAddress>,
+        mut message: Box<dyn Message>,
+        ack_nonce: Option<u32>,
+    ) {
+        let message = debug_serialize_msg!(message);
+        self.runtime_manager
+            .deliver(addr, sender, message, ack_nonce);
     }
 
     // TODO: Document
@@ -266,7 +530,18 @@ impl Context<'_> {
         }
     }
 
-    /// Return the addresses for all children of the current actor
+    /// Get the sender of the current message, or `None` for system messages.
+    pub(crate) fn sender_address(&self) -> Option<&'_ ActorAddress> {
+        match self.sender {
+            SenderType::Actor(sender_address) => Some(sender_address),
+            SenderType::Parent => self.parent.as_ref(),
+            SenderType::SentToSelf => Some(self.address),
+            SenderType::System => None,
+        }
+    }
+
+    /// Return the addresses for all children of the current actor. Children are removed once
+    /// they have stopped (or failed to start).
     pub fn children(&self) -> &[ActorAddress] {
         self.children
     }
@@ -281,6 +556,72 @@ impl Context<'_> {
         self.address
     }
 
+    /// The current time of the actor system. This is `Instant::now()`, except for deterministic
+    /// systems (see `ExecutorType::Deterministic`), whose time is virtual. Actors that measure
+    /// time should use it rather than `Instant::now()` to behave the same in both.
+    pub fn now(&self) -> Instant {
+        self.runtime_manager.now()
+    }
+
+    /// A random number generator, which is seeded from the seed of deterministic systems.
+    pub(crate) fn rng(&self) -> Rng {
+        self.runtime_manager.rng()
+    }
+
+    /// Select the running actors matching a path, which may contain wildcards. Relative paths
+    /// (e.g. `../worker-*`) are resolved against the address of the current actor, while
+    /// absolute paths (e.g. `local://root/workers/*`) are used as-is. See [`ActorSelection`]
+    /// for the supported path syntax.
+    pub fn select(&self, path: &str) -> Result<ActorSelection, BusanError> {
+        let pattern = PathPattern::parse_relative(&self.address.uri, path)?;
+        Ok(ActorSelection::new(pattern, self.runtime_manager.clone()))
+    }
+
+    /// Watch another actor for termination. Once the watched actor has stopped, a
+    /// [`Terminated`](crate::message::system::Terminated) message is delivered to the current
+    /// actor. If the actor has already stopped (or does not exist), the message is delivered
+    /// immediately.
+    pub fn watch(&self, addr: &ActorAddress) {
+        self.runtime_manager.watch(self.address, addr);
+    }
+
+    /// Stop watching an actor previously watched with [`Context::watch`].
+    pub fn unwatch(&self, addr: &ActorAddress) {
+        self.runtime_manager.unwatch(self.address, addr);
+    }
+
+    /// Register an actor with the receptionist under `key`. The registration is removed once
+    /// the actor stops. See [`ServiceKey`] for details.
+    pub fn register_service<M: Message>(&self, key: &ServiceKey<M>, addr: &ActorAddress) {
+        self.runtime_manager.register_service(&key.service(), addr);
+    }
+
+    /// Remove a registration previously made with [`Context::register_service`].
+    pub fn deregister_service<M: Message>(&self, key: &ServiceKey<M>, addr: &ActorAddress) {
+        self.runtime_manager
+            .deregister_service(&key.service(), addr);
+    }
+
+    /// Return the addresses of the running actors registered under `key`, in order of
+    /// registration. This is a blocking call to the runtime manager.
+    pub fn find_service<M: Message>(&self, key: &ServiceKey<M>) -> Vec<ActorAddress> {
+        self.runtime_manager.find_service(&key.service())
+    }
+
+    /// Subscribe the current actor to the actors registered under `key`. A
+    /// [`Listing`](crate::message::system::Listing) message is delivered right away and again
+    /// whenever actors register, deregister or stop.
+    pub fn subscribe_service<M: Message>(&self, key: &ServiceKey<M>) {
+        self.runtime_manager
+            .subscribe_service(&key.service(), self.address);
+    }
+
+    /// Stop receiving updates for a service subscribed to with [`Context::subscribe_service`].
+    pub fn unsubscribe_service<M: Message>(&self, key: &ServiceKey<M>) {
+        self.runtime_manager
+            .unsubscribe_service(&key.service(), self.address);
+    }
+
     /// Perform immediate shutdown for the current actor.
     pub fn shutdown(&mut self) {
         cell_state::set_shutdown(self.cell_state);
@@ -291,8 +632,39 @@ impl Context<'_> {
     }
 }
 
+/// Options for spawning an actor, used with [`Context::spawn_child_with`] and
+/// [`SystemHandle::spawn_actor_with`](crate::SystemHandle::spawn_actor_with).
+#[derive(Default)]
+pub struct SpawnOptions {
+    pub(crate) placement: Option<Box<dyn PlacementStrategy>>,
+    pub(crate) group: Option<String>,
+}
+
+impl SpawnOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Place the actor with `strategy` instead of the system's placement strategy. The strategy
+    /// is only used for this spawn, so stateful strategies (such as
+    /// [`RoundRobin`](crate::executor::placement::RoundRobin)) start from their initial state.
+    pub fn with_placement<S: PlacementStrategy + 'static>(mut self, strategy: S) -> Self {
+        self.placement = Some(Box::new(strategy));
+        self
+    }
+
+    /// Spawn the actor in the executor group named `group` (see
+    /// [`ExecutorConfig::groups`](crate::config::ExecutorConfig::groups)) instead of the default
+    /// group. Spawning fails if there is no such group.
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
+    }
+}
+
 pub struct ActorSpawnHandle {
     ready_channel: Receiver<Result<ActorAddress, BusanError>>,
+    runtime_manager: RuntimeManagerRef,
 }
 
 /// Handle used to await actor assignment upon initial creation (spawn).
@@ -318,23 +690,21 @@ impl ActorSpawnHandle {
     pub fn await_ready(&self) -> Result<ActorAddress, BusanError> {
         // TODO: A reasonable wait timeout here would be good, probably something
         // that could be defined is a global/system-level config.
-        match self.ready_channel.recv() {
-            Ok(res) => res,
-            Err(e) => Err(BusanError::UnassignableActor(format!(
-                "Internal Error: {}",
-                e
-            ))),
+        match self.runtime_manager.await_reply(&self.ready_channel) {
+            Some(res) => res,
+            None => Err(BusanError::UnassignableActor(
+                "Internal Error: runtime manager stopped".to_string(),
+            )),
         }
     }
 
     /// Blocks on actor assignment and discards the error. Equivalent to
     /// `spawn_handle.await_ready().unwrap()`
     pub fn await_unwrap(&self) -> ActorAddress {
-        let res = self.await_ready();
         // If there is an error, panic with a descriptive message of what went wrong
-        if let Err(e) = res {
-            panic!("Failed while waiting for actor spawn: {}", e);
+        match self.await_ready() {
+            Ok(address) => address,
+            Err(e) => panic!("Failed while waiting for actor spawn: {}", e),
         }
-        self.await_ready().unwrap()
     }
 }
@@ -2,23 +2,48 @@ use crate::actor::proto::Scheme;
 use crate::actor::{Envelope, Mailbox};
 use crate::message::Message;
 use log::trace;
-use std::cell::RefCell;
+use std::error::Error;
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-/// Handle representing the address of an actor
+/// Source of incarnation IDs. IDs start at 1, since 0 marks an address with an unknown
+/// incarnation.
+static NEXT_INCARNATION: AtomicU64 = AtomicU64::new(1);
+
+/// Handle representing the address of an actor.
+///
+/// Addresses are `Send + Sync`, so they can be shared between threads (e.g. in an `Arc` or in
+/// global state) and used from outside of the actor system.
 #[derive(Debug)]
 pub struct ActorAddress {
     pub(crate) uri: Uri,
 
-    /// `mailbox` is a `Sender` channel used for transmitting messages to the actor and is
-    /// is implemented as a `RefCell` for interior mutability. This allows for addresses creation
-    /// to be decoupled from resolution of the mailbox.
-    pub(crate) mailbox: RefCell<Option<Mailbox>>,
+    /// Unique ID of the actor instance the address refers to. While the path of an actor
+    /// identifies its position in the actor hierarchy, the incarnation distinguishes between
+    /// actors that (at different times) are spawned at the same path. A value of 0 refers to
+    /// whichever actor is currently running at the path (e.g. for parsed addresses without an
+    /// incarnation).
+    pub(crate) incarnation: u64,
+
+    /// The resolved mailbox of the actor, if any. The slot is behind a lock (rather than a
+    /// `RefCell`) so that the address can be resolved lazily while still being shared across
+    /// threads. Resolution is decoupled from address creation. The mailbox is closed once the
+    /// actor stops, which invalidates the resolution of every address (including clones) resolved
+    /// to it. See [`Context::resolve`](crate::actor::Context::resolve).
+    resolution: RwLock<Option<Mailbox>>,
 }
 
+/// Addresses are displayed as their [`Uri`] followed by the incarnation, such as
+/// `local://root/child-1#12`. The incarnation is omitted if it is unknown.
 impl Display for ActorAddress {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.uri)
+        write!(f, "{}", self.uri)?;
+        if self.incarnation != 0 {
+            write!(f, "#{}", self.incarnation)?;
+        }
+        Ok(())
     }
 }
 
@@ -26,7 +51,8 @@ impl Clone for ActorAddress {
     fn clone(&self) -> Self {
         Self {
             uri: self.uri.clone(),
-            mailbox: RefCell::new(self.mailbox.borrow().clone()),
+            incarnation: self.incarnation,
+            resolution: RwLock::new(self.resolution().clone()),
         }
     }
 }
@@ -37,34 +63,108 @@ impl ActorAddress {
         self.uri.clone()
     }
 
-    pub(crate) fn new_child(parent: &ActorAddress, name: &str, id: usize) -> Self {
+    /// Get the incarnation ID of the actor the address refers to, or 0 if it is unknown.
+    pub fn incarnation(&self) -> u64 {
+        self.incarnation
+    }
+
+    /// Create an unresolved address for the actor at `uri` with the given `incarnation`.
+    pub(crate) fn new(uri: Uri, incarnation: u64) -> Self {
         Self {
-            uri: parent.uri.new_child(&format!("{}-{}", name, id)),
-            mailbox: RefCell::new(None),
+            uri,
+            incarnation,
+            resolution: RwLock::new(None),
         }
     }
 
+    pub(crate) fn new_child(parent: &ActorAddress, name: &str, id: usize) -> Self {
+        Self::new(
+            parent.uri.new_child(&format!("{}-{}", name, id)),
+            next_incarnation(),
+        )
+    }
+
     pub(crate) fn new_root(name: &str) -> Self {
+        Self::new(Uri::new(Scheme::Local, &[name]), next_incarnation())
+    }
+
+    /// Create an address for an entity created by the actor system itself, under the reserved
+    /// `system` path (e.g. `local://system/inbox-12`).
+    pub(crate) fn new_system(name: &str) -> Self {
+        let incarnation = next_incarnation();
+        Self::new(
+            Uri::new(
+                Scheme::Local,
+                &["system", &format!("{}-{}", name, incarnation)],
+            ),
+            incarnation,
+        )
+    }
+
+    /// Create an address that has been resolved to `mailbox`.
+    pub(crate) fn new_resolved(uri: Uri, incarnation: u64, mailbox: Mailbox) -> Self {
         Self {
-            uri: Uri::new(Scheme::Local, &[name]),
-            mailbox: RefCell::new(None),
+            uri,
+            incarnation,
+            resolution: RwLock::new(Some(mailbox)),
         }
     }
 
+    /// Returns true if the address may refer to the actor with the given `incarnation`, which
+    /// is the case if the incarnations match or the address' incarnation is unknown.
+    pub(crate) fn matches_incarnation(&self, incarnation: u64) -> bool {
+        self.incarnation == 0 || self.incarnation == incarnation
+    }
+
+    // The lock is never held while calling into user code, so a poisoned lock still holds a
+    // consistent value.
+    fn resolution(&self) -> RwLockReadGuard<'_, Option<Mailbox>> {
+        self.resolution
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn resolution_mut(&self) -> RwLockWriteGuard<'_, Option<Mailbox>> {
+        self.resolution
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
     pub(crate) fn set_mailbox(&self, mailbox: Mailbox) {
-        *self.mailbox.borrow_mut() = Some(mailbox);
+        *self.resolution_mut() = Some(mailbox);
     }
 
-    pub(crate) fn is_resolved(&self) -> bool {
-        self.mailbox.borrow().is_some()
+    /// Forget the resolved mailbox, e.g. once the actor is known to have stopped.
+    pub(crate) fn clear_mailbox(&self) {
+        *self.resolution_mut() = None;
     }
 
+    /// Returns true if the address has been resolved and the resolution is still valid (i.e. the
+    /// actor has not stopped since).
+    pub(crate) fn is_resolved_open(&self) -> bool {
+        matches!(self.resolution().as_ref(), Some(mailbox) if !mailbox.is_closed())
+    }
+
+    /// The resolved mailbox of the actor, if any.
+    pub(crate) fn mailbox(&self) -> Option<Mailbox> {
+        self.resolution().clone()
+    }
+
+    /// The number of messages queued in the actor's mailbox, or `None` if the address has
+    /// not been resolved.
+    pub(crate) fn mailbox_len(&self) -> Option<usize> {
+        self.resolution().as_ref().map(Mailbox::len)
+    }
+
+    /// Send a message to the resolved mailbox. If the address is not (or no longer) resolved, or
+    /// the actor has stopped (and the mailbox is closed), the sender and message are returned so
+    /// they can be handed to the dead letter queue.
     pub(crate) fn send(
         &self,
         from: Option<Self>,
         message: Box<dyn Message>,
         ack_nonce: Option<u32>,
-    ) {
+    ) -> Result<(), (Option<Self>, Box<dyn Message>)> {
         trace!(
             "[{}] Sending message to {}",
             from.as_ref()
@@ -73,12 +173,17 @@ impl ActorAddress {
             self
         );
 
-        let envelope = Envelope::new(from, self, message, ack_nonce);
-        let result = (self.mailbox.borrow().as_ref().unwrap()).send(envelope);
-        // TODO: Handle a non-OK error (once actor shutdown is implemented) On error, should
-        //       redirect to the dead letter queue. This function may simply return an error
-        //       so that the caller can do the redirection.
-        debug_assert!(result.is_ok(), "Error sending to actor address {}", self);
+        // The resolution may be cleared by another thread sharing the address at any time, so it
+        // is checked and used under a single guard.
+        let resolution = self.resolution();
+        let mailbox = match resolution.as_ref() {
+            Some(mailbox) => mailbox,
+            None => return Err((from, message)),
+        };
+        let envelope = Envelope::new(from.clone(), self, message, ack_nonce);
+        mailbox
+            .send(envelope)
+            .map_err(|e| (from, e.into_inner().payload))
     }
 
     pub(crate) fn is_parent(&self, maybe_parent: &ActorAddress) -> bool {
@@ -86,6 +191,28 @@ impl ActorAddress {
     }
 }
 
+fn next_incarnation() -> u64 {
+    NEXT_INCARNATION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Parse an (unresolved) address from a [`Uri`] string, optionally followed by an incarnation
+/// (as displayed), such as `local://root/child-1` or `local://root/child-1#12`. See [`Uri`] for
+/// the accepted grammar.
+impl FromStr for ActorAddress {
+    type Err = UriParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (uri, incarnation) = match s.rsplit_once('#') {
+            Some((uri, incarnation)) => match incarnation.parse() {
+                Ok(incarnation) if incarnation != 0 => (uri, incarnation),
+                _ => return Err(UriParseError::InvalidIncarnation(incarnation.to_string())),
+            },
+            None => (s, 0),
+        };
+        Ok(Self::new(uri.parse()?, incarnation))
+    }
+}
+
 /// `Uri` is a URI-like type that identifies an actor system and an actor within that system.
 /// The hierarchical nature, or tree-like, organization of actors is also present in URIs, with
 /// children and parents readily identifiable by path. Take for example the following hierarchy
@@ -104,6 +231,31 @@ impl ActorAddress {
 ///     ├── database                 local://geoip_updater/download_manager/publisher/database
 ///     └── event_emitter            local://geoip_updater/download_manager/publisher/event_emitter
 /// ```
+///
+/// ## Parsing
+///
+/// A `Uri` can be parsed from its `Display` form (and parsing a displayed `Uri` returns the same
+/// value). The grammar is strict:
+///
+///   + The scheme must be `local` or `remote`, followed by `://`
+///   + There must be at least one path segment and segments may not be empty
+///   + Segments may only contain ASCII letters, digits, `-`, `_` and `.`, and may not be `.` or
+///     `..`
+///
+/// The names in [`RESERVED_NAMES`] are reserved for actors created by the actor system itself.
+/// They are accepted by the parser (so that addresses of system actors can be parsed), but may
+/// not be used to name root actors.
+///
+/// ```rust
+/// # use busan::actor::{Uri, UriParseError};
+/// let uri: Uri = "local://root/worker-0".parse().unwrap();
+/// assert_eq!(uri.to_string(), "local://root/worker-0");
+///
+/// assert_eq!(
+///     "local://root//worker-0".parse::<Uri>(),
+///     Err(UriParseError::EmptySegment { position: 1 }),
+/// );
+/// ```
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Uri {
     pub(crate) scheme: Scheme,
@@ -159,8 +311,121 @@ impl Uri {
     pub(crate) fn path(&self) -> String {
         self.path_segments.join("/")
     }
+
+    /// Construct a `Uri` from a scheme and the path component (as returned by [`Uri::path`]),
+    /// validating each of the path segments.
+    pub(crate) fn from_path(scheme: Scheme, path: &str) -> Result<Self, UriParseError> {
+        if path.is_empty() {
+            return Err(UriParseError::EmptyPath);
+        }
+        let path_segments = path
+            .split('/')
+            .enumerate()
+            .map(|(position, segment)| validate_segment(position, segment).map(String::from))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            scheme,
+            path_segments,
+        })
+    }
+}
+
+/// Names reserved for actors created by the actor system. See [`Uri`] for details.
+pub const RESERVED_NAMES: [&str; 2] = ["system", "user"];
+
+/// Validate a user-provided actor name, which must be a valid path segment and must not be one
+/// of the [`RESERVED_NAMES`].
+pub(crate) fn validate_name(name: &str) -> Result<(), UriParseError> {
+    validate_segment(0, name)?;
+    if RESERVED_NAMES.contains(&name) {
+        return Err(UriParseError::ReservedName(name.to_string()));
+    }
+    Ok(())
 }
 
+pub(crate) fn validate_segment(position: usize, segment: &str) -> Result<&str, UriParseError> {
+    match segment {
+        "" => Err(UriParseError::EmptySegment { position }),
+        "." | ".." => Err(UriParseError::RelativeSegment { position }),
+        _ => match segment
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        {
+            Some(character) => Err(UriParseError::InvalidCharacter {
+                position,
+                character,
+            }),
+            None => Ok(segment),
+        },
+    }
+}
+
+impl FromStr for Uri {
+    type Err = UriParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (scheme, path) = s.split_once("://").ok_or(UriParseError::MissingScheme)?;
+        let scheme = match scheme {
+            "local" => Scheme::Local,
+            "remote" => Scheme::Remote,
+            other => return Err(UriParseError::UnknownScheme(other.to_string())),
+        };
+        Self::from_path(scheme, path)
+    }
+}
+
+/// Error returned when parsing a [`Uri`] (or [`ActorAddress`]) fails, or when an actor name is
+/// not valid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UriParseError {
+    /// The string does not start with a scheme followed by `://`
+    MissingScheme,
+    /// The scheme is not one of `local` or `remote`
+    UnknownScheme(String),
+    /// There are no path segments after the scheme
+    EmptyPath,
+    /// The path segment at `position` (starting from 0) is empty
+    EmptySegment { position: usize },
+    /// The path segment at `position` is `.` or `..`
+    RelativeSegment { position: usize },
+    /// The path segment at `position` contains a character that is not allowed
+    InvalidCharacter { position: usize, character: char },
+    /// The name is reserved for actors created by the actor system
+    ReservedName(String),
+    /// The incarnation following `#` in an address is not a positive integer
+    InvalidIncarnation(String),
+}
+
+impl Display for UriParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UriParseError::MissingScheme => write!(f, "missing scheme (e.g. \"local://\")"),
+            UriParseError::UnknownScheme(scheme) => write!(f, "unknown scheme \"{}\"", scheme),
+            UriParseError::EmptyPath => write!(f, "path is empty"),
+            UriParseError::EmptySegment { position } => {
+                write!(f, "path segment {} is empty", position)
+            }
+            UriParseError::RelativeSegment { position } => {
+                write!(f, "path segment {} is a relative segment", position)
+            }
+            UriParseError::InvalidCharacter {
+                position,
+                character,
+            } => write!(
+                f,
+                "path segment {} contains invalid character {:?}",
+                position, character
+            ),
+            UriParseError::ReservedName(name) => write!(f, "\"{}\" is a reserved name", name),
+            UriParseError::InvalidIncarnation(incarnation) => {
+                write!(f, "invalid incarnation \"{}\"", incarnation)
+            }
+        }
+    }
+}
+
+impl Error for UriParseError {}
+
 impl Display for Uri {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self.scheme {
@@ -174,6 +439,8 @@ impl Display for Uri {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::message::common_types::StringWrapper;
+    use proptest::prelude::*;
 
     #[test]
     #[should_panic]
@@ -262,6 +529,154 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_errors() {
+        let test_cases = vec![
+            ("root/child", UriParseError::MissingScheme),
+            (
+                "http://root",
+                UriParseError::UnknownScheme("http".to_string()),
+            ),
+            ("local://", UriParseError::EmptyPath),
+            ("local://root/", UriParseError::EmptySegment { position: 1 }),
+            ("local:///root", UriParseError::EmptySegment { position: 0 }),
+            (
+                "local://root/../a",
+                UriParseError::RelativeSegment { position: 1 },
+            ),
+            (
+                "local://root/a b",
+                UriParseError::InvalidCharacter {
+                    position: 1,
+                    character: ' ',
+                },
+            ),
+            (
+                "local://root/worker-*",
+                UriParseError::InvalidCharacter {
+                    position: 1,
+                    character: '*',
+                },
+            ),
+        ];
+        for (input, expected) in test_cases {
+            assert_eq!(input.parse::<Uri>(), Err(expected), "parsing {}", input);
+        }
+
+        // Reserved names are only rejected as actor names
+        assert!("local://system/ask-0".parse::<Uri>().is_ok());
+        assert_eq!(
+            validate_name("system"),
+            Err(UriParseError::ReservedName("system".to_string()))
+        );
+        assert!(validate_name("system-actor").is_ok());
+    }
+
+    proptest! {
+        #[test]
+        fn test_display_round_trip(
+            scheme in prop_oneof![Just(Scheme::Local), Just(Scheme::Remote)],
+            segments in prop::collection::vec("[a-zA-Z0-9_-][a-zA-Z0-9_.-]{0,15}", 1..6),
+        ) {
+            let uri = Uri { scheme, path_segments: segments };
+            prop_assert_eq!(uri.to_string().parse::<Uri>(), Ok(uri.clone()));
+            let address: ActorAddress = uri.to_string().parse().unwrap();
+            prop_assert_eq!(&address.uri, &uri);
+            prop_assert_eq!(address.incarnation, 0);
+        }
+
+        #[test]
+        fn test_address_display_round_trip(incarnation in 0u64.., path in "[a-z]{1,8}(/[a-z0-9-]{1,8}){0,4}") {
+            let address = ActorAddress::new(Uri::from_path(Scheme::Local, &path).unwrap(), incarnation);
+            let parsed: ActorAddress = address.to_string().parse().unwrap();
+            prop_assert_eq!(parsed.uri, address.uri);
+            prop_assert_eq!(parsed.incarnation, address.incarnation);
+        }
+
+        #[test]
+        fn test_parse_arbitrary(input in "(local|remote|http)?(://)?[a-z0-9./* -]{0,12}") {
+            // Parsing never panics and anything that parses displays as the original input
+            if let Ok(uri) = input.parse::<Uri>() {
+                prop_assert_eq!(uri.to_string(), input);
+            }
+        }
+    }
+
+    #[test]
+    fn test_address_incarnation() {
+        let root = ActorAddress::new_root("root");
+        let first = ActorAddress::new_child(&root, "child", 0);
+        let second = ActorAddress::new_child(&root, "child", 0);
+        assert_eq!(first.uri, second.uri);
+        assert_ne!(first.incarnation, second.incarnation);
+        assert!(!first.matches_incarnation(second.incarnation));
+
+        let parsed: ActorAddress = first.uri.to_string().parse().unwrap();
+        assert!(parsed.matches_incarnation(second.incarnation));
+
+        for invalid in [
+            "local://root#",
+            "local://root#0",
+            "local://root#-1",
+            "local://root#a",
+        ] {
+            assert!(matches!(
+                invalid.parse::<ActorAddress>(),
+                Err(UriParseError::InvalidIncarnation(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_resolution_closed() {
+        let (sender, _receiver) = Mailbox::new(0, None);
+        let (other_sender, _other_receiver) = Mailbox::new(0, None);
+        let address = ActorAddress::new_root("root");
+        let other = ActorAddress::new_root("other");
+        assert!(!address.is_resolved_open());
+
+        address.set_mailbox(sender.clone());
+        other.set_mailbox(other_sender);
+        let clone = address.clone();
+        assert!(clone.is_resolved_open());
+
+        // Closing the mailbox invalidates the resolution, including for clones, but not for
+        // addresses of other actors
+        sender.close();
+        assert!(!address.is_resolved_open());
+        assert!(!clone.is_resolved_open());
+        assert!(other.is_resolved_open());
+        assert!(address
+            .send(None, Box::new(StringWrapper::default()), None)
+            .is_err());
+
+        // Sending through a cleared (or never resolved) address fails rather than panicking
+        other.clear_mailbox();
+        assert!(!other.is_resolved_open());
+        assert!(other
+            .send(None, Box::new(StringWrapper::default()), None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_address_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ActorAddress>();
+
+        // A shared address can be resolved on one thread and used on another
+        let (sender, receiver) = Mailbox::new(0, None);
+        let address = std::sync::Arc::new(ActorAddress::new_root("root"));
+        let shared = address.clone();
+        std::thread::spawn(move || shared.set_mailbox(sender))
+            .join()
+            .unwrap();
+        assert!(address.is_resolved_open());
+        assert!(address
+            .send(None, Box::new(StringWrapper::default()), None)
+            .is_ok());
+        assert_eq!(receiver.len(), 1);
+    }
+
     #[test]
     fn test_address_parent_detection() {
         let root = ActorAddress::new_root("root");
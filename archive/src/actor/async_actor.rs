@@ -0,0 +1,102 @@
+use crate::actor::{Actor, Context};
+use crate::message::Message;
+use log::error;
+use std::future::Future;
+use std::pin::Pin;
+
+/// An actor whose [`receive`](AsyncActor::receive) (and [`before_start`](AsyncActor::before_start))
+/// are asynchronous, so they can `await` other futures (e.g. async I/O from other libraries).
+///
+/// Async actors are run by executors of type
+/// [`ExecutorType::Tokio`](crate::config::ExecutorType::Tokio), within a tokio runtime. Like any
+/// other actor, an async actor processes one message at a time: the next message is not received
+/// until the future returned by `receive` has completed, and messages that arrive in the meantime
+/// are queued in its mailbox.
+///
+/// Every `AsyncActor` is also an [`Actor`], so async actors are spawned (and addressed) like any
+/// other actor and can exchange messages with synchronous actors. They can only be spawned in an
+/// executor group (see [`SpawnOptions::with_group`](crate::actor::SpawnOptions::with_group)) of
+/// tokio executors though.
+///
+/// ```rust,no_run
+/// # use busan::actor::{AsyncActor, ActorInit, Context};
+/// # use busan::message::Message;
+/// # use busan::message::common_types::StringWrapper;
+/// struct Fetcher;
+/// # impl ActorInit for Fetcher {
+/// #     type Init = StringWrapper;
+/// #     fn init(_init_msg: StringWrapper) -> Self { Fetcher }
+/// # }
+///
+/// impl AsyncActor for Fetcher {
+///     async fn receive(&mut self, ctx: Context<'_>, msg: Box<dyn Message>) {
+///         tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+///         ctx.send_message(ctx.sender(), msg, None);
+///     }
+/// }
+/// ```
+pub trait AsyncActor: Send {
+    /// Asynchronous version of [`Actor::before_start`]. No messages are received until the
+    /// returned future has completed.
+    fn before_start<'a>(&'a mut self, _ctx: Context<'a>) -> impl Future<Output = ()> + Send + 'a {
+        async {}
+    }
+
+    /// Receive a message. The next message is received once the returned future has completed.
+    fn receive<'a>(
+        &'a mut self,
+        ctx: Context<'a>,
+        msg: Box<dyn Message>,
+    ) -> impl Future<Output = ()> + Send + 'a;
+
+    /// See [`Actor::before_stop`].
+    fn before_stop(&mut self, _ctx: Context) {}
+
+    /// See [`Actor::after_stop`].
+    fn after_stop(&mut self) {}
+}
+
+type BoxFuture<'a> = Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+
+/// Object-safe version of [`AsyncActor`], used by executors to run async actors through their
+/// `Box<dyn Actor>` (see `Actor::as_async`).
+#[doc(hidden)]
+pub trait DynAsyncActor: Send {
+    fn before_start_boxed<'a>(&'a mut self, ctx: Context<'a>) -> BoxFuture<'a>;
+
+    fn receive_boxed<'a>(&'a mut self, ctx: Context<'a>, msg: Box<dyn Message>) -> BoxFuture<'a>;
+}
+
+impl<A: AsyncActor> DynAsyncActor for A {
+    fn before_start_boxed<'a>(&'a mut self, ctx: Context<'a>) -> BoxFuture<'a> {
+        Box::pin(AsyncActor::before_start(self, ctx))
+    }
+
+    fn receive_boxed<'a>(&'a mut self, ctx: Context<'a>, msg: Box<dyn Message>) -> BoxFuture<'a> {
+        Box::pin(AsyncActor::receive(self, ctx, msg))
+    }
+}
+
+impl<A: AsyncActor> Actor for A {
+    // Executors that run async actors call `before_start_boxed` and `receive_boxed` instead.
+    // Async actors are never assigned to other executors, so these are never called.
+    fn before_start(&mut self, ctx: Context) {
+        error!("{}: async actor not run by an async executor", ctx.address);
+    }
+
+    fn receive(&mut self, ctx: Context, _msg: Box<dyn Message>) {
+        error!("{}: async actor not run by an async executor", ctx.address);
+    }
+
+    fn before_stop(&mut self, ctx: Context) {
+        AsyncActor::before_stop(self, ctx)
+    }
+
+    fn after_stop(&mut self) {
+        AsyncActor::after_stop(self)
+    }
+
+    fn as_async(&mut self) -> Option<&mut dyn DynAsyncActor> {
+        Some(self)
+    }
+}
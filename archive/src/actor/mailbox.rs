@@ -0,0 +1,216 @@
+use crate::actor::Envelope;
+use crate::executor::RunQueue;
+use crossbeam_channel::{Receiver, SendError, Sender};
+use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard};
+
+/// The sending side of an actor's mailbox. Enqueueing a message schedules the actor on its
+/// executor (see [`Schedule`]), so executors only visit actors that have pending messages.
+#[derive(Clone, Debug)]
+pub(crate) struct Mailbox {
+    sender: Sender<Envelope>,
+    schedule: Arc<Schedule>,
+}
+
+impl Mailbox {
+    /// Create the mailbox of the actor with incarnation `id`, whose messages are received on the
+    /// returned `Receiver`. `run_queue` is the run queue of the executor running the actor, or
+    /// `None` if messages are not processed by an executor (e.g. for an inbox).
+    pub(crate) fn new(id: u64, run_queue: Option<Arc<dyn RunQueue>>) -> (Self, Receiver<Envelope>) {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let schedule = Arc::new(Schedule {
+            id,
+            scheduled: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+            run_queue: RwLock::new(run_queue),
+        });
+        (Self { sender, schedule }, receiver)
+    }
+
+    /// Enqueue a message. Fails if the mailbox has been closed or the actor dropped.
+    pub(crate) fn send(&self, envelope: Envelope) -> Result<(), SendError<Envelope>> {
+        if self.is_closed() {
+            return Err(SendError(envelope));
+        }
+        self.sender.send(envelope)?;
+        self.schedule.schedule();
+        Ok(())
+    }
+
+    /// The number of queued messages
+    pub(crate) fn len(&self) -> usize {
+        self.sender.len()
+    }
+
+    pub(crate) fn schedule(&self) -> Arc<Schedule> {
+        self.schedule.clone()
+    }
+
+    /// Stop accepting messages, once the actor has stopped. Closing is shared by all clones of
+    /// the mailbox, so addresses resolved to it are re-resolved on their next use.
+    pub(crate) fn close(&self) {
+        self.schedule.closed.store(true, Ordering::Release);
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.schedule.closed.load(Ordering::Acquire)
+    }
+}
+
+/// Scheduling state of an actor, shared between its mailbox and its executor. An actor with
+/// pending messages is scheduled on the [`RunQueue`] of its executor. The `scheduled` flag
+/// ensures an actor is only queued once, no matter how many messages are sent to it, and stays
+/// set while the actor runs, so an actor is never run by two threads at once.
+pub(crate) struct Schedule {
+    id: u64,
+    scheduled: AtomicBool,
+    /// Set once the actor has stopped (see [`Mailbox::close`])
+    closed: AtomicBool,
+    /// Replaced when the actor is moved to another executor
+    run_queue: RwLock<Option<Arc<dyn RunQueue>>>,
+}
+
+impl Schedule {
+    /// Schedule the actor on its executor, unless it is already scheduled.
+    pub(crate) fn schedule(&self) {
+        if let Some(run_queue) = &*self.run_queue() {
+            if !self.scheduled.swap(true, Ordering::SeqCst) {
+                run_queue.schedule(self.id);
+            }
+        }
+    }
+
+    /// Queue the actor on its executor even if it is already marked as scheduled. Used once an
+    /// actor is assigned to its executor, as messages sent before that could mark the actor as
+    /// scheduled without it being queued.
+    pub(crate) fn force_schedule(&self) {
+        if let Some(run_queue) = &*self.run_queue() {
+            self.scheduled.store(true, Ordering::SeqCst);
+            run_queue.schedule(self.id);
+        }
+    }
+
+    /// Schedule the actor on `run_queue` from now on, when the actor is moved to another
+    /// executor. The actor is assigned to the executor afterwards, which schedules it if needed.
+    pub(crate) fn set_run_queue(&self, run_queue: Arc<dyn RunQueue>) {
+        *self
+            .run_queue
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(run_queue);
+    }
+
+    fn run_queue(&self) -> RwLockReadGuard<'_, Option<Arc<dyn RunQueue>>> {
+        self.run_queue
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Deschedule the actor after its executor has emptied the mailbox (`receiver`). Messages
+    /// enqueued after the mailbox was found empty, but before the flag was cleared, did not
+    /// schedule the actor, so the mailbox is checked once more after clearing the flag.
+    pub(crate) fn deschedule(&self, receiver: &Receiver<Envelope>) {
+        self.scheduled.store(false, Ordering::SeqCst);
+        if !receiver.is_empty() {
+            self.schedule();
+        }
+    }
+}
+
+impl Debug for Schedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Schedule")
+            .field("id", &self.id)
+            .field("scheduled", &self.scheduled)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::ActorAddress;
+    use crate::message::common_types::StringWrapper;
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Duration;
+
+    /// Records the actors scheduled on it
+    #[derive(Default)]
+    struct RecordingRunQueue {
+        scheduled: Mutex<Vec<u64>>,
+    }
+
+    impl RunQueue for RecordingRunQueue {
+        fn schedule(&self, id: u64) {
+            self.scheduled.lock().unwrap().push(id);
+        }
+    }
+
+    impl RunQueue for crossbeam_channel::Sender<u64> {
+        fn schedule(&self, id: u64) {
+            self.send(id).unwrap();
+        }
+    }
+
+    fn envelope(address: &ActorAddress) -> Envelope {
+        Envelope::new(None, address, Box::new(StringWrapper::default()), None)
+    }
+
+    #[test]
+    fn test_send_during_deschedule() {
+        let run_queue = Arc::new(RecordingRunQueue::default());
+        let scheduled = || run_queue.scheduled.lock().unwrap().len();
+        let address = ActorAddress::new_root("actor");
+        let (mailbox, receiver) = Mailbox::new(7, Some(run_queue.clone()));
+
+        // The actor is only scheduled once, no matter how many messages are sent
+        mailbox.send(envelope(&address)).unwrap();
+        mailbox.send(envelope(&address)).unwrap();
+        assert_eq!(scheduled(), 1);
+
+        // A message sent after the executor emptied the mailbox, but before the actor was
+        // descheduled, finds the actor still scheduled...
+        while receiver.try_recv().is_ok() {}
+        mailbox.send(envelope(&address)).unwrap();
+        assert_eq!(scheduled(), 1);
+        // ...so descheduling schedules it once more
+        mailbox.schedule().deschedule(&receiver);
+        assert_eq!(scheduled(), 2);
+
+        // Once descheduled with an empty mailbox, the next message schedules the actor
+        receiver.try_recv().unwrap();
+        mailbox.schedule().deschedule(&receiver);
+        assert_eq!(scheduled(), 2);
+        mailbox.send(envelope(&address)).unwrap();
+        assert_eq!(*run_queue.scheduled.lock().unwrap(), vec![7, 7, 7]);
+    }
+
+    #[test]
+    fn test_no_lost_messages() {
+        const MESSAGES: usize = 10_000;
+        let (run_queue, runnable) = crossbeam_channel::unbounded::<u64>();
+        let (mailbox, receiver) = Mailbox::new(1, Some(Arc::new(run_queue)));
+        let schedule = mailbox.schedule();
+        let sender = thread::spawn(move || {
+            let address = ActorAddress::new_root("actor");
+            for _ in 0..MESSAGES {
+                mailbox.send(envelope(&address)).unwrap();
+            }
+        });
+
+        // Run the actor like an executor would, until all messages have been received. A
+        // message that is not followed by the actor being scheduled would time out.
+        let mut received = 0;
+        while received < MESSAGES {
+            runnable
+                .recv_timeout(Duration::from_secs(5))
+                .expect("actor with pending messages was not scheduled");
+            while receiver.try_recv().is_ok() {
+                received += 1;
+            }
+            schedule.deschedule(&receiver);
+        }
+        sender.join().unwrap();
+    }
+}
@@ -75,6 +75,12 @@
 //!     [`StringWrapper`](crate::message::common_types::StringWrapper) instead of defining our own
 //!     protobuf message
 //!
+//! ## Async Actors
+//!
+//! With the `tokio` feature enabled, actors may implement `AsyncActor` instead of `Actor`. The
+//! `receive` of an async actor returns a future, so it can `await` async code. Async actors are
+//! run by tokio executors (`ExecutorType::Tokio`) and otherwise behave like any other actor.
+//!
 //! <!-- TODO: Link to the 'patterns' module when it exists -->
 
 // Allow this since we're re-exporting everything and just re-using the module name for
@@ -87,10 +93,19 @@
 pub mod actor;
 #[doc(hidden)]
 pub mod address;
+#[cfg(feature = "tokio")]
+#[doc(hidden)]
+pub mod async_actor;
 #[doc(hidden)]
 pub mod envelope;
 #[doc(hidden)]
+pub mod mailbox;
+#[doc(hidden)]
 pub mod matcher;
+#[doc(hidden)]
+pub mod receptionist;
+#[doc(hidden)]
+pub mod selection;
 
 pub mod proto;
 
@@ -98,8 +113,15 @@ pub mod proto;
 pub use actor::*;
 #[doc(inline)]
 pub use address::*;
+#[cfg(feature = "tokio")]
+#[doc(inline)]
+pub use async_actor::*;
 #[doc(inline)]
 pub use matcher::*;
+#[doc(inline)]
+pub use receptionist::*;
+#[doc(inline)]
+pub use selection::*;
 
 pub(crate) use envelope::*;
-pub(crate) type Mailbox = crossbeam_channel::Sender<Envelope>;
+pub(crate) use mailbox::*;
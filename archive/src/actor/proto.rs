@@ -7,7 +7,6 @@
 use crate::actor;
 use crate::message::common_types::impl_busan_message;
 use crate::message::{Message, ToMessage};
-use std::cell::RefCell;
 
 // Import the generated protobuf definitions (see build.rs)
 include!(concat!(env!("OUT_DIR"), "/actor.proto.rs"));
@@ -23,25 +22,21 @@ impl ToMessage<ActorAddress> for &actor::ActorAddress {
                 Scheme::Remote => Scheme::Remote as i32,
             },
             path: self.uri.path(),
+            incarnation: self.incarnation,
         }
     }
 }
 
 impl TryFrom<ActorAddress> for actor::ActorAddress {
-    type Error = String;
+    type Error = actor::UriParseError;
 
     fn try_from(address: ActorAddress) -> Result<Self, Self::Error> {
         let scheme = Scheme::from_i32(address.scheme)
-            .ok_or(format!("Invalid scheme: {}", address.scheme))?;
-        Ok(actor::ActorAddress {
-            // TODO: This needs an internal constructor. Presumably there is at least
-            //       one other place we're doing this same thing
-            uri: actor::Uri {
-                scheme,
-                path_segments: address.path.split('/').map(|s| s.to_string()).collect(),
-            },
-            mailbox: RefCell::new(None),
-        })
+            .ok_or_else(|| actor::UriParseError::UnknownScheme(address.scheme.to_string()))?;
+        Ok(actor::ActorAddress::new(
+            actor::Uri::from_path(scheme, &address.path)?,
+            address.incarnation,
+        ))
     }
 }
 
@@ -0,0 +1,277 @@
+use crate::actor::{ActorAddress, Uri};
+use crate::message::system::Listing;
+use crate::message::{Message, ToMessage};
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+
+/// Key identifying a service registered with the receptionist, where `M` is the type of message
+/// the service accepts.
+///
+/// The receptionist is a system service that lets independent parts of the actor tree discover
+/// each other without having addresses passed down by hand. Actors are registered under a key
+/// with [`Context::register_service`](crate::actor::Context::register_service), looked up with
+/// [`Context::find_service`](crate::actor::Context::find_service) and changes can be followed
+/// with [`Context::subscribe_service`](crate::actor::Context::subscribe_service). Actors are
+/// removed from all services once they stop.
+///
+/// Services are identified by the `id` together with the message type of the key, so keys that
+/// share an id but accept different messages refer to different services.
+///
+/// ```rust,no_run
+/// # use busan::prelude::*;
+/// # use busan::actor::ServiceKey;
+/// # use busan::message::common_types::StringWrapper;
+/// const INDEXERS: ServiceKey<StringWrapper> = ServiceKey::new("indexer");
+///
+/// # struct Indexer;
+/// impl Actor for Indexer {
+///     fn before_start(&mut self, ctx: Context) {
+///         ctx.register_service(&INDEXERS, ctx.address());
+///     }
+/// }
+///
+/// # struct Publisher;
+/// impl Actor for Publisher {
+///     fn before_start(&mut self, ctx: Context) {
+///         ctx.subscribe_service(&INDEXERS);
+///     }
+///
+///     fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+///         if let Some(indexers) = INDEXERS.listing(msg.as_ref()) {
+///             // ...
+///         }
+///     }
+/// }
+/// ```
+pub struct ServiceKey<M> {
+    id: &'static str,
+    _message: PhantomData<fn() -> M>,
+}
+
+impl<M: Message> ServiceKey<M> {
+    /// Create a key for the service identified by `id`.
+    pub const fn new(id: &'static str) -> Self {
+        Self {
+            id,
+            _message: PhantomData,
+        }
+    }
+
+    /// The id identifying the service.
+    pub fn id(&self) -> &str {
+        self.id
+    }
+
+    /// The name the service is registered under with the receptionist, made up of the id and
+    /// the message type (as in [`Listing`] messages).
+    pub fn service(&self) -> String {
+        format!("{}[{}]", self.id, std::any::type_name::<M>())
+    }
+
+    /// If `msg` is a [`Listing`] for this service, return the addresses in the listing.
+    pub fn listing(&self, msg: &dyn Message) -> Option<Vec<ActorAddress>> {
+        msg.as_any()
+            .downcast_ref::<Listing>()
+            .filter(|listing| listing.service == self.service())
+            .map(Listing::addresses)
+    }
+}
+
+impl<M> Clone for ServiceKey<M> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            _message: PhantomData,
+        }
+    }
+}
+
+impl<M> Debug for ServiceKey<M> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ServiceKey({})", self.id)
+    }
+}
+
+impl Listing {
+    /// The addresses of the actors registered under the service.
+    pub fn addresses(&self) -> Vec<ActorAddress> {
+        self.addresses
+            .iter()
+            .flat_map(|list| list.addresses.iter())
+            .filter_map(|address| ActorAddress::try_from(address.clone()).ok())
+            .collect()
+    }
+}
+
+/// Bookkeeping of registered services and their subscribers, held by the runtime manager.
+#[derive(Default)]
+pub(crate) struct ServiceRegistry {
+    /// Registered actors per service, in order of registration
+    services: HashMap<String, Vec<ActorAddress>>,
+    subscribers: HashMap<String, Vec<ActorAddress>>,
+}
+
+impl ServiceRegistry {
+    /// Register `address` under `service`, returning `true` if it was not already registered.
+    pub(crate) fn register(&mut self, service: &str, address: ActorAddress) -> bool {
+        let addresses = self.services.entry(service.to_string()).or_default();
+        if addresses.iter().any(|a| a.uri == address.uri) {
+            return false;
+        }
+        addresses.push(address);
+        true
+    }
+
+    /// Remove `uri` from `service`, returning `true` if it was registered.
+    pub(crate) fn deregister(&mut self, service: &str, uri: &Uri) -> bool {
+        match self.services.get_mut(service) {
+            Some(addresses) => {
+                let len = addresses.len();
+                addresses.retain(|a| &a.uri != uri);
+                let removed = addresses.len() != len;
+                if addresses.is_empty() {
+                    self.services.remove(service);
+                }
+                removed
+            }
+            None => false,
+        }
+    }
+
+    /// Remove a stopped actor from all services and subscriptions, returning the services whose
+    /// listing changed.
+    pub(crate) fn remove_actor(&mut self, uri: &Uri) -> Vec<String> {
+        let services: Vec<String> = self
+            .services
+            .iter()
+            .filter(|(_, addresses)| addresses.iter().any(|a| &a.uri == uri))
+            .map(|(service, _)| service.clone())
+            .collect();
+        for service in services.iter() {
+            self.deregister(service, uri);
+        }
+        self.subscribers.retain(|_, subscribers| {
+            subscribers.retain(|s| &s.uri != uri);
+            !subscribers.is_empty()
+        });
+        services
+    }
+
+    pub(crate) fn subscribe(&mut self, service: &str, subscriber: ActorAddress) {
+        let subscribers = self.subscribers.entry(service.to_string()).or_default();
+        if !subscribers.iter().any(|s| s.uri == subscriber.uri) {
+            subscribers.push(subscriber);
+        }
+    }
+
+    pub(crate) fn unsubscribe(&mut self, service: &str, uri: &Uri) {
+        if let Some(subscribers) = self.subscribers.get_mut(service) {
+            subscribers.retain(|s| &s.uri != uri);
+            if subscribers.is_empty() {
+                self.subscribers.remove(service);
+            }
+        }
+    }
+
+    /// The actors registered under `service`
+    pub(crate) fn registered(&self, service: &str) -> &[ActorAddress] {
+        self.services.get(service).map_or(&[], Vec::as_slice)
+    }
+
+    pub(crate) fn subscribers(&self, service: &str) -> &[ActorAddress] {
+        self.subscribers.get(service).map_or(&[], Vec::as_slice)
+    }
+
+    pub(crate) fn listing(&self, service: &str) -> Listing {
+        Listing {
+            service: service.to_string(),
+            addresses: Some(self.registered(service).to_message()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::common_types::StringWrapper;
+
+    #[test]
+    fn test_register_and_deregister() {
+        let mut registry = ServiceRegistry::default();
+        let a = ActorAddress::new_root("a");
+        let b = ActorAddress::new_root("b");
+
+        assert!(registry.register("indexer", a.clone()));
+        assert!(registry.register("indexer", b.clone()));
+        assert!(!registry.register("indexer", a.clone()));
+        assert!(registry.register("publisher", a.clone()));
+
+        let listing = registry.listing("indexer");
+        let uris: Vec<_> = listing.addresses().iter().map(|a| a.uri()).collect();
+        assert_eq!(uris, vec![a.uri(), b.uri()]);
+
+        assert!(registry.deregister("indexer", &a.uri));
+        assert!(!registry.deregister("indexer", &a.uri));
+        assert!(!registry.deregister("unknown", &a.uri));
+        assert_eq!(registry.registered("indexer").len(), 1);
+    }
+
+    #[test]
+    fn test_remove_actor() {
+        let mut registry = ServiceRegistry::default();
+        let a = ActorAddress::new_root("a");
+        let b = ActorAddress::new_root("b");
+        registry.register("indexer", a.clone());
+        registry.register("publisher", a.clone());
+        registry.register("publisher", b.clone());
+        registry.subscribe("indexer", a.clone());
+        registry.subscribe("indexer", b.clone());
+
+        let mut changed = registry.remove_actor(&a.uri);
+        changed.sort();
+        assert_eq!(changed, vec!["indexer", "publisher"]);
+        assert!(registry.registered("indexer").is_empty());
+        assert_eq!(registry.registered("publisher").len(), 1);
+        // Subscriptions of the stopped actor are removed as well
+        let subscribers: Vec<_> = registry
+            .subscribers("indexer")
+            .iter()
+            .map(|s| s.uri())
+            .collect();
+        assert_eq!(subscribers, vec![b.uri()]);
+        assert!(registry.remove_actor(&a.uri).is_empty());
+    }
+
+    #[test]
+    fn test_key_listing() {
+        const INDEXERS: ServiceKey<Listing> = ServiceKey::new("indexer");
+        let mut registry = ServiceRegistry::default();
+        registry.register(&INDEXERS.service(), ActorAddress::new_root("a"));
+
+        assert_eq!(
+            INDEXERS
+                .listing(&registry.listing(&INDEXERS.service()))
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(INDEXERS.listing(&registry.listing("publisher")).is_none());
+        assert!(INDEXERS.listing(&Listing::default()).is_none());
+    }
+
+    #[test]
+    fn test_key_message_type() {
+        const LISTINGS: ServiceKey<Listing> = ServiceKey::new("indexer");
+        const STRINGS: ServiceKey<StringWrapper> = ServiceKey::new("indexer");
+        assert_ne!(LISTINGS.service(), STRINGS.service());
+        assert_eq!(LISTINGS.service(), LISTINGS.clone().service());
+
+        let mut registry = ServiceRegistry::default();
+        registry.register(&LISTINGS.service(), ActorAddress::new_root("a"));
+        assert!(registry.registered(&STRINGS.service()).is_empty());
+        assert!(STRINGS
+            .listing(&registry.listing(&LISTINGS.service()))
+            .is_none());
+    }
+}
@@ -0,0 +1,306 @@
+use crate::actor::proto::Scheme;
+use crate::actor::{ActorAddress, Context, Uri};
+use crate::error::BusanError;
+use crate::message::{Message, ToMessage};
+use crate::system::RuntimeManagerRef;
+use std::fmt::{Display, Formatter};
+
+/// A selection of actors, identified by a path pattern rather than by address.
+///
+/// Selections are created with [`ActorSystem::select`](crate::ActorSystem::select) (absolute
+/// paths) or [`Context::select`] (absolute or relative paths). A path may contain:
+///
+///   + Exact segments (e.g. `local://root/workers/worker-0`)
+///   + `*` within a segment, matching any part of a single segment (e.g. `workers/worker-*`)
+///   + `**` as a segment, matching zero or more segments (e.g. `local://root/**/indexer-*`)
+///   + `..` and `.` segments, which are resolved when the selection is created
+///
+/// A selection is resolved against the actors running at the time it is used, so the same
+/// selection may resolve to different actors over time.
+///
+/// ```rust,no_run
+/// # use busan::prelude::*;
+/// # use busan::message::common_types::StringWrapper;
+/// # struct Supervisor;
+/// impl Actor for Supervisor {
+///     fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+///         // Broadcast to all siblings of the current actor named "worker-*"
+///         let workers = ctx.select("../worker-*").unwrap();
+///         workers.send(&ctx, "reload");
+///     }
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ActorSelection {
+    pattern: PathPattern,
+    runtime_manager: RuntimeManagerRef,
+}
+
+impl ActorSelection {
+    pub(crate) fn new(pattern: PathPattern, runtime_manager: RuntimeManagerRef) -> Self {
+        Self {
+            pattern,
+            runtime_manager,
+        }
+    }
+
+    /// Resolve the selection into the addresses of all currently running actors matching the
+    /// path, ordered by path. This is a blocking call to the runtime manager.
+    pub fn resolve(&self) -> Vec<ActorAddress> {
+        self.runtime_manager.select(&self.pattern)
+    }
+
+    /// Send a message to every actor matching the selection, returning the number of actors
+    /// the message was sent to.
+    pub fn send<M: Message + 'static, T: ToMessage<M>>(&self, ctx: &Context, message: T) -> usize {
+        let message = message.to_message();
+        let addresses = self.resolve();
+        for address in addresses.iter() {
+            ctx.send_message(address, message.clone_box(), None);
+        }
+        addresses.len()
+    }
+}
+
+impl Display for ActorSelection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+/// A parsed selection path. `..` and `.` segments have already been resolved, so a pattern
+/// only consists of (possibly wildcard) segments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PathPattern {
+    scheme: Scheme,
+    segments: Vec<SegmentPattern>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum SegmentPattern {
+    /// A segment that must match exactly
+    Exact(String),
+    /// A segment containing one or more `*` wildcards
+    Glob(String),
+    /// `**`, matching zero or more segments
+    AnyPath,
+}
+
+impl PathPattern {
+    /// Parse an absolute path (e.g. `local://root/workers/*`).
+    pub(crate) fn parse(path: &str) -> Result<Self, BusanError> {
+        let (scheme, rest) = match path.split_once("://") {
+            Some(("local", rest)) => (Scheme::Local, rest),
+            Some(("remote", rest)) => (Scheme::Remote, rest),
+            Some((scheme, _)) => {
+                return Err(BusanError::InvalidPath(format!(
+                    "unknown scheme \"{}\" in {}",
+                    scheme, path
+                )))
+            }
+            None => {
+                return Err(BusanError::InvalidPath(format!(
+                    "{} is not an absolute path",
+                    path
+                )))
+            }
+        };
+        Self::resolve(scheme, &[], rest, path)
+    }
+
+    /// Parse a path relative to `base`, or an absolute path if it includes a scheme.
+    pub(crate) fn parse_relative(base: &Uri, path: &str) -> Result<Self, BusanError> {
+        if path.contains("://") {
+            return Self::parse(path);
+        }
+        Self::resolve(base.scheme, &base.path_segments, path, path)
+    }
+
+    /// Apply the segments of `path` to `base`, resolving `..` and `.` along the way.
+    fn resolve(
+        scheme: Scheme,
+        base: &[String],
+        path: &str,
+        original: &str,
+    ) -> Result<Self, BusanError> {
+        let mut segments: Vec<SegmentPattern> = base
+            .iter()
+            .map(|segment| SegmentPattern::Exact(segment.clone()))
+            .collect();
+        for segment in path.trim_end_matches('/').split('/') {
+            match segment {
+                "" => {
+                    return Err(BusanError::InvalidPath(format!(
+                        "empty path segment in {}",
+                        original
+                    )))
+                }
+                "." => {}
+                ".." => {
+                    if segments.pop().is_none() {
+                        return Err(BusanError::InvalidPath(format!(
+                            "{} refers to a path above the root",
+                            original
+                        )));
+                    }
+                }
+                "**" => segments.push(SegmentPattern::AnyPath),
+                glob if glob.contains('*') => segments.push(SegmentPattern::Glob(glob.to_string())),
+                exact => segments.push(SegmentPattern::Exact(exact.to_string())),
+            }
+        }
+        if segments.is_empty() {
+            return Err(BusanError::InvalidPath(format!(
+                "{} does not refer to any actor",
+                original
+            )));
+        }
+        Ok(Self { scheme, segments })
+    }
+
+    /// Returns true if the pattern matches `uri`.
+    pub(crate) fn matches(&self, uri: &Uri) -> bool {
+        self.scheme == uri.scheme && match_segments(&self.segments, &uri.path_segments)
+    }
+}
+
+impl Display for PathPattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.scheme {
+            Scheme::Local => write!(f, "local://")?,
+            Scheme::Remote => write!(f, "remote://")?,
+        }
+        let segments: Vec<&str> = self
+            .segments
+            .iter()
+            .map(|segment| match segment {
+                SegmentPattern::Exact(s) | SegmentPattern::Glob(s) => s.as_str(),
+                SegmentPattern::AnyPath => "**",
+            })
+            .collect();
+        write!(f, "{}", segments.join("/"))
+    }
+}
+
+fn match_segments(patterns: &[SegmentPattern], segments: &[String]) -> bool {
+    match patterns.split_first() {
+        None => segments.is_empty(),
+        Some((SegmentPattern::AnyPath, rest)) => {
+            (0..=segments.len()).any(|skip| match_segments(rest, &segments[skip..]))
+        }
+        Some((pattern, rest)) => match segments.split_first() {
+            Some((segment, remaining)) => {
+                let matched = match pattern {
+                    SegmentPattern::Exact(exact) => exact == segment,
+                    SegmentPattern::Glob(glob) => match_glob(glob, segment),
+                    SegmentPattern::AnyPath => unreachable!(),
+                };
+                matched && match_segments(rest, remaining)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Match a single segment against a pattern where `*` matches any (possibly empty) sequence
+/// of characters.
+fn match_glob(glob: &str, segment: &str) -> bool {
+    let mut parts = glob.split('*');
+    // There is always at least one part and, since the glob contains a `*`, at least two
+    let prefix = parts.next().unwrap();
+    let mut remaining = match segment.strip_prefix(prefix) {
+        Some(remaining) => remaining,
+        None => return false,
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let suffix = parts.pop().unwrap_or_default();
+    for part in parts {
+        match remaining.find(part) {
+            Some(index) => remaining = &remaining[index + part.len()..],
+            None => return false,
+        }
+    }
+    remaining.len() >= suffix.len() && remaining.ends_with(suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uri(path: &str) -> Uri {
+        Uri {
+            scheme: Scheme::Local,
+            path_segments: path.split('/').map(String::from).collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let pattern = PathPattern::parse("local://root/workers/./worker-*/../**/").unwrap();
+        assert_eq!(pattern.to_string(), "local://root/workers/**");
+
+        assert!(PathPattern::parse("root/workers").is_err());
+        assert!(PathPattern::parse("http://root").is_err());
+        assert!(PathPattern::parse("local://root//workers").is_err());
+        assert!(PathPattern::parse("local://root/../..").is_err());
+        assert!(PathPattern::parse("local://root/..").is_err());
+    }
+
+    #[test]
+    fn test_parse_relative() {
+        let base = uri("root/manager-0");
+        let pattern = PathPattern::parse_relative(&base, "../worker-*").unwrap();
+        assert_eq!(pattern.to_string(), "local://root/worker-*");
+        let pattern = PathPattern::parse_relative(&base, "child-0").unwrap();
+        assert_eq!(pattern.to_string(), "local://root/manager-0/child-0");
+        let pattern = PathPattern::parse_relative(&base, "local://other").unwrap();
+        assert_eq!(pattern.to_string(), "local://other");
+        assert!(PathPattern::parse_relative(&base, "../../..").is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let test_cases = vec![
+            (
+                "local://root/workers/worker-0",
+                "root/workers/worker-0",
+                true,
+            ),
+            (
+                "local://root/workers/worker-0",
+                "root/workers/worker-1",
+                false,
+            ),
+            ("local://root/workers/*", "root/workers/worker-1", true),
+            ("local://root/workers/*", "root/workers", false),
+            (
+                "local://root/workers/*",
+                "root/workers/worker-1/child-0",
+                false,
+            ),
+            ("local://root/*/worker-*", "root/workers/worker-1", true),
+            ("local://root/*/worker-*", "root/workers/indexer-1", false),
+            ("local://root/*-0/*x*y", "root/a-0/xay", true),
+            ("local://root/*-0/*x*y", "root/a-1/xay", false),
+            ("local://root/*-0/*x*y", "root/a-0/xy", true),
+            ("local://root/*-0/*x*y", "root/a-0/yx", false),
+            ("local://root/**", "root", true),
+            ("local://root/**", "root/a/b/c", true),
+            ("local://root/**/c", "root/a/b/c", true),
+            ("local://root/**/c", "root/c", true),
+            ("local://root/**/c", "root/a/b/c/d", false),
+            ("local://**/b/**", "root/a/b/c/d", true),
+            ("local://**/b/**", "root/a/c/d", false),
+        ];
+        for (pattern, path, expected) in test_cases {
+            let parsed = PathPattern::parse(pattern).unwrap();
+            assert_eq!(
+                parsed.matches(&uri(path)),
+                expected,
+                "{} matching {}",
+                pattern,
+                path
+            );
+        }
+    }
+}
@@ -1,11 +1,27 @@
 //! Configuration structs for [`ActorSystem`](crate::ActorSystem) creation
 
+use crate::executor::placement::{PlacementStrategy, RoundRobin};
+use crate::executor::ExecutorFactory;
 use num_cpus;
+use std::collections::HashSet;
+use std::sync::Arc;
 
 /// Configuration struct for an ActorSystem.
-#[derive(Default)]
 pub struct ActorSystemConfig {
     pub executor_config: ExecutorConfig,
+
+    /// Decides which executor newly spawned actors are assigned to (see
+    /// [`placement`](crate::executor::placement)). Defaults to [`RoundRobin`].
+    pub placement_strategy: Box<dyn PlacementStrategy>,
+}
+
+impl Default for ActorSystemConfig {
+    fn default() -> Self {
+        ActorSystemConfig {
+            executor_config: ExecutorConfig::default(),
+            placement_strategy: Box::new(RoundRobin::default()),
+        }
+    }
 }
 
 impl ActorSystemConfig {
@@ -20,10 +36,141 @@ pub struct ExecutorConfig {
 
     // The type of executor to use
     pub executor_type: ExecutorType,
+
+    /// How executors wait for new work once they are idle
+    pub idle_strategy: IdleStrategy,
+
+    /// The maximum number of messages an actor processes before its executor moves on to the
+    /// next actor with pending messages. Lower values are fairer to other actors on the same
+    /// executor, higher values have less scheduling overhead.
+    pub throughput: usize,
+
+    /// Additional executor groups. The executors configured above form the default group
+    /// ([`DEFAULT_EXECUTOR_GROUP`]), which actors are spawned in unless another group is chosen
+    /// at spawn time (see [`SpawnOptions::with_group`](crate::actor::SpawnOptions::with_group)).
+    pub groups: Vec<ExecutorGroupConfig>,
+
+    /// The maximum number of threads running blocking work offloaded by actors (see
+    /// [`Context::spawn_blocking`](crate::actor::Context::spawn_blocking)). Threads are started
+    /// as needed, further work waits for a thread to become available.
+    pub blocking_threads: usize,
+}
+
+/// Name of the executor group configured by the top-level fields of [`ExecutorConfig`]
+pub const DEFAULT_EXECUTOR_GROUP: &str = "default";
+
+/// A named group of executors, which isolates the actors spawned in it from the actors of other
+/// groups. For example, actors that block (e.g. on I/O) can be spawned in their own group so they
+/// don't stall the actors of the default group.
+pub struct ExecutorGroupConfig {
+    pub name: String,
+    pub kind: ExecutorGroupKind,
+
+    /// The type of executor of a shared group, if different from the system's
+    /// [`ExecutorConfig::executor_type`]. Executors of pinned groups are always threads.
+    pub executor_type: Option<ExecutorType>,
+}
+
+pub enum ExecutorGroupKind {
+    /// A pool of `num_executors` executors shared by the actors of the group. The executors are of
+    /// the configured [`ExecutorType`] and are placed with the system's placement strategy.
+    Shared { num_executors: usize },
+
+    /// Each actor of the group runs on an executor (thread) of its own, which is started when the
+    /// actor is spawned and stopped once the actor has stopped.
+    Pinned,
+}
+
+impl ExecutorGroupConfig {
+    /// A group of `num_executors` executors shared by the actors of the group.
+    pub fn shared(name: &str, num_executors: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: ExecutorGroupKind::Shared { num_executors },
+            executor_type: None,
+        }
+    }
+
+    /// A group giving each of its actors an executor of its own.
+    pub fn pinned(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: ExecutorGroupKind::Pinned,
+            executor_type: None,
+        }
+    }
+
+    /// Use executors of `executor_type` for the (shared) group. For example, async actors can be
+    /// run in a group of `ExecutorType::Tokio` executors while the rest of the system uses
+    /// thread executors.
+    pub fn with_executor_type(mut self, executor_type: ExecutorType) -> Self {
+        self.executor_type = Some(executor_type);
+        self
+    }
 }
 
 pub enum ExecutorType {
+    /// Each executor is a thread running the actors assigned to it.
     Thread,
+    /// The executors form a pool sharing all actors. Idle executors steal runnable actors from
+    /// busy ones, so a few busy actors don't leave other executors idle. An actor still only runs
+    /// on one executor at a time and processes its messages in order.
+    WorkStealing,
+    /// Each executor runs its actors as tasks on a (single-threaded) tokio runtime, which allows
+    /// running async actors (see [`AsyncActor`](crate::actor::AsyncActor)) alongside regular
+    /// actors. Requires the `tokio` feature.
+    #[cfg(feature = "tokio")]
+    Tokio,
+    /// All actors and the runtime manager run on the thread driving the system rather than on
+    /// threads of their own, which makes runs reproducible (e.g. in tests). The order in which
+    /// actors run is chosen by a PRNG seeded with `seed` and time is virtual (see
+    /// [`Context::now`](crate::actor::Context::now)), so running a system with the same seed
+    /// replays the same interleaving of its actors.
+    ///
+    /// The system only runs while it is driven, i.e. during
+    /// [`ActorSystem::run_until_idle`](crate::ActorSystem::run_until_idle),
+    /// [`ActorSystem::advance_time`](crate::ActorSystem::advance_time),
+    /// [`ActorSystem::await_shutdown`](crate::ActorSystem::await_shutdown) and while waiting for
+    /// replies with [`Inbox::receive`](crate::Inbox::receive) or
+    /// [`SystemHandle::ask`](crate::SystemHandle::ask). Timeouts of the latter are in virtual
+    /// time. `num_executors` logical executors are used for placement. Executor groups are not
+    /// supported.
+    Deterministic { seed: u64 },
+    /// Executors spawned by a user-provided factory (see
+    /// [`ExecutorFactory`] for what an executor has to do). The factory is shared, so the same
+    /// factory can be used by several executor groups.
+    Custom(Arc<dyn ExecutorFactory>),
+}
+
+impl ExecutorType {
+    /// Returns true if executors of this type can run async actors.
+    pub(crate) fn runs_async_actors(&self) -> bool {
+        #[cfg(feature = "tokio")]
+        if let ExecutorType::Tokio = self {
+            return true;
+        }
+        false
+    }
+}
+
+/// How an executor waits for new work (messages, commands or due timers) once it is idle.
+/// Executors are woken as soon as work arrives, so the strategy trades CPU usage for the latency
+/// of waking up.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum IdleStrategy {
+    /// Park the executor thread as soon as it is idle (the default). Uses the least CPU, but
+    /// every message that arrives at an idle executor has to wake up the thread.
+    #[default]
+    Park,
+
+    /// Spin for up to `max_spins` iterations and then yield the thread `yields` times before
+    /// parking. The number of spins adapts between 0 and `max_spins`: it grows while work keeps
+    /// arriving before the executor parks and shrinks while it doesn't.
+    ///
+    /// Spinning only pays off with a spare CPU for each busy executor. Otherwise a spinning
+    /// executor holds on to the CPU that other executors need, which makes round trips between
+    /// actors much slower than with [`IdleStrategy::Park`] (see the `ping_pong_bench` example).
+    SpinThenPark { max_spins: u32, yields: u32 },
 }
 
 impl ExecutorConfig {
@@ -31,6 +178,51 @@ impl ExecutorConfig {
         if self.num_executors == 0 {
             return Err("num_executors must be greater than 0".to_string());
         }
+        if self.throughput == 0 {
+            return Err("throughput must be greater than 0".to_string());
+        }
+        if self.blocking_threads == 0 {
+            return Err("blocking_threads must be greater than 0".to_string());
+        }
+        if let ExecutorType::Deterministic { .. } = self.executor_type {
+            if !self.groups.is_empty() {
+                return Err("deterministic systems don't support executor groups".to_string());
+            }
+        }
+        let mut names = HashSet::from([DEFAULT_EXECUTOR_GROUP]);
+        for group in &self.groups {
+            if group.name.is_empty() {
+                return Err("executor group names must not be empty".to_string());
+            }
+            if !names.insert(group.name.as_str()) {
+                return Err(format!("duplicate executor group {}", group.name));
+            }
+            match group.kind {
+                ExecutorGroupKind::Shared { num_executors: 0 } => {
+                    return Err(format!(
+                        "num_executors of executor group {} must be greater than 0",
+                        group.name
+                    ));
+                }
+                _ if matches!(
+                    group.executor_type,
+                    Some(ExecutorType::Deterministic { .. })
+                ) =>
+                {
+                    return Err(format!(
+                        "executor group {} can't use deterministic executors",
+                        group.name
+                    ));
+                }
+                ExecutorGroupKind::Pinned if group.executor_type.is_some() => {
+                    return Err(format!(
+                        "executor_type can't be set for pinned executor group {}",
+                        group.name
+                    ));
+                }
+                _ => (),
+            }
+        }
 
         Ok(())
     }
@@ -41,6 +233,10 @@ impl Default for ExecutorConfig {
         ExecutorConfig {
             num_executors: num_cpus::get(),
             executor_type: ExecutorType::Thread,
+            idle_strategy: IdleStrategy::default(),
+            throughput: 5,
+            groups: Vec::new(),
+            blocking_threads: 64,
         }
     }
 }
@@ -1,4 +1,6 @@
+use crate::actor::UriParseError;
 use std::fmt::Display;
+use std::time::Duration;
 
 /// Error type for all errors originating from library actions
 #[derive(Debug)]
@@ -7,12 +9,36 @@ pub enum BusanError {
     /// executors available, IO error when scheduling remotely). Contains
     /// a user focused explanation of the specific error cause.
     UnassignableActor(String),
+
+    /// Encountered when an executor can't be added to or removed from an executor group (e.g.
+    /// because the group does not exist). Contains a user focused explanation of the cause.
+    InvalidExecutor(String),
+
+    /// Encountered when a path (e.g. for an actor selection) cannot be parsed or does not refer
+    /// to a valid location in the actor hierarchy.
+    InvalidPath(String),
+
+    /// Encountered when an actor is given a name that cannot be used as part of its address.
+    InvalidName(UriParseError),
+
+    /// Encountered when using a [`SystemHandle`](crate::SystemHandle) of a system that
+    /// has shut down (or started shutting down).
+    SystemShutdown,
+
+    /// Encountered when no reply is received in time (e.g. for an ask), containing the time
+    /// waited.
+    Timeout(Duration),
 }
 
 impl Display for BusanError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             BusanError::UnassignableActor(s) => write!(f, "Actor is unassignable: {}", s),
+            BusanError::InvalidExecutor(s) => write!(f, "Invalid executor: {}", s),
+            BusanError::InvalidPath(s) => write!(f, "Invalid path: {}", s),
+            BusanError::InvalidName(e) => write!(f, "Invalid actor name: {}", e),
+            BusanError::SystemShutdown => write!(f, "Actor system has shut down"),
+            BusanError::Timeout(d) => write!(f, "Timed out after {:?}", d),
         }
     }
 }
@@ -0,0 +1,219 @@
+//! Thread pool for blocking work offloaded by actors (see
+//! [`Context::spawn_blocking`](crate::actor::Context::spawn_blocking)), so it doesn't block the
+//! executors. Threads are started as work is queued, up to the configured maximum, and stop once
+//! they have been idle for a while.
+
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+use log::trace;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+/// How long an idle thread waits for work before it stops
+const KEEP_ALIVE: Duration = Duration::from_secs(10);
+
+type Job = Box<dyn FnOnce() + Send>;
+
+pub(crate) struct BlockingPool {
+    max_threads: usize,
+    sender: Sender<Job>,
+    receiver: Receiver<Job>,
+    threads: Mutex<Threads>,
+    shutdown: AtomicBool,
+}
+
+#[derive(Default)]
+struct Threads {
+    running: usize,
+    idle: usize,
+    /// Jobs that have been queued, but not yet taken by a thread
+    queued: usize,
+}
+
+impl BlockingPool {
+    pub(crate) fn new(max_threads: usize) -> Self {
+        let (sender, receiver) = unbounded();
+        Self {
+            max_threads,
+            sender,
+            receiver,
+            threads: Mutex::default(),
+            shutdown: AtomicBool::new(false),
+        }
+    }
+
+    /// Queue `job` to run on a thread of the pool. A thread is started for the job unless enough
+    /// threads are idle, or the maximum number of threads is running already (in which case the
+    /// job waits for a thread to become available). Jobs are dropped once the pool has shut down.
+    pub(crate) fn spawn(self: &Arc<Self>, job: Job) {
+        if self.shutdown.load(Ordering::Acquire) {
+            return;
+        }
+        let mut threads = self.threads();
+        threads.queued += 1;
+        let _ = self.sender.send(job);
+        if threads.queued > threads.idle && threads.running < self.max_threads {
+            threads.running += 1;
+            let pool = self.clone();
+            thread::Builder::new()
+                .name("busan-blocking".to_string())
+                .spawn(move || pool.run_worker())
+                .expect("failed to start blocking thread");
+        }
+    }
+
+    /// Stop running queued jobs. Jobs that are running are not interrupted, their threads stop
+    /// once the jobs have returned.
+    pub(crate) fn shutdown(&self) {
+        self.shutdown.store(true, Ordering::Release);
+    }
+
+    fn run_worker(&self) {
+        loop {
+            self.threads().idle += 1;
+            let job = self.receiver.recv_timeout(KEEP_ALIVE);
+            let mut threads = self.threads();
+            threads.idle -= 1;
+            match job {
+                Ok(job) => {
+                    threads.queued -= 1;
+                    drop(threads);
+                    if self.shutdown.load(Ordering::Acquire) {
+                        continue;
+                    }
+                    job();
+                }
+                // Jobs queued after the timeout are taken before the thread stops
+                Err(RecvTimeoutError::Timeout) if !self.receiver.is_empty() => {}
+                Err(_) => {
+                    threads.running -= 1;
+                    trace!("blocking thread idle, stopping");
+                    return;
+                }
+            }
+        }
+    }
+
+    fn threads(&self) -> MutexGuard<'_, Threads> {
+        self.threads.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{Actor, ActorAddress, ActorInit, Context};
+    use crate::config::{ActorSystemConfig, ExecutorConfig, ExecutorType};
+    use crate::message::common_types::StringWrapper;
+    use crate::message::system::BlockingTaskFailed;
+    use crate::message::Message;
+    use crate::system::ActorSystem;
+    use crate::util::testing::string_value;
+    use std::sync::atomic::AtomicUsize;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn test_max_threads() {
+        let pool = Arc::new(BlockingPool::new(2));
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let (done, finished) = unbounded();
+        for _ in 0..4 {
+            let (running, max_running, done) = (running.clone(), max_running.clone(), done.clone());
+            pool.spawn(Box::new(move || {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(20));
+                running.fetch_sub(1, Ordering::SeqCst);
+                done.send(()).unwrap();
+            }));
+        }
+        for _ in 0..4 {
+            finished.recv_timeout(Duration::from_secs(1)).unwrap();
+        }
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+        assert_eq!(pool.threads().running, 2);
+    }
+
+    /// Offloads every message it receives to the blocking pool, which replies with the message
+    /// and the thread it ran on, or panics on `"panic"`. Results are sent back to the sender of
+    /// the message.
+    struct Offload {
+        requesters: Vec<ActorAddress>,
+    }
+
+    impl ActorInit for Offload {
+        type Init = StringWrapper;
+
+        fn init(_init_msg: Self::Init) -> Self {
+            Offload {
+                requesters: Vec::new(),
+            }
+        }
+    }
+
+    impl Actor for Offload {
+        fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+            if ctx.sender().uri != ctx.address().uri {
+                self.requesters.push(ctx.sender().clone());
+                let value = string_value(msg);
+                ctx.spawn_blocking(move || {
+                    if value == "panic" {
+                        panic!("blocking work failed");
+                    }
+                    format!("{} {:?}", value, thread::current().id())
+                });
+            } else if let Some(failed) = msg.as_any().downcast_ref::<BlockingTaskFailed>() {
+                ctx.send(
+                    &self.requesters.remove(0),
+                    format!("failed: {}", failed.reason),
+                );
+            } else {
+                ctx.send_message(&self.requesters.remove(0), msg, None);
+            }
+        }
+    }
+
+    fn init_system(executor_type: ExecutorType) -> ActorSystem {
+        ActorSystem::init(ActorSystemConfig {
+            executor_config: ExecutorConfig {
+                num_executors: 1,
+                executor_type,
+                ..ExecutorConfig::default()
+            },
+            ..ActorSystemConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_spawn_blocking_result() {
+        let system = init_system(ExecutorType::Thread);
+        let handle = system.handle();
+        let offload = handle.spawn_actor::<Offload, _, _>("offload", "").unwrap();
+
+        let reply = string_value(handle.ask(&offload, "work", TIMEOUT).unwrap());
+        let (value, thread) = reply.split_once(' ').unwrap();
+        assert_eq!(value, "work");
+        assert_ne!(thread, format!("{:?}", thread::current().id()));
+
+        let reply = string_value(handle.ask(&offload, "panic", TIMEOUT).unwrap());
+        assert_eq!(reply, "failed: blocking work failed");
+        system.shutdown();
+    }
+
+    #[test]
+    fn test_spawn_blocking_deterministic() {
+        let system = init_system(ExecutorType::Deterministic { seed: 1 });
+        let handle = system.handle();
+        let offload = handle.spawn_actor::<Offload, _, _>("offload", "").unwrap();
+
+        // The work runs on the thread driving the system
+        let reply = string_value(handle.ask(&offload, "work", TIMEOUT).unwrap());
+        assert_eq!(reply, format!("work {:?}", thread::current().id()));
+        let reply = string_value(handle.ask(&offload, "panic", TIMEOUT).unwrap());
+        assert_eq!(reply, "failed: blocking work failed");
+        system.shutdown();
+    }
+}
@@ -0,0 +1,387 @@
+//! Executor of deterministic systems (see `ExecutorType::Deterministic`). Rather than running on
+//! threads of their own, the executors of a deterministic system are run by the thread driving
+//! the system, one unit of work (a command, a message or a timer) at a time. Which unit of work
+//! is performed next is chosen by a seeded PRNG, so the same seed always results in the same
+//! interleaving of actors.
+
+use log::{debug, info};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::actor::ActorCell;
+use crate::executor::timer::TimerQueue;
+use crate::executor::{ExecutorCommands, ExecutorFactory, ExecutorHandle, RunQueue};
+use crate::system::RuntimeManagerRef;
+use crate::util::rng::Rng;
+use crate::util::CommandChannel;
+
+/// Virtual time of a deterministic system. Time only moves forward when it is advanced
+/// explicitly, which the system does once it is idle (to the deadline of the next timer).
+pub(crate) struct VirtualClock {
+    start: Instant,
+    /// Nanoseconds elapsed since `start`
+    elapsed: AtomicU64,
+}
+
+impl VirtualClock {
+    pub(crate) fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            elapsed: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn now(&self) -> Instant {
+        self.start + Duration::from_nanos(self.elapsed.load(Ordering::Acquire))
+    }
+
+    /// Move the time forward to `instant`. Times in the past are ignored.
+    pub(crate) fn advance_to(&self, instant: Instant) {
+        let elapsed = instant.saturating_duration_since(self.start).as_nanos() as u64;
+        self.elapsed.fetch_max(elapsed, Ordering::AcqRel);
+    }
+}
+
+/// A unit of work of the [`DeterministicExecutor`]
+pub(crate) enum Work {
+    /// Handle the next command of the executor with the given index
+    Command(usize),
+    /// Process the next message of the runnable actor with the given index
+    Actor(usize),
+    /// Deliver the next scheduled message that is due
+    Timer,
+}
+
+/// Runs the logical executors of a deterministic system. Each executor has a command channel of
+/// its own (so the runtime manager can treat them like any other executor), but actors of all
+/// executors share a single run queue.
+pub(crate) struct DeterministicExecutor {
+    executors: Vec<LogicalExecutor>,
+
+    // Executors added through the factory of the executor, which are run from the next step on
+    added: Arc<Mutex<Vec<LogicalExecutor>>>,
+
+    // map of actor incarnations to actors and the index of the executor they are assigned to
+    actor_cells: HashMap<u64, (usize, ActorCell)>,
+
+    run_queue: Arc<DeterministicRunQueue>,
+
+    // Messages scheduled for later delivery by actors of all executors
+    timers: TimerQueue,
+
+    clock: Arc<VirtualClock>,
+    rng: Rng,
+    runtime_manager: RuntimeManagerRef,
+}
+
+struct LogicalExecutor {
+    name: String,
+    command_channel: CommandChannel<ExecutorCommands>,
+    running: bool,
+}
+
+/// Adds logical executors to a [`DeterministicExecutor`], so the runtime manager can start them
+/// like any other executor.
+pub(crate) struct DeterministicExecutorFactory {
+    added: Arc<Mutex<Vec<LogicalExecutor>>>,
+    run_queue: Arc<DeterministicRunQueue>,
+}
+
+impl ExecutorFactory for DeterministicExecutorFactory {
+    fn spawn_executor(
+        &self,
+        name: String,
+        command_channel: CommandChannel<ExecutorCommands>,
+        _manager_ref: RuntimeManagerRef,
+    ) -> ExecutorHandle {
+        self.added.lock().unwrap().push(LogicalExecutor {
+            name,
+            command_channel,
+            running: true,
+        });
+        // The executor has no thread of its own, so there is nothing to wait for
+        ExecutorHandle::new(|| {}).with_run_queue(self.run_queue.clone())
+    }
+}
+
+/// Actors with pending messages, in the order they were scheduled.
+#[derive(Default)]
+struct DeterministicRunQueue {
+    runnable: Mutex<Vec<u64>>,
+}
+
+impl RunQueue for DeterministicRunQueue {
+    fn schedule(&self, id: u64) {
+        self.runnable.lock().unwrap().push(id);
+    }
+}
+
+impl DeterministicExecutor {
+    pub(crate) fn new(
+        clock: Arc<VirtualClock>,
+        rng: Rng,
+        runtime_manager: RuntimeManagerRef,
+    ) -> Self {
+        Self {
+            executors: Vec::new(),
+            added: Default::default(),
+            actor_cells: HashMap::new(),
+            run_queue: Default::default(),
+            timers: TimerQueue::default(),
+            clock,
+            rng,
+            runtime_manager,
+        }
+    }
+
+    /// A factory for adding logical executors to this executor.
+    pub(crate) fn factory(&self) -> DeterministicExecutorFactory {
+        DeterministicExecutorFactory {
+            added: self.added.clone(),
+            run_queue: self.run_queue.clone(),
+        }
+    }
+
+    /// Pick a number in `[0, choices)` with the PRNG of the system.
+    pub(crate) fn choose(&mut self, choices: usize) -> usize {
+        self.rng.gen_range(choices)
+    }
+
+    /// All units of work that can be performed right now, in a deterministic order.
+    pub(crate) fn pending_work(&mut self) -> Vec<Work> {
+        self.executors.append(&mut self.added.lock().unwrap());
+        let mut work: Vec<Work> = self
+            .executors
+            .iter()
+            .enumerate()
+            .filter(|(_, executor)| executor.running && !executor.command_channel.recv_is_empty())
+            .map(|(index, _)| Work::Command(index))
+            .collect();
+        let runnable = self.run_queue.runnable.lock().unwrap().len();
+        work.extend((0..runnable).map(Work::Actor));
+        if self
+            .timers
+            .next_deadline()
+            .is_some_and(|deadline| deadline <= self.clock.now())
+        {
+            work.push(Work::Timer);
+        }
+        work
+    }
+
+    /// The deadline of the earliest scheduled message, if any.
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.timers.next_deadline()
+    }
+
+    pub(crate) fn perform(&mut self, work: Work) {
+        match work {
+            Work::Command(index) => {
+                if let Some(command) = self.executors[index].command_channel.try_recv() {
+                    self.handle_command(index, command);
+                }
+            }
+            Work::Actor(index) => {
+                let id = self.run_queue.runnable.lock().unwrap().remove(index);
+                self.run_actor(id);
+            }
+            Work::Timer => {
+                if let Some(message) = self.timers.pop_due(self.clock.now()) {
+                    message.deliver();
+                }
+            }
+        }
+    }
+
+    fn handle_command(&mut self, index: usize, command: ExecutorCommands) {
+        let executor = &mut self.executors[index];
+        match command {
+            ExecutorCommands::AssignActor(mut cell) => {
+                debug!("received actor assignment for {}", cell.address());
+                cell.start(&self.runtime_manager, &executor.command_channel);
+                let id = cell.address().incarnation();
+                if self.actor_cells.insert(id, (index, cell)).is_some() {
+                    panic!("Actor with incarnation {} already exists", id);
+                }
+                self.actor_cells[&id].1.schedule_pending();
+            }
+            ExecutorCommands::RunActor(id) => self.run_actor(id),
+            ExecutorCommands::ShutdownActor(address) => {
+                if let Some((_, cell)) = self.actor_cells.get_mut(&address.incarnation()) {
+                    cell.stop(&self.runtime_manager, &executor.command_channel);
+                }
+            }
+            ExecutorCommands::ShutdownActorComplete(address) => {
+                if let Some((_, cell)) = self.actor_cells.remove(&address.incarnation()) {
+                    cell.complete_stop(&self.runtime_manager);
+                }
+            }
+            ExecutorCommands::ScheduleMessage(message) => self.timers.push(message),
+            ExecutorCommands::Drain => {
+                info!("received drain command for {}", executor.name);
+                executor.running = false;
+                // Timers are shared by all executors, so only the actors are handed back
+                while let Some(command) = executor.command_channel.try_recv() {
+                    if let ExecutorCommands::ScheduleMessage(message) = command {
+                        self.timers.push(message);
+                    }
+                }
+                let mut ids: Vec<u64> = self
+                    .actor_cells
+                    .iter()
+                    .filter(|(_, (executor_index, _))| *executor_index == index)
+                    .map(|(id, _)| *id)
+                    .collect();
+                // Actors are placed on the remaining executors in the order they are handed back
+                ids.sort_unstable();
+                let actors = ids
+                    .iter()
+                    .filter_map(|id| self.actor_cells.remove(id))
+                    .map(|(_, cell)| cell)
+                    .collect();
+                self.runtime_manager
+                    .executor_drained(executor.name.clone(), actors, Vec::new());
+                self.runtime_manager.notify_shutdown(executor.name.clone());
+            }
+            ExecutorCommands::Shutdown => {
+                info!("received shutdown command for {}", executor.name);
+                executor.running = false;
+                self.actor_cells
+                    .retain(|_, (executor_index, _)| *executor_index != index);
+                self.runtime_manager.notify_shutdown(executor.name.clone());
+            }
+        }
+    }
+
+    /// Process the next message of the actor with incarnation `id`. Only a single message is
+    /// processed, so the PRNG decides between every two messages which actor runs next.
+    fn run_actor(&mut self, id: u64) {
+        let (index, cell) = match self.actor_cells.get_mut(&id) {
+            Some(entry) => entry,
+            // The actor has stopped since it was scheduled
+            None => return,
+        };
+        let command_channel = &self.executors[*index].command_channel;
+        if cell.process_next(&self.runtime_manager, command_channel) {
+            self.run_queue.schedule(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::actor::{Actor, ActorInit, Context};
+    use crate::config::{ActorSystemConfig, ExecutorConfig, ExecutorType};
+    use crate::message::common_types::StringWrapper;
+    use crate::message::Message;
+    use crate::system::ActorSystem;
+    use crate::util::testing::{string_value, Forward};
+    use std::collections::HashSet;
+    use std::time::{Duration, Instant};
+
+    fn init_system(seed: u64) -> ActorSystem {
+        ActorSystem::init(ActorSystemConfig {
+            executor_config: ExecutorConfig {
+                num_executors: 3,
+                executor_type: ExecutorType::Deterministic { seed },
+                throughput: 1,
+                ..ExecutorConfig::default()
+            },
+            ..ActorSystemConfig::default()
+        })
+    }
+
+    /// The order in which the messages forwarded by a few actors arrive at an inbox
+    fn interleaving(seed: u64) -> Vec<String> {
+        let system = init_system(seed);
+        let handle = system.handle();
+        let inbox = handle.inbox().unwrap();
+        let actors: Vec<_> = (0..4)
+            .map(|i| {
+                handle
+                    .spawn_actor::<Forward, _, _>(&format!("a{}", i), inbox.address().to_string())
+                    .unwrap()
+            })
+            .collect();
+        for (i, actor) in actors.iter().enumerate() {
+            for j in 0..3 {
+                inbox.send(actor, format!("a{}-{}", i, j)).unwrap();
+            }
+        }
+
+        system.run_until_idle();
+        let received: Vec<String> = std::iter::from_fn(|| inbox.try_receive())
+            .map(string_value)
+            .collect();
+        // Only the interleaving of the actors varies, each actor forwards its messages in order
+        for i in 0..4 {
+            let prefix = format!("a{}-", i);
+            let own: Vec<_> = received.iter().filter(|m| m.starts_with(&prefix)).collect();
+            let expected: Vec<_> = (0..3).map(|j| format!("{}{}", prefix, j)).collect();
+            assert_eq!(own, expected.iter().collect::<Vec<_>>());
+        }
+        system.shutdown();
+        received
+    }
+
+    #[test]
+    fn test_same_seed_same_interleaving() {
+        assert_eq!(interleaving(7), interleaving(7));
+    }
+
+    #[test]
+    fn test_seeds_vary_interleaving() {
+        let interleavings: HashSet<_> = (0..30).map(interleaving).collect();
+        assert!(
+            interleavings.len() > 1,
+            "all seeds gave {:?}",
+            interleavings
+        );
+    }
+
+    /// Replies to every message after the number of seconds in the message
+    struct Delay;
+
+    impl ActorInit for Delay {
+        type Init = StringWrapper;
+
+        fn init(_init_msg: Self::Init) -> Self {
+            Delay
+        }
+    }
+
+    impl Actor for Delay {
+        fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+            let value = string_value(msg);
+            let delay = Duration::from_secs(value.parse().unwrap());
+            ctx.send_after(ctx.sender(), value, delay);
+        }
+    }
+
+    #[test]
+    fn test_advance_time_fires_timers() {
+        let started = Instant::now();
+        let system = init_system(1);
+        let handle = system.handle();
+        let inbox = handle.inbox().unwrap();
+        let delay = handle.spawn_actor::<Delay, _, _>("delay", "").unwrap();
+        inbox.send(&delay, "120").unwrap();
+        inbox.send(&delay, "60").unwrap();
+
+        system.run_until_idle();
+        assert!(inbox.try_receive().is_none());
+        system.advance_time(Duration::from_secs(59));
+        assert!(inbox.try_receive().is_none());
+        system.advance_time(Duration::from_secs(1));
+        assert_eq!(string_value(inbox.try_receive().unwrap()), "60");
+        assert!(inbox.try_receive().is_none());
+        system.advance_time(Duration::from_secs(60));
+        assert_eq!(string_value(inbox.try_receive().unwrap()), "120");
+        system.shutdown();
+
+        // The timers fired in virtual time
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
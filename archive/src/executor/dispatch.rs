@@ -0,0 +1,270 @@
+//! Processing of actors shared by all executors: running the lifecycle hooks of actors and
+//! processing their messages. Executors decide _where_ and _when_ an actor runs, the functions
+//! here implement what happens once it does.
+
+use log::{debug, trace};
+
+use crate::actor::{cell_state, ActorAddress, ActorCell, Context, Envelope, SenderType};
+use crate::executor::timer::ScheduledMessage;
+use crate::executor::ExecutorCommands;
+use crate::message::system::{ack, ChildStopped, PoisonPill};
+use crate::message::Message;
+use crate::system::RuntimeManagerRef;
+use crate::util::CommandChannel;
+
+// Macro for quickly constructing a context object within an executor. The construction
+// of the context almost always looks the same, just some slight differences with the sender.
+macro_rules! context {
+    ($runtime_manager:expr, $command_channel:expr, $cell:tt, $sender:path) => {
+        context!($runtime_manager, $command_channel, $cell, ($sender))
+    };
+    ($runtime_manager:expr, $command_channel:expr, $cell:tt, $sender:expr) => {
+        Context {
+            address: &$cell.address,
+            runtime_manager: $runtime_manager,
+            executor_command_channel: $command_channel,
+            parent: &$cell.parent,
+            children: &mut $cell.children,
+            next_child_id: &mut $cell.next_child_id,
+            sender: &$sender,
+            cell_state: &mut $cell.state,
+            ack_nonce: &mut $cell.ack_nonce,
+        }
+    };
+}
+
+/// Start a newly assigned actor by calling `before_start`, unless it has been started already
+/// (on another executor).
+pub(crate) fn start_actor(
+    cell: &mut ActorCell,
+    runtime_manager: &RuntimeManagerRef,
+    command_channel: &CommandChannel<ExecutorCommands>,
+) {
+    if !begin_start(cell) {
+        return;
+    }
+    trace!("calling before_start for actor {}", &cell.address.uri);
+    cell.actor.before_start(context!(
+        runtime_manager,
+        command_channel,
+        cell,
+        SenderType::System
+    ));
+}
+
+/// Begin stopping an actor: mark the cell as shut down, call `before_stop` and inform the
+/// runtime manager (which stops the children of the actor first).
+pub(crate) fn stop_actor(
+    cell: &mut ActorCell,
+    runtime_manager: &RuntimeManagerRef,
+    command_channel: &CommandChannel<ExecutorCommands>,
+) {
+    cell_state::set_shutdown(&mut cell.state);
+    trace!("calling before_stop for actor {}", &cell.address.uri);
+    cell.actor.before_stop(context!(
+        runtime_manager,
+        command_channel,
+        cell,
+        SenderType::System
+    ));
+
+    // Inform the runtime manager of the shutdown. The runtime manager will take care of
+    // shutting down the children first and then send a `ShutdownActorComplete` message so we
+    // can call `after_stop`.
+    runtime_manager.actor_shutdown_notice(
+        &cell.address,
+        cell.parent.clone(),
+        cell.children.clone(),
+    );
+
+    // TODO: Notify the parent and all of the watchers
+    //       thought: maybe this should be done by the runtime manager when the
+    //       shutdown signal is sent. (add an additional flag of "notify_parent"
+    //       to the message we send in `runtime_manager.shutdown_actor`).
+
+    // Messages queued for the stopping actor are sent to dead letters on its next turn.
+    cell.schedule.schedule();
+}
+
+/// Finish stopping an actor once its children have stopped by calling `after_stop`.
+pub(crate) fn complete_stop(cell: &mut ActorCell, runtime_manager: &RuntimeManagerRef) {
+    trace!("calling after_stop for actor {}", &cell.address.uri);
+    cell.actor.after_stop();
+    // The mailbox is closed once the cell is dropped, so anything still queued would
+    // otherwise be lost.
+    drain_to_dead_letters(runtime_manager, cell);
+}
+
+/// Run a turn of an actor: process up to `throughput` of its messages. Returns `true` if the
+/// actor has messages left, in which case it remains scheduled and must be run again. Otherwise
+/// the actor is descheduled.
+pub(crate) fn run_turn(
+    cell: &mut ActorCell,
+    runtime_manager: &RuntimeManagerRef,
+    command_channel: &CommandChannel<ExecutorCommands>,
+    throughput: usize,
+) -> bool {
+    begin_turn(cell, runtime_manager);
+    for _ in 0..throughput {
+        let envelope = match next_envelope(cell) {
+            Some(envelope) => envelope,
+            None => break,
+        };
+        let mut ctx = context!(runtime_manager, command_channel, cell, envelope.sender);
+        if let Some(payload) = accept_message(&mut ctx, envelope.ack, envelope.payload) {
+            cell.actor.receive(ctx, payload);
+        }
+    }
+    end_turn(cell)
+}
+
+/// Async version of [`start_actor`], which awaits `before_start` of async actors.
+#[cfg(feature = "tokio")]
+pub(crate) async fn start_actor_async(
+    cell: &mut ActorCell,
+    runtime_manager: &RuntimeManagerRef,
+    command_channel: &CommandChannel<ExecutorCommands>,
+) {
+    if !begin_start(cell) {
+        return;
+    }
+    trace!("calling before_start for actor {}", &cell.address.uri);
+    let ctx = context!(runtime_manager, command_channel, cell, SenderType::System);
+    match cell.actor.as_async() {
+        Some(actor) => actor.before_start_boxed(ctx).await,
+        None => cell.actor.before_start(ctx),
+    }
+}
+
+/// Async version of [`run_turn`], which awaits `receive` of async actors. Messages are still
+/// processed one at a time: the next message is taken from the mailbox once `receive` has
+/// completed.
+#[cfg(feature = "tokio")]
+pub(crate) async fn run_turn_async(
+    cell: &mut ActorCell,
+    runtime_manager: &RuntimeManagerRef,
+    command_channel: &CommandChannel<ExecutorCommands>,
+    throughput: usize,
+) -> bool {
+    begin_turn(cell, runtime_manager);
+    for _ in 0..throughput {
+        let envelope = match next_envelope(cell) {
+            Some(envelope) => envelope,
+            None => break,
+        };
+        let mut ctx = context!(runtime_manager, command_channel, cell, envelope.sender);
+        if let Some(payload) = accept_message(&mut ctx, envelope.ack, envelope.payload) {
+            match cell.actor.as_async() {
+                Some(actor) => actor.receive_boxed(ctx, payload).await,
+                None => cell.actor.receive(ctx, payload),
+            }
+        }
+    }
+    end_turn(cell)
+}
+
+/// Mark the actor as started. Returns `false` if it already was.
+fn begin_start(cell: &mut ActorCell) -> bool {
+    if cell_state::is_started(cell.state) {
+        return false;
+    }
+    cell_state::set_started(&mut cell.state);
+    true
+}
+
+fn begin_turn(cell: &ActorCell, runtime_manager: &RuntimeManagerRef) {
+    if cell_state::is_shutdown(cell.state) {
+        drain_to_dead_letters(runtime_manager, cell);
+    }
+}
+
+/// The next message for the actor to process, unless it is shutting down.
+fn next_envelope(cell: &ActorCell) -> Option<Envelope> {
+    if cell_state::is_shutdown(cell.state) {
+        return None;
+    }
+    let envelope = cell.mailbox.try_recv().ok()?;
+    trace!("[{}] processing message: {:?}", &cell.address, &envelope);
+    Some(envelope)
+}
+
+/// Handle the parts of a message that are processed by the system rather than the actor: ACKs,
+/// poison pills and notices of stopped children. Returns the message if it should be received by the actor.
+fn accept_message(
+    ctx: &mut Context,
+    ack_nonce: Option<u32>,
+    payload: Box<dyn Message>,
+) -> Option<Box<dyn Message>> {
+    if let Some(ack_nonce) = ack_nonce {
+        trace!("Sending ACK({}) to {}", ack_nonce, ctx.sender);
+        send_ack(ctx, ack_nonce);
+    }
+    if payload.as_any().is::<PoisonPill>() {
+        trace!("[{}] received poison pill, shutting down", ctx.address);
+        ctx.shutdown();
+        return None;
+    }
+    if let SenderType::System = ctx.sender {
+        if let Some(stopped) = payload.as_any().downcast_ref::<ChildStopped>() {
+            let child = stopped.address.clone().map(ActorAddress::try_from);
+            if let Some(Ok(child)) = child {
+                trace!("[{}] child {} stopped", ctx.address, child);
+                ctx.children.retain(|c| c.uri != child.uri);
+            }
+            return None;
+        }
+    }
+    Some(payload)
+}
+
+/// Deschedule the actor if its mailbox is empty. Returns `true` if it has messages left.
+fn end_turn(cell: &ActorCell) -> bool {
+    if cell.mailbox.is_empty() {
+        cell.schedule.deschedule(&cell.mailbox);
+        false
+    } else {
+        true
+    }
+}
+
+/// Send an ACK message to the sender (extracted from context)
+fn send_ack(ctx: &Context, nonce: u32) {
+    match ctx.sender {
+        SenderType::Actor(from) => ctx.send(from, ack(nonce)),
+        SenderType::Parent => {
+            if let Some(from) = ctx.parent().as_ref() {
+                ctx.send(from, ack(nonce));
+            }
+        }
+        SenderType::SentToSelf => ctx.send(ctx.address, ack(nonce)),
+        SenderType::System => (),
+    }
+}
+
+/// Hand all messages queued for a stopping (or stopped) actor to the dead letter queue.
+fn drain_to_dead_letters(runtime_manager: &RuntimeManagerRef, cell: &ActorCell) {
+    for envelope in cell.mailbox.try_iter() {
+        let sender = match envelope.sender {
+            SenderType::Actor(sender) => Some(sender),
+            SenderType::Parent => cell.parent.clone(),
+            SenderType::SentToSelf => Some(cell.address.clone()),
+            SenderType::System => None,
+        };
+        runtime_manager.dead_letter(&cell.address, sender, envelope.payload);
+    }
+}
+
+/// Deliver a scheduled message. Messages for actors that have since stopped are dropped.
+pub(crate) fn deliver_scheduled(message: ScheduledMessage) {
+    let envelope = Envelope::new(message.sender, &message.receiver, message.payload, None);
+    let result = match message.receiver.mailbox() {
+        Some(mailbox) => mailbox.send(envelope).is_ok(),
+        None => false,
+    };
+    if !result {
+        debug!(
+            "dropping scheduled message for stopped actor {}",
+            message.receiver
+        );
+    }
+}
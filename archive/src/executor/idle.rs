@@ -0,0 +1,126 @@
+//! Idle handling for executors, implementing the [`IdleStrategy`] configured for the system.
+
+use crate::config::IdleStrategy;
+
+/// What an idle executor should do next while waiting for work
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum IdleAction {
+    Spin,
+    Yield,
+    Park,
+}
+
+/// Tracks how long an executor has been idle and decides whether to spin, yield or park next.
+pub(crate) struct Backoff {
+    strategy: IdleStrategy,
+    /// The current number of spins before yielding, adapted between 0 and `max_spins`
+    spin_limit: u32,
+    /// The number of idle iterations since work was last found
+    idle: u32,
+    /// Whether the executor has parked since work was last found
+    parked: bool,
+}
+
+impl Backoff {
+    pub(crate) fn new(strategy: IdleStrategy) -> Self {
+        let spin_limit = match strategy {
+            IdleStrategy::Park => 0,
+            IdleStrategy::SpinThenPark { max_spins, .. } => max_spins,
+        };
+        Self {
+            strategy,
+            spin_limit,
+            idle: 0,
+            parked: false,
+        }
+    }
+
+    /// The next action for an executor that found no work.
+    pub(crate) fn next(&mut self) -> IdleAction {
+        let action = match self.strategy {
+            IdleStrategy::Park => IdleAction::Park,
+            IdleStrategy::SpinThenPark { yields, .. } => {
+                if self.idle < self.spin_limit {
+                    IdleAction::Spin
+                } else if self.idle - self.spin_limit < yields {
+                    IdleAction::Yield
+                } else {
+                    IdleAction::Park
+                }
+            }
+        };
+        self.idle = self.idle.saturating_add(1);
+        self.parked |= action == IdleAction::Park;
+        action
+    }
+
+    /// Reset the backoff once the executor has found work. If the work arrived while spinning
+    /// (or yielding), spinning paid off and the spin limit is increased. If the executor had to
+    /// park, the spin limit is decreased instead.
+    pub(crate) fn reset(&mut self) {
+        if self.idle == 0 {
+            return;
+        }
+        if let IdleStrategy::SpinThenPark { max_spins, .. } = self.strategy {
+            self.spin_limit = if self.parked {
+                self.spin_limit / 2
+            } else {
+                self.spin_limit.saturating_mul(2).clamp(1, max_spins.max(1))
+            }
+            .min(max_spins);
+        }
+        self.idle = 0;
+        self.parked = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idle_until_park(backoff: &mut Backoff) -> Vec<IdleAction> {
+        let mut actions = vec![backoff.next()];
+        while actions.last() != Some(&IdleAction::Park) {
+            actions.push(backoff.next());
+        }
+        actions
+    }
+
+    #[test]
+    fn test_park() {
+        let mut backoff = Backoff::new(IdleStrategy::Park);
+        assert_eq!(backoff.next(), IdleAction::Park);
+        backoff.reset();
+        assert_eq!(backoff.next(), IdleAction::Park);
+    }
+
+    #[test]
+    fn test_spin_then_park() {
+        let mut backoff = Backoff::new(IdleStrategy::SpinThenPark {
+            max_spins: 4,
+            yields: 2,
+        });
+        use IdleAction::*;
+        assert_eq!(
+            idle_until_park(&mut backoff),
+            vec![Spin, Spin, Spin, Spin, Yield, Yield, Park]
+        );
+
+        // Parking without work arriving halves the number of spins, down to none
+        for expected in [2, 1, 0, 0] {
+            backoff.reset();
+            assert_eq!(backoff.spin_limit, expected);
+            idle_until_park(&mut backoff);
+        }
+        backoff.reset();
+        assert_eq!(idle_until_park(&mut backoff), vec![Yield, Yield, Park]);
+
+        // Work arriving before parking doubles the number of spins, up to the maximum
+        backoff.reset();
+        for expected in [1, 2, 4, 4] {
+            backoff.next();
+            backoff.reset();
+            assert_eq!(backoff.spin_limit, expected);
+        }
+    }
+}
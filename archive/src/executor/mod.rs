@@ -1,21 +1,70 @@
 //! Runtime executor implementations for actors
 
+pub(crate) mod blocking;
+pub(crate) mod deterministic;
+pub(crate) mod dispatch;
+pub(crate) mod idle;
+pub mod placement;
 pub(crate) mod thread_executor;
+pub(crate) mod timer;
+#[cfg(feature = "tokio")]
+pub(crate) mod tokio_executor;
+pub(crate) mod work_stealing;
 
 use crate::actor::{ActorAddress, ActorCell};
-use crate::config::ExecutorType;
+use crate::config::{ExecutorConfig, ExecutorType};
 use crate::system::RuntimeManagerRef;
+use crate::util::lib_macros::channel_send;
 use crate::util::CommandChannel;
+use std::sync::Arc;
+
+pub use timer::ScheduledMessage;
 
 pub enum ExecutorCommands {
     AssignActor(ActorCell),
+    /// Process pending messages of the actor with the given incarnation (see
+    /// [`ActorAddress::incarnation`]). Sent when a message is enqueued for an idle actor.
+    RunActor(u64),
     ShutdownActor(ActorAddress),
     ShutdownActorComplete(ActorAddress),
+    ScheduleMessage(ScheduledMessage),
+    /// Hand the actors and scheduled messages of the executor back to the runtime manager (see
+    /// [`RuntimeManagerRef::executor_drained`]) and shut down. Sent when the executor is removed
+    /// from its group while the system is running.
+    Drain,
     Shutdown,
 }
 
 /// responsible for creating an executor
-pub trait ExecutorFactory {
+///
+/// Besides the built-in executors, systems can run their actors on custom executors by providing
+/// a factory with [`ExecutorType::Custom`]. An executor receives its work through the command
+/// channel and drives the actors assigned to it with the methods of [`ActorCell`]:
+///
+/// - [`AssignActor`](ExecutorCommands::AssignActor): call [`ActorCell::start`], store the cell
+///   by its [`incarnation`](ActorAddress::incarnation) and then call
+///   [`ActorCell::schedule_pending`].
+/// - [`RunActor`](ExecutorCommands::RunActor): call [`ActorCell::run_turn`] (or
+///   [`ActorCell::process_next`]) and schedule the actor again if it has messages left.
+/// - [`ShutdownActor`](ExecutorCommands::ShutdownActor): call [`ActorCell::stop`].
+/// - [`ShutdownActorComplete`](ExecutorCommands::ShutdownActorComplete): remove the cell and call
+///   [`ActorCell::complete_stop`].
+/// - [`ScheduleMessage`](ExecutorCommands::ScheduleMessage): call [`ScheduledMessage::deliver`]
+///   once the [`deadline`](ScheduledMessage::deadline) has passed.
+/// - [`Drain`](ExecutorCommands::Drain): stop running actors, call
+///   [`RuntimeManagerRef::executor_drained`] with the cells of the executor's actors and its
+///   pending scheduled messages (including those still queued on the command channel), then shut
+///   down like for `Shutdown`.
+/// - [`Shutdown`](ExecutorCommands::Shutdown): stop and call
+///   [`RuntimeManagerRef::notify_shutdown`].
+///
+/// Executors never create a [`Context`](crate::actor::Context) themselves: the [`ActorCell`]
+/// methods above create the context for each call into the actor, so they take its place for
+/// custom executors.
+///
+/// Factories are kept by the runtime manager, which uses them to start executors as they are
+/// added to a group. See `tests/custom_executor.rs` for a complete executor.
+pub trait ExecutorFactory: Send + Sync {
     // Spawn an executor with a given name. Tha name will be used by the
     // executor for routing messages to the correct actor.
     fn spawn_executor(
@@ -26,24 +75,62 @@ pub trait ExecutorFactory {
     ) -> ExecutorHandle;
 }
 
+impl<F: ExecutorFactory + ?Sized> ExecutorFactory for Arc<F> {
+    fn spawn_executor(
+        &self,
+        name: String,
+        command_channel: CommandChannel<ExecutorCommands>,
+        manager_ref: RuntimeManagerRef,
+    ) -> ExecutorHandle {
+        (**self).spawn_executor(name, command_channel, manager_ref)
+    }
+}
+
 pub trait Executor {
     fn run(self);
 }
 
+/// Queue of actors with pending messages. When a message arrives for an idle actor, the actor's
+/// mailbox schedules the actor (identified by its incarnation, see
+/// [`ActorAddress::incarnation`]) on the run queue of its executor.
+pub trait RunQueue: Send + Sync {
+    fn schedule(&self, id: u64);
+}
+
+/// Unless an executor provides its own run queue (see [`ExecutorHandle::with_run_queue`]),
+/// actors are scheduled by sending [`ExecutorCommands::RunActor`] to the executor.
+impl RunQueue for CommandChannel<ExecutorCommands> {
+    fn schedule(&self, id: u64) {
+        channel_send!(self, ExecutorCommands::RunActor(id));
+    }
+}
+
 /// ExecutorHandle contains all the context necessary for the control-thread, which
 /// amounts to a channel to send commands through and a way to close or await closing
 /// of the executor.
 pub struct ExecutorHandle {
-    close_fn: Box<dyn FnOnce()>,
+    close_fn: Box<dyn FnOnce() + Send>,
+    run_queue: Option<Arc<dyn RunQueue>>,
 }
 
 impl ExecutorHandle {
-    pub fn new<F: FnOnce() + 'static>(close_fn: F) -> ExecutorHandle {
+    pub fn new<F: FnOnce() + Send + 'static>(close_fn: F) -> ExecutorHandle {
         ExecutorHandle {
             close_fn: Box::new(close_fn),
+            run_queue: None,
         }
     }
 
+    /// Use `run_queue` to schedule the actors assigned to the executor.
+    pub fn with_run_queue(mut self, run_queue: Arc<dyn RunQueue>) -> ExecutorHandle {
+        self.run_queue = Some(run_queue);
+        self
+    }
+
+    pub(crate) fn run_queue(&self) -> Option<Arc<dyn RunQueue>> {
+        self.run_queue.clone()
+    }
+
     /// Close the executor handle. Note that this can only be called once and consumes itself.
     pub(crate) fn await_close(self) {
         (self.close_fn)();
@@ -51,9 +138,35 @@ impl ExecutorHandle {
 }
 
 /// A static function that can be used to convert the config ExecutorType into a concrete
-/// ExecutorFactory.
-pub fn get_executor_factory(executor_type: &ExecutorType) -> Box<dyn ExecutorFactory> {
+/// ExecutorFactory. Panics for `ExecutorType::Deterministic`, whose executors are run by the
+/// system itself.
+pub fn get_executor_factory(config: &ExecutorConfig) -> Box<dyn ExecutorFactory> {
+    executor_factory(&config.executor_type, config)
+}
+
+/// Create an ExecutorFactory for executors of `executor_type` (which may differ from the
+/// `executor_type` of the `config` for executor groups).
+pub(crate) fn executor_factory(
+    executor_type: &ExecutorType,
+    config: &ExecutorConfig,
+) -> Box<dyn ExecutorFactory> {
     match executor_type {
-        ExecutorType::Thread => Box::new(thread_executor::ThreadExecutorFactory {}),
+        ExecutorType::Thread => Box::new(thread_executor::ThreadExecutorFactory {
+            idle_strategy: config.idle_strategy.clone(),
+            throughput: config.throughput,
+        }),
+        ExecutorType::WorkStealing => Box::new(work_stealing::WorkStealingExecutorFactory {
+            idle_strategy: config.idle_strategy.clone(),
+            throughput: config.throughput,
+            pool: Default::default(),
+        }),
+        #[cfg(feature = "tokio")]
+        ExecutorType::Tokio => Box::new(tokio_executor::TokioExecutorFactory {
+            throughput: config.throughput,
+        }),
+        ExecutorType::Deterministic { .. } => {
+            panic!("deterministic executors are run by their actor system")
+        }
+        ExecutorType::Custom(factory) => Box::new(factory.clone()),
     }
 }
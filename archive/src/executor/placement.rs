@@ -0,0 +1,239 @@
+//! Placement strategies decide which executor a newly spawned actor is assigned to.
+//!
+//! The strategy for the system is configured with
+//! [`ActorSystemConfig::placement_strategy`](crate::config::ActorSystemConfig::placement_strategy)
+//! and can be overridden for a single spawn with [`SpawnOptions`](crate::actor::SpawnOptions).
+//! The built-in strategies are:
+//!
+//!   + [`RoundRobin`] (the default) assigns actors to each executor in turn
+//!   + [`LeastLoaded`] assigns actors to the executor with the fewest actors or queued messages
+//!   + [`CoLocateWithParent`] assigns children to the executor of their parent, which avoids
+//!     cross-thread messaging between chatty parents and children
+//!   + [`HashOfName`] assigns actors with the same name to the same executor
+//!
+//! Custom strategies implement [`PlacementStrategy`].
+
+use crate::actor::ActorAddress;
+use crate::util::hash::fnv1a;
+use std::collections::HashMap;
+
+/// Chooses the executor for newly spawned actors. Strategies are called by the runtime manager
+/// for every spawn, so they should be cheap.
+pub trait PlacementStrategy: Send {
+    /// Returns the index of the executor (between `0` and [`PlacementContext::num_executors`])
+    /// to assign the actor to. Out of range indexes wrap around.
+    fn place(&mut self, ctx: &PlacementContext) -> usize;
+}
+
+/// The actor being spawned and the executors it can be assigned to, passed to
+/// [`PlacementStrategy::place`]. Executors are identified by their index in the executor group,
+/// in the order they were added. Indexes only change when executors are removed from the group
+/// (see [`SystemHandle::remove_executor`](crate::SystemHandle::remove_executor)), which shifts the
+/// executors after the removed one.
+pub struct PlacementContext<'a> {
+    name: &'a str,
+    address: &'a ActorAddress,
+    parent_executor: Option<usize>,
+    executors: &'a [String],
+    actor_counts: &'a HashMap<String, usize>,
+    queue_depth: &'a dyn Fn(&str) -> usize,
+}
+
+impl<'a> PlacementContext<'a> {
+    pub(crate) fn new(
+        name: &'a str,
+        address: &'a ActorAddress,
+        parent_executor: Option<usize>,
+        executors: &'a [String],
+        actor_counts: &'a HashMap<String, usize>,
+        queue_depth: &'a dyn Fn(&str) -> usize,
+    ) -> Self {
+        Self {
+            name,
+            address,
+            parent_executor,
+            executors,
+            actor_counts,
+            queue_depth,
+        }
+    }
+
+    /// The name the actor was spawned with (without the suffix added to the names of children).
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// The address of the actor being spawned.
+    pub fn address(&self) -> &ActorAddress {
+        self.address
+    }
+
+    /// The executor of the parent of the actor, or `None` for top-level actors.
+    pub fn parent_executor(&self) -> Option<usize> {
+        self.parent_executor
+    }
+
+    /// The number of executors actors can be assigned to.
+    pub fn num_executors(&self) -> usize {
+        self.executors.len()
+    }
+
+    /// The name of the executor at `index`.
+    pub fn executor_name(&self, index: usize) -> &str {
+        &self.executors[index]
+    }
+
+    /// The number of running actors assigned to the executor at `index`.
+    pub fn actor_count(&self, index: usize) -> usize {
+        self.actor_counts
+            .get(&self.executors[index])
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The number of messages queued for the actors of the executor at `index`. This visits the
+    /// mailboxes of all running actors, so it is more expensive than
+    /// [`actor_count`](Self::actor_count).
+    pub fn queue_depth(&self, index: usize) -> usize {
+        (self.queue_depth)(&self.executors[index])
+    }
+}
+
+/// Assign actors to each executor in turn.
+#[derive(Default)]
+pub struct RoundRobin {
+    next: usize,
+}
+
+impl PlacementStrategy for RoundRobin {
+    fn place(&mut self, ctx: &PlacementContext) -> usize {
+        let index = self.next % ctx.num_executors();
+        self.next = index + 1;
+        index
+    }
+}
+
+/// The measure of load used by [`LeastLoaded`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadMetric {
+    /// The number of running actors on the executor
+    ActorCount,
+    /// The number of messages queued for the actors of the executor
+    QueueDepth,
+}
+
+/// Assign actors to the executor with the lowest load. Ties go to the executor with the lowest
+/// index.
+pub struct LeastLoaded {
+    metric: LoadMetric,
+}
+
+impl LeastLoaded {
+    pub fn new(metric: LoadMetric) -> Self {
+        Self { metric }
+    }
+}
+
+impl PlacementStrategy for LeastLoaded {
+    fn place(&mut self, ctx: &PlacementContext) -> usize {
+        (0..ctx.num_executors())
+            .min_by_key(|&index| match self.metric {
+                LoadMetric::ActorCount => ctx.actor_count(index),
+                LoadMetric::QueueDepth => ctx.queue_depth(index),
+            })
+            .unwrap_or(0)
+    }
+}
+
+/// Assign children to the executor of their parent. Top-level actors (and children whose parent
+/// is no longer running) are placed with the `fallback` strategy.
+pub struct CoLocateWithParent {
+    fallback: Box<dyn PlacementStrategy>,
+}
+
+impl CoLocateWithParent {
+    pub fn new<S: PlacementStrategy + 'static>(fallback: S) -> Self {
+        Self {
+            fallback: Box::new(fallback),
+        }
+    }
+}
+
+impl Default for CoLocateWithParent {
+    fn default() -> Self {
+        Self::new(RoundRobin::default())
+    }
+}
+
+impl PlacementStrategy for CoLocateWithParent {
+    fn place(&mut self, ctx: &PlacementContext) -> usize {
+        ctx.parent_executor()
+            .unwrap_or_else(|| self.fallback.place(ctx))
+    }
+}
+
+/// Assign actors by a (stable) hash of their name, so actors with the same name are assigned to
+/// the same executor as long as the executors of the group don't change.
+#[derive(Default)]
+pub struct HashOfName;
+
+impl PlacementStrategy for HashOfName {
+    fn place(&mut self, ctx: &PlacementContext) -> usize {
+        (fnv1a(ctx.name().as_bytes()) % ctx.num_executors() as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place<S: PlacementStrategy>(
+        strategy: &mut S,
+        name: &str,
+        parent_executor: Option<usize>,
+        actor_counts: &HashMap<String, usize>,
+    ) -> usize {
+        let executors: Vec<_> = (0..3).map(|i| format!("executor-{}", i)).collect();
+        let address = ActorAddress::new_root(name);
+        let queue_depth = |executor: &str| if executor == "executor-1" { 0 } else { 10 };
+        let ctx = PlacementContext::new(
+            name,
+            &address,
+            parent_executor,
+            &executors,
+            actor_counts,
+            &queue_depth,
+        );
+        strategy.place(&ctx)
+    }
+
+    #[test]
+    fn test_placement_strategies() {
+        let counts = HashMap::from([
+            ("executor-0".to_string(), 2),
+            ("executor-1".to_string(), 3),
+            ("executor-2".to_string(), 1),
+        ]);
+
+        let mut round_robin = RoundRobin::default();
+        let placed: Vec<_> = (0..4)
+            .map(|_| place(&mut round_robin, "a", None, &counts))
+            .collect();
+        assert_eq!(placed, vec![0, 1, 2, 0]);
+
+        let mut by_count = LeastLoaded::new(LoadMetric::ActorCount);
+        assert_eq!(place(&mut by_count, "a", None, &counts), 2);
+        let mut by_depth = LeastLoaded::new(LoadMetric::QueueDepth);
+        assert_eq!(place(&mut by_depth, "a", None, &counts), 1);
+
+        let mut co_locate = CoLocateWithParent::new(LeastLoaded::new(LoadMetric::ActorCount));
+        assert_eq!(place(&mut co_locate, "a", Some(1), &counts), 1);
+        assert_eq!(place(&mut co_locate, "a", None, &counts), 2);
+
+        let mut by_name = HashOfName;
+        assert_eq!(
+            place(&mut by_name, "user-1", None, &counts),
+            place(&mut by_name, "user-1", Some(0), &HashMap::new())
+        );
+    }
+}
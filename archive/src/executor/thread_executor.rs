@@ -1,17 +1,23 @@
 use log::{debug, info, trace};
 use std::collections::HashMap;
 use std::thread;
-use std::time::Duration;
+use std::time::Instant;
 
-use crate::actor::{cell_state, ActorCell, Context, SenderType, Uri};
+use crate::actor::ActorCell;
+use crate::config::IdleStrategy;
+use crate::executor::dispatch;
+use crate::executor::idle::{Backoff, IdleAction};
+use crate::executor::timer::TimerQueue;
 use crate::executor::{
     CommandChannel, Executor, ExecutorCommands, ExecutorFactory, ExecutorHandle,
 };
-use crate::message::system::ack;
 use crate::system::RuntimeManagerRef;
-use crate::util::lib_macros::channel_must_recv;
+use crate::util::lib_macros::channel_send;
 
-pub struct ThreadExecutorFactory {}
+pub struct ThreadExecutorFactory {
+    pub(crate) idle_strategy: IdleStrategy,
+    pub(crate) throughput: usize,
+}
 impl ExecutorFactory for ThreadExecutorFactory {
     fn spawn_executor(
         &self,
@@ -19,8 +25,18 @@ impl ExecutorFactory for ThreadExecutorFactory {
         command_channel: CommandChannel<ExecutorCommands>,
         manager_ref: RuntimeManagerRef,
     ) -> ExecutorHandle {
-        let t =
-            thread::spawn(move || ThreadExecutor::init(name, command_channel, manager_ref).run());
+        let idle_strategy = self.idle_strategy.clone();
+        let throughput = self.throughput;
+        let t = thread::spawn(move || {
+            ThreadExecutor::init(
+                name,
+                command_channel,
+                manager_ref,
+                idle_strategy,
+                throughput,
+            )
+            .run()
+        });
         ExecutorHandle::new(move || t.join().unwrap())
     }
 }
@@ -31,122 +47,131 @@ struct ThreadExecutor {
     // for routing decisions
     name: String,
 
-    // map of actor names to actors where the key (actor name) is part of the address-to-actor
-    // resolution
-    actor_cells: HashMap<Uri, ActorCell>,
+    // map of actor incarnations (see `ActorAddress::incarnation`) to actors
+    actor_cells: HashMap<u64, ActorCell>,
 
     // Handle for sending commands to the current executor. Useful for spawning new actors
     // on the main event loop, or any action that may need to be performed in a slightly
-    // delayed manner.
+    // delayed manner. This is also the run queue of the executor: actors with pending
+    // messages are scheduled with `ExecutorCommands::RunActor`.
     command_channel: CommandChannel<ExecutorCommands>,
 
     // Handle for sending message to the manager. This is useful for coordinating system-wide
     // actions such as shutting down the system, spawning new actors, etc.
     runtime_manager: RuntimeManagerRef,
+
+    // Messages scheduled for later delivery by actors on this executor
+    timers: TimerQueue,
+
+    // Decides how to wait for new work when idle. Mailboxes of the executor's actors and the
+    // command channel wake the executor once work arrives.
+    backoff: Backoff,
+
+    // The maximum number of messages an actor processes per turn
+    throughput: usize,
 }
 impl ThreadExecutor {
     fn init(
         name: String,
         command_channel: CommandChannel<ExecutorCommands>,
         runtime_manager: RuntimeManagerRef,
+        idle_strategy: IdleStrategy,
+        throughput: usize,
     ) -> ThreadExecutor {
         ThreadExecutor {
             name,
             actor_cells: HashMap::new(),
             command_channel,
             runtime_manager,
+            timers: TimerQueue::default(),
+            backoff: Backoff::new(idle_strategy),
+            throughput,
         }
     }
 
     /// Utility function for ensuring that the address of an actor is unique. Useful before
     /// inserting a new entry in the actor store (when creating actors).
-    fn assert_unique_address(&self, address: &Uri) {
-        if self.actor_cells.contains_key(address) {
-            panic!("Actor name {} already exists", address);
+    fn assert_unique_address(&self, cell: &ActorCell) {
+        if self.actor_cells.contains_key(&cell.address.incarnation) {
+            panic!("Actor {} already exists", cell.address);
         }
     }
 
-    /// Send an ACK message to the sender (extracted from context)
-    fn send_ack(ctx: &Context, nonce: u32) {
-        match ctx.sender {
-            SenderType::Actor(from) => ctx.send(from, ack(nonce)),
-            SenderType::Parent => {
-                if let Some(from) = ctx.parent().as_ref() {
-                    ctx.send(from, ack(nonce));
-                }
-            }
-            SenderType::SentToSelf => ctx.send(ctx.address, ack(nonce)),
-            SenderType::System => (),
+    /// Run a turn of the actor with incarnation `id` and reschedule the actor if it has
+    /// messages left.
+    fn run_actor(&mut self, id: u64) {
+        let cell = match self.actor_cells.get_mut(&id) {
+            Some(c) => c,
+            // The actor has stopped since it was scheduled
+            None => return,
+        };
+        let has_more = dispatch::run_turn(
+            cell,
+            &self.runtime_manager,
+            &self.command_channel,
+            self.throughput,
+        );
+        if has_more {
+            // Give other actors a turn before processing the remaining messages
+            channel_send!(self.command_channel, ExecutorCommands::RunActor(id));
         }
     }
-}
 
-// Macro for quickly constructing a context object within the thread executor. The construction
-// of the context almost always looks the same, just some slight differences with the sender.
-macro_rules! context {
-    ($self:tt, $cell:tt, $sender:path) => {
-        context!($self, $cell, ($sender))
-    };
-    ($self:tt, $cell:tt, $sender:expr) => {
-        Context {
-            address: &$cell.address,
-            runtime_manager: &$self.runtime_manager,
-            executor_command_channel: &$self.command_channel,
-            parent: &$cell.parent,
-            children: &mut $cell.children,
-            sender: &$sender,
-            cell_state: &mut $cell.state,
-            ack_nonce: &mut $cell.ack_nonce,
+    /// Hand all actors and scheduled messages of the executor back to the runtime manager.
+    fn drain(&mut self) {
+        while let Some(command) = self.command_channel.try_recv() {
+            if let ExecutorCommands::ScheduleMessage(message) = command {
+                self.timers.push(message);
+            }
         }
-    };
+        let actors = self.actor_cells.drain().map(|(_, cell)| cell).collect();
+        self.runtime_manager
+            .executor_drained(self.name.clone(), actors, self.timers.drain());
+    }
 }
 
 impl Executor for ThreadExecutor {
     fn run(mut self) {
-        const SLEEP_DURATION_MS: u64 = 1;
-
         loop {
-            if !self.command_channel.recv_is_empty() {
-                match channel_must_recv!(self.command_channel) {
+            let mut found_work = false;
+            if let Some(command) = self.command_channel.try_recv() {
+                found_work = true;
+                match command {
                     ExecutorCommands::AssignActor(mut cell) => {
                         debug!("received actor assignment for {}", &cell.address.uri);
-                        self.assert_unique_address(&cell.address.uri);
-                        trace!("calling before_start for actor {}", &cell.address.uri);
-                        cell.actor
-                            .before_start(context!(self, cell, SenderType::System));
-                        self.actor_cells.insert(cell.address.uri.clone(), cell);
+                        self.assert_unique_address(&cell);
+                        dispatch::start_actor(
+                            &mut cell,
+                            &self.runtime_manager,
+                            &self.command_channel,
+                        );
+                        // Messages may have been sent while the actor was being assigned
+                        let id = cell.address.incarnation;
+                        self.actor_cells.insert(id, cell);
+                        self.actor_cells[&id].schedule_pending();
                     }
+                    ExecutorCommands::RunActor(id) => self.run_actor(id),
                     ExecutorCommands::ShutdownActor(address) => {
-                        let cell = match self.actor_cells.get_mut(&address.uri) {
-                            Some(c) => c,
-                            None => continue,
-                        };
-                        cell_state::set_shutdown(&mut cell.state);
-                        trace!("calling before_stop for actor {}", &cell.address.uri);
-                        cell.actor
-                            .before_stop(context!(self, cell, SenderType::System));
-
-                        // Inform the runtime manager of the shutdown. The runtime manager
-                        // will take care of shutting down the children first and then send
-                        // a `ShutdownActorComplete` message so we can call `after_stop`.
-                        self.runtime_manager.actor_shutdown_notice(
-                            &address,
-                            cell.parent.clone(),
-                            cell.children.clone(),
-                        );
-
-                        // TODO: Notify the parent and all of the watchers
-                        //       thought: maybe this should be done by the runtime manager when the
-                        //       shutdown signal is sent. (add an additional flag of "notify_parent"
-                        //       to the message we send in `runtime_manager.shutdown_actor`).
+                        if let Some(cell) = self.actor_cells.get_mut(&address.incarnation) {
+                            dispatch::stop_actor(
+                                cell,
+                                &self.runtime_manager,
+                                &self.command_channel,
+                            );
+                        }
                     }
                     ExecutorCommands::ShutdownActorComplete(address) => {
-                        let mut cell = match self.actor_cells.remove(&address.uri) {
-                            Some(c) => c,
-                            None => continue,
-                        };
-                        trace!("calling after_stop for actor {}", &cell.address.uri);
-                        cell.actor.after_stop();
+                        if let Some(mut cell) = self.actor_cells.remove(&address.incarnation) {
+                            dispatch::complete_stop(&mut cell, &self.runtime_manager);
+                        }
+                    }
+                    ExecutorCommands::ScheduleMessage(message) => {
+                        self.timers.push(message);
+                    }
+                    ExecutorCommands::Drain => {
+                        info!("received drain command");
+                        self.drain();
+                        break;
                     }
                     ExecutorCommands::Shutdown => {
                         info!("received shutdown command");
@@ -154,36 +179,86 @@ impl Executor for ThreadExecutor {
                     }
                 }
             }
-            // Iterate over the actor-cells and check if there are any non-empty mailboxes.
-            // If one is found, process a message from it.
-            let mut messages_processed = 0;
-            for (_, cell) in self.actor_cells.iter_mut() {
-                if cell_state::is_shutdown(cell.state) {
-                    // TODO: Forward messages to dead-letter-queue
-                    continue;
-                }
-                if !cell.mailbox.is_empty() {
-                    messages_processed += 1;
-                    let result = cell.mailbox.try_recv();
-                    if let Ok(envelope) = result {
-                        trace!("[{}] processing message: {:?}", &cell.address, &envelope);
-                        let ctx = context!(self, cell, envelope.sender);
-                        if let Some(ack_nonce) = envelope.ack {
-                            trace!("Sending ACK({}) to {}", ack_nonce, ctx.sender);
-                            Self::send_ack(&ctx, ack_nonce);
-                        }
-                        cell.actor.receive(ctx, envelope.payload);
-                    }
-                }
+            // Deliver any scheduled messages that are due
+            let now = Instant::now();
+            while let Some(message) = self.timers.pop_due(now) {
+                found_work = true;
+                dispatch::deliver_scheduled(message);
             }
 
-            // If no messages were processed, sleep for a bit to avoid busy-waiting
-            if messages_processed == 0 {
-                trace!("nothing to do, sleeping...");
-                thread::sleep(Duration::from_millis(SLEEP_DURATION_MS));
+            if found_work {
+                self.backoff.reset();
+                continue;
+            }
+
+            // Nothing to do, so wait for work according to the idle strategy. A parked executor
+            // is woken by new commands (including actors scheduled by their mailbox), or once
+            // the next timer is due.
+            match self.backoff.next() {
+                IdleAction::Spin => std::hint::spin_loop(),
+                IdleAction::Yield => thread::yield_now(),
+                IdleAction::Park => {
+                    let timeout = self
+                        .timers
+                        .next_deadline()
+                        .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+                    trace!("nothing to do, parking...");
+                    self.command_channel.wait(timeout);
+                }
             }
         }
 
         self.runtime_manager.notify_shutdown(self.name);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{ActorSystemConfig, ExecutorConfig};
+    use crate::system::ActorSystem;
+    use crate::util::testing::{string_value, Block, Forward};
+    use std::time::Duration;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn test_actors_take_turns() {
+        let system = ActorSystem::init(ActorSystemConfig {
+            executor_config: ExecutorConfig {
+                num_executors: 1,
+                throughput: 2,
+                ..ExecutorConfig::default()
+            },
+            ..ActorSystemConfig::default()
+        });
+        let handle = system.handle();
+        let inbox = handle.inbox().unwrap();
+        let a = handle
+            .spawn_actor::<Forward, _, _>("a", inbox.address().to_string())
+            .unwrap();
+        let b = handle
+            .spawn_actor::<Forward, _, _>("b", inbox.address().to_string())
+            .unwrap();
+        let block = handle.spawn_actor::<Block, _, _>("block", "").unwrap();
+
+        // Queue up messages for both actors while the executor is busy, so both have more
+        // messages than they can process in a single turn once it is free again
+        inbox.send(&block, "block").unwrap();
+        assert_eq!(string_value(inbox.receive(TIMEOUT).unwrap()), "block");
+        for i in 0..5 {
+            handle.send(&a, format!("a{}", i)).unwrap();
+        }
+        for i in 0..5 {
+            handle.send(&b, format!("b{}", i)).unwrap();
+        }
+
+        let received: Vec<String> = (0..10)
+            .map(|_| string_value(inbox.receive(TIMEOUT).unwrap()))
+            .collect();
+        assert_eq!(
+            received,
+            vec!["a0", "a1", "b0", "b1", "a2", "a3", "b2", "b3", "a4", "b4"]
+        );
+        system.shutdown();
+    }
+}
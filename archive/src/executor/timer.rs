@@ -0,0 +1,142 @@
+//! Timers for messages scheduled with [`Context::send_after`](crate::actor::Context::send_after).
+//! Each executor keeps its own queue of timers and delivers messages as they become due.
+
+use crate::actor::ActorAddress;
+use crate::executor::dispatch;
+use crate::message::Message;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::iter;
+use std::time::Instant;
+
+/// A message to be delivered to `receiver` once `deadline` has passed.
+pub struct ScheduledMessage {
+    pub(crate) deadline: Instant,
+    pub(crate) receiver: ActorAddress,
+    pub(crate) sender: Option<ActorAddress>,
+    pub(crate) payload: Box<dyn Message>,
+}
+
+impl ScheduledMessage {
+    /// The point in time the message is due.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Deliver the message to its receiver. Messages for actors that have since stopped are
+    /// dropped.
+    pub fn deliver(self) {
+        dispatch::deliver_scheduled(self)
+    }
+}
+
+/// Queue of scheduled messages, ordered by deadline. Messages with the same deadline are
+/// delivered in the order they were scheduled.
+#[derive(Default)]
+pub(crate) struct TimerQueue {
+    heap: BinaryHeap<Timer>,
+    sequence: u64,
+}
+
+struct Timer {
+    sequence: u64,
+    message: ScheduledMessage,
+}
+
+impl TimerQueue {
+    pub(crate) fn push(&mut self, message: ScheduledMessage) {
+        self.sequence += 1;
+        self.heap.push(Timer {
+            sequence: self.sequence,
+            message,
+        });
+    }
+
+    /// The deadline of the earliest scheduled message, if any.
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.heap.peek().map(|timer| timer.message.deadline)
+    }
+
+    /// Remove and return the next message that is due at `now`, if any.
+    pub(crate) fn pop_due(&mut self, now: Instant) -> Option<ScheduledMessage> {
+        match self.heap.peek() {
+            Some(timer) if timer.message.deadline <= now => {
+                self.heap.pop().map(|timer| timer.message)
+            }
+            _ => None,
+        }
+    }
+
+    /// Remove and return all messages, in the order they are due.
+    pub(crate) fn drain(&mut self) -> Vec<ScheduledMessage> {
+        iter::from_fn(|| self.heap.pop().map(|timer| timer.message)).collect()
+    }
+}
+
+// `BinaryHeap` is a max-heap, so the ordering is reversed to pop the earliest deadline first.
+impl Ord for Timer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .message
+            .deadline
+            .cmp(&self.message.deadline)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for Timer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Timer {
+    fn eq(&self, other: &Self) -> bool {
+        self.sequence == other.sequence
+    }
+}
+
+impl Eq for Timer {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::common_types::U32Wrapper;
+    use std::time::Duration;
+
+    fn scheduled(deadline: Instant, value: u32) -> ScheduledMessage {
+        ScheduledMessage {
+            deadline,
+            receiver: ActorAddress::new_root("receiver"),
+            sender: None,
+            payload: Box::new(U32Wrapper { value }),
+        }
+    }
+
+    #[test]
+    fn test_timer_order() {
+        let now = Instant::now();
+        let mut timers = TimerQueue::default();
+        timers.push(scheduled(now + Duration::from_millis(20), 1));
+        timers.push(scheduled(now + Duration::from_millis(10), 2));
+        timers.push(scheduled(now + Duration::from_millis(10), 3));
+
+        assert!(timers.pop_due(now).is_none());
+        assert_eq!(
+            timers.next_deadline(),
+            Some(now + Duration::from_millis(10))
+        );
+        let due: Vec<_> = std::iter::from_fn(|| timers.pop_due(now + Duration::from_millis(15)))
+            .map(|m| {
+                m.payload
+                    .as_any()
+                    .downcast_ref::<U32Wrapper>()
+                    .unwrap()
+                    .value
+            })
+            .collect();
+        assert_eq!(due, vec![2, 3]);
+        assert!(timers.pop_due(now + Duration::from_millis(20)).is_some());
+        assert!(timers.pop_due(now + Duration::from_millis(20)).is_none());
+    }
+}
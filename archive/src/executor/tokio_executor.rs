@@ -0,0 +1,402 @@
+//! Executor running its actors as tasks on a tokio runtime. Each executor has a runtime with a
+//! single worker thread, and each actor assigned to the executor is a task on that runtime. This
+//! allows async actors (see [`AsyncActor`](crate::actor::AsyncActor)) to await futures between
+//! (and during) messages, while other actors on the executor keep running.
+//!
+//! Commands for the executor are handled on a thread of its own and forwarded to the tasks of
+//! the actors. Actors with pending messages are woken through the run queue of the executor.
+//! When the executor is drained, the tasks of its actors and scheduled messages return them, so
+//! they can be handed back to the runtime manager.
+
+use log::{debug, info};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::{mem, thread};
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::{watch, Notify};
+use tokio::task::JoinHandle;
+
+use crate::actor::ActorCell;
+use crate::executor::dispatch;
+use crate::executor::timer::ScheduledMessage;
+use crate::executor::{
+    CommandChannel, Executor, ExecutorCommands, ExecutorFactory, ExecutorHandle, RunQueue,
+};
+use crate::system::RuntimeManagerRef;
+
+pub struct TokioExecutorFactory {
+    pub(crate) throughput: usize,
+}
+
+impl ExecutorFactory for TokioExecutorFactory {
+    fn spawn_executor(
+        &self,
+        name: String,
+        command_channel: CommandChannel<ExecutorCommands>,
+        manager_ref: RuntimeManagerRef,
+    ) -> ExecutorHandle {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name(name.clone())
+            .enable_all()
+            .build()
+            .expect("failed to start tokio runtime");
+        let run_queue = Arc::new(TaskRunQueue::default());
+        let executor = TokioExecutor {
+            name,
+            runtime,
+            actors: HashMap::new(),
+            timers: Vec::new(),
+            drain_signal: watch::channel(false).0,
+            run_queue: run_queue.clone(),
+            command_channel,
+            runtime_manager: manager_ref,
+            throughput: self.throughput,
+        };
+        let t = thread::spawn(move || executor.run());
+        ExecutorHandle::new(move || t.join().unwrap()).with_run_queue(run_queue)
+    }
+}
+
+/// Wakes the tasks of actors with pending messages
+#[derive(Default)]
+struct TaskRunQueue {
+    tasks: Mutex<HashMap<u64, Arc<Notify>>>,
+}
+
+impl TaskRunQueue {
+    fn insert(&self, id: u64, notify: Arc<Notify>) {
+        self.tasks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(id, notify);
+    }
+
+    fn remove(&self, id: u64) {
+        self.tasks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&id);
+    }
+}
+
+impl RunQueue for TaskRunQueue {
+    fn schedule(&self, id: u64) {
+        // Actors that are not assigned yet are scheduled once they are (see `AssignActor`).
+        let tasks = self.tasks.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(notify) = tasks.get(&id) {
+            notify.notify_one();
+        }
+    }
+}
+
+/// Lifecycle events forwarded from the executor to the task of an actor
+enum Lifecycle {
+    Stop,
+    CompleteStop,
+    /// Stop running the actor and return its cell
+    Drain,
+}
+
+/// Handle of the task running an actor
+struct TaskHandle {
+    lifecycle: UnboundedSender<Lifecycle>,
+    task: JoinHandle<Option<ActorCell>>,
+}
+
+struct TokioExecutor {
+    name: String,
+    runtime: Runtime,
+    // Tasks of the executor's actors, by incarnation
+    actors: HashMap<u64, TaskHandle>,
+    // Tasks delivering scheduled messages, which return their message if the executor is drained
+    // before it is due
+    timers: Vec<JoinHandle<Option<ScheduledMessage>>>,
+    drain_signal: watch::Sender<bool>,
+    run_queue: Arc<TaskRunQueue>,
+    command_channel: CommandChannel<ExecutorCommands>,
+    runtime_manager: RuntimeManagerRef,
+    throughput: usize,
+}
+
+impl TokioExecutor {
+    fn assign_actor(&mut self, cell: ActorCell) {
+        let id = cell.address.incarnation;
+        let notify = Arc::new(Notify::new());
+        let (lifecycle, lifecycle_receiver) = unbounded_channel();
+        self.run_queue.insert(id, notify.clone());
+
+        // Messages sent while the actor was being assigned could not wake the task, so it is
+        // woken now.
+        cell.schedule_pending();
+        let task = ActorTask {
+            cell,
+            notify,
+            lifecycle: lifecycle_receiver,
+            runtime_manager: self.runtime_manager.clone(),
+            command_channel: self.command_channel.clone(),
+            throughput: self.throughput,
+        };
+        let task = self.runtime.spawn(task.run());
+        self.actors.insert(id, TaskHandle { lifecycle, task });
+    }
+
+    fn schedule_message(&mut self, message: ScheduledMessage) {
+        let mut drained = self.drain_signal.subscribe();
+        self.timers.retain(|timer| !timer.is_finished());
+        self.timers.push(self.runtime.spawn(async move {
+            tokio::select! {
+                _ = tokio::time::sleep_until(message.deadline.into()) => {
+                    dispatch::deliver_scheduled(message);
+                    None
+                }
+                _ = drained.changed() => Some(message),
+            }
+        }));
+    }
+
+    fn send_lifecycle(&self, id: u64, event: Lifecycle) {
+        if let Some(handle) = self.actors.get(&id) {
+            let _ = handle.lifecycle.send(event);
+        }
+    }
+
+    /// Stop the tasks of all actors and scheduled messages and hand them back to the runtime
+    /// manager.
+    fn drain(&mut self) {
+        let _ = self.drain_signal.send(true);
+        let actor_tasks: Vec<_> = self
+            .actors
+            .drain()
+            .map(|(_, handle)| {
+                let _ = handle.lifecycle.send(Lifecycle::Drain);
+                handle.task
+            })
+            .collect();
+        let timer_tasks = mem::take(&mut self.timers);
+        // Actors finish their current turn before returning their cell
+        let (actors, mut timers) = self.runtime.block_on(async move {
+            let mut actors = Vec::new();
+            for task in actor_tasks {
+                if let Ok(Some(cell)) = task.await {
+                    actors.push(cell);
+                }
+            }
+            let mut timers = Vec::new();
+            for task in timer_tasks {
+                if let Ok(Some(message)) = task.await {
+                    timers.push(message);
+                }
+            }
+            (actors, timers)
+        });
+        while let Some(command) = self.command_channel.try_recv() {
+            if let ExecutorCommands::ScheduleMessage(message) = command {
+                timers.push(message);
+            }
+        }
+        self.runtime_manager
+            .executor_drained(self.name.clone(), actors, timers);
+    }
+}
+
+impl Executor for TokioExecutor {
+    fn run(mut self) {
+        // Actors run on the runtime, so there is nothing to do here but wait for commands
+        while let Ok(command) = self.command_channel.recv() {
+            match command {
+                ExecutorCommands::AssignActor(cell) => {
+                    debug!("received actor assignment for {}", &cell.address.uri);
+                    self.assign_actor(cell);
+                }
+                ExecutorCommands::RunActor(id) => self.run_queue.schedule(id),
+                ExecutorCommands::ShutdownActor(address) => {
+                    self.send_lifecycle(address.incarnation, Lifecycle::Stop);
+                }
+                ExecutorCommands::ShutdownActorComplete(address) => {
+                    self.send_lifecycle(address.incarnation, Lifecycle::CompleteStop);
+                    self.actors.remove(&address.incarnation);
+                    self.run_queue.remove(address.incarnation);
+                }
+                ExecutorCommands::ScheduleMessage(message) => self.schedule_message(message),
+                ExecutorCommands::Drain => {
+                    info!("received drain command");
+                    self.drain();
+                    break;
+                }
+                ExecutorCommands::Shutdown => {
+                    info!("received shutdown command");
+                    break;
+                }
+            }
+        }
+
+        // Dropping the runtime stops the tasks of any remaining actors
+        drop(self.runtime);
+        self.runtime_manager.notify_shutdown(self.name);
+    }
+}
+
+/// The task running an actor on the runtime of its executor
+struct ActorTask {
+    cell: ActorCell,
+    notify: Arc<Notify>,
+    lifecycle: UnboundedReceiver<Lifecycle>,
+    runtime_manager: RuntimeManagerRef,
+    command_channel: CommandChannel<ExecutorCommands>,
+    throughput: usize,
+}
+
+impl ActorTask {
+    /// Run the actor until it has stopped, or return its cell once the executor is drained.
+    async fn run(mut self) -> Option<ActorCell> {
+        let (rm, cc) = (&self.runtime_manager, &self.command_channel);
+        dispatch::start_actor_async(&mut self.cell, rm, cc).await;
+        loop {
+            tokio::select! {
+                biased;
+                event = self.lifecycle.recv() => match event {
+                    Some(Lifecycle::Stop) => dispatch::stop_actor(&mut self.cell, rm, cc),
+                    Some(Lifecycle::Drain) => return Some(self.cell),
+                    Some(Lifecycle::CompleteStop) | None => {
+                        dispatch::complete_stop(&mut self.cell, rm);
+                        return None;
+                    }
+                },
+                _ = self.notify.notified() => {
+                    if dispatch::run_turn_async(&mut self.cell, rm, cc, self.throughput).await {
+                        // Give other actors on the executor a turn, and handle lifecycle events
+                        // before the next one
+                        self.notify.notify_one();
+                        tokio::task::yield_now().await;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::actor::{ActorAddress, ActorInit, AsyncActor, Context, SpawnOptions};
+    use crate::config::{ActorSystemConfig, ExecutorConfig, ExecutorGroupConfig, ExecutorType};
+    use crate::message::common_types::StringWrapper;
+    use crate::message::Message;
+    use crate::system::ActorSystem;
+    use crate::util::testing::{string_value, Echo};
+    use std::time::Duration;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn init_system() -> ActorSystem {
+        ActorSystem::init(ActorSystemConfig {
+            executor_config: ExecutorConfig {
+                num_executors: 1,
+                groups: vec![
+                    ExecutorGroupConfig::shared("async", 1).with_executor_type(ExecutorType::Tokio)
+                ],
+                ..ExecutorConfig::default()
+            },
+            ..ActorSystemConfig::default()
+        })
+    }
+
+    fn async_group() -> SpawnOptions {
+        SpawnOptions::new().with_group("async")
+    }
+
+    /// Replies to every message once when it starts processing it and again once it is done,
+    /// awaiting a timer in between
+    struct Slow;
+
+    impl ActorInit for Slow {
+        type Init = StringWrapper;
+
+        fn init(_init_msg: Self::Init) -> Self {
+            Slow
+        }
+    }
+
+    impl AsyncActor for Slow {
+        async fn receive(&mut self, ctx: Context<'_>, msg: Box<dyn Message>) {
+            let value = string_value(msg);
+            ctx.send(ctx.sender(), format!("start {}", value));
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            ctx.send(ctx.sender(), format!("end {}", value));
+        }
+    }
+
+    /// Asks the (synchronous) actor it is initialized with to echo every message, and replies
+    /// with the echo
+    struct Relay {
+        echo: ActorAddress,
+        requesters: Vec<ActorAddress>,
+    }
+
+    impl ActorInit for Relay {
+        type Init = StringWrapper;
+
+        fn init(init_msg: Self::Init) -> Self {
+            Relay {
+                echo: init_msg.value.parse().unwrap(),
+                requesters: Vec::new(),
+            }
+        }
+    }
+
+    impl AsyncActor for Relay {
+        async fn receive(&mut self, ctx: Context<'_>, msg: Box<dyn Message>) {
+            tokio::task::yield_now().await;
+            if ctx.sender().uri == self.echo.uri {
+                let requester = self.requesters.remove(0);
+                ctx.send_message(&requester, msg, None);
+            } else {
+                self.requesters.push(ctx.sender().clone());
+                ctx.send_message(&self.echo, msg, None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_async_receive_processes_one_message_at_a_time() {
+        let system = init_system();
+        let handle = system.handle();
+        let inbox = handle.inbox().unwrap();
+        let slow = handle
+            .spawn_actor_with::<Slow, _, _>("slow", "", async_group())
+            .unwrap();
+
+        // The messages queue up while the first one is awaiting its timer
+        for i in 0..5 {
+            inbox.send(&slow, i.to_string()).unwrap();
+        }
+        let received: Vec<String> = (0..10)
+            .map(|_| string_value(inbox.receive(TIMEOUT).unwrap()))
+            .collect();
+        let expected: Vec<String> = (0..5)
+            .flat_map(|i| [format!("start {}", i), format!("end {}", i)])
+            .collect();
+        assert_eq!(received, expected);
+        system.shutdown();
+    }
+
+    #[test]
+    fn test_sync_and_async_actors_exchange_messages() {
+        let system = init_system();
+        let handle = system.handle();
+        let inbox = handle.inbox().unwrap();
+        let echo = handle.spawn_actor::<Echo, _, _>("echo", "").unwrap();
+        let relay = handle
+            .spawn_actor_with::<Relay, _, _>("relay", echo.to_string(), async_group())
+            .unwrap();
+
+        for i in 0..5 {
+            inbox.send(&relay, i.to_string()).unwrap();
+        }
+        for i in 0..5 {
+            assert_eq!(string_value(inbox.receive(TIMEOUT).unwrap()), i.to_string());
+        }
+        system.shutdown();
+    }
+}
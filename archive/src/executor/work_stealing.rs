@@ -0,0 +1,427 @@
+//! Executor pool in which idle executors steal runnable actors from busy ones.
+//!
+//! Actor cells are shared by all executors of the pool, so an actor can run on any of them.
+//! Each executor has a local queue of runnable actors, and actors scheduled by their mailbox
+//! are pushed to a queue shared by the pool. Executors take actors from their local queue first,
+//! then from the shared queue and finally steal from the local queues of other executors.
+//!
+//! An actor is still only ever run by a single executor at a time: an actor is only queued once
+//! (see `Schedule`) and its cell is locked while it runs. Since the messages of an actor are only
+//! ever taken from its mailbox by the executor running it, migrating an actor preserves the order
+//! of its mailbox.
+//!
+//! Since the actors belong to the pool, an executor removed from the pool (see
+//! `ExecutorCommands::Drain`) only hands back its scheduled messages. Its runnable actors are
+//! queued on the shared queue for the remaining executors.
+
+use crossbeam_deque::{Injector, Steal, Stealer, Worker};
+use log::{debug, info, trace};
+use std::collections::HashMap;
+use std::iter;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
+use std::thread;
+use std::time::Instant;
+
+use crate::actor::ActorCell;
+use crate::config::IdleStrategy;
+use crate::executor::dispatch;
+use crate::executor::idle::{Backoff, IdleAction};
+use crate::executor::timer::TimerQueue;
+use crate::executor::{
+    CommandChannel, Executor, ExecutorCommands, ExecutorFactory, ExecutorHandle, RunQueue,
+};
+use crate::system::RuntimeManagerRef;
+
+/// A cell shared by the executors of the pool, locked while the actor runs.
+type SharedCell = Arc<Mutex<ActorCell>>;
+
+pub struct WorkStealingExecutorFactory {
+    pub(crate) idle_strategy: IdleStrategy,
+    pub(crate) throughput: usize,
+    pub(crate) pool: Arc<Pool>,
+}
+
+impl ExecutorFactory for WorkStealingExecutorFactory {
+    fn spawn_executor(
+        &self,
+        name: String,
+        command_channel: CommandChannel<ExecutorCommands>,
+        manager_ref: RuntimeManagerRef,
+    ) -> ExecutorHandle {
+        let local = Worker::new_fifo();
+        let parked = Arc::new(AtomicBool::new(false));
+        self.pool.add_executor(PoolMember {
+            name: name.clone(),
+            stealer: local.stealer(),
+            command_channel: command_channel.clone(),
+            parked: parked.clone(),
+        });
+
+        let executor = WorkStealingExecutor {
+            name,
+            pool: self.pool.clone(),
+            local,
+            parked,
+            command_channel,
+            runtime_manager: manager_ref,
+            timers: TimerQueue::default(),
+            backoff: Backoff::new(self.idle_strategy.clone()),
+            throughput: self.throughput,
+        };
+        let t = thread::spawn(move || executor.run());
+        ExecutorHandle::new(move || t.join().unwrap()).with_run_queue(self.pool.clone())
+    }
+}
+
+/// State shared by all executors of the pool
+#[derive(Default)]
+pub(crate) struct Pool {
+    /// Runnable actors scheduled by their mailbox
+    injector: Injector<SharedCell>,
+    members: RwLock<Vec<PoolMember>>,
+    /// All actors assigned to the pool, by incarnation
+    cells: RwLock<HashMap<u64, SharedCell>>,
+}
+
+struct PoolMember {
+    name: String,
+    stealer: Stealer<SharedCell>,
+    command_channel: CommandChannel<ExecutorCommands>,
+    /// Set while the executor is (about to be) parked
+    parked: Arc<AtomicBool>,
+}
+
+impl Pool {
+    fn add_executor(&self, member: PoolMember) {
+        self.members
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(member);
+    }
+
+    fn remove_executor(&self, name: &str) {
+        self.members
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|member| member.name != name);
+    }
+
+    fn cell(&self, id: u64) -> Option<SharedCell> {
+        self.cells
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&id)
+            .cloned()
+    }
+
+    fn insert_cell(&self, id: u64, cell: SharedCell) {
+        self.cells
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(id, cell);
+    }
+
+    fn remove_cell(&self, id: u64) -> Option<SharedCell> {
+        self.cells
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&id)
+    }
+
+    /// Queue a runnable actor and wake an executor to run it.
+    fn push(&self, cell: SharedCell) {
+        self.injector.push(cell);
+        self.wake_one();
+    }
+
+    /// Wake one parked executor, if any. Executors mark themselves as parked before checking
+    /// for work a final time, so either the executor finds the work or it is woken here.
+    fn wake_one(&self) {
+        let members = self.members.read().unwrap_or_else(PoisonError::into_inner);
+        let parked = members
+            .iter()
+            .find(|member| member.parked.swap(false, Ordering::SeqCst));
+        if let Some(member) = parked {
+            member.command_channel.notify();
+        }
+    }
+
+    /// Returns true if any executor has runnable actors queued.
+    fn has_runnable(&self) -> bool {
+        !self.injector.is_empty()
+            || self
+                .members
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .any(|member| !member.stealer.is_empty())
+    }
+
+    /// Take a runnable actor from the shared queue (moving a batch to `local`) or, failing
+    /// that, steal one from another executor.
+    fn steal(&self, local: &Worker<SharedCell>) -> Option<SharedCell> {
+        iter::repeat_with(|| {
+            self.injector.steal_batch_and_pop(local).or_else(|| {
+                self.members
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .iter()
+                    .map(|member| member.stealer.steal())
+                    .collect()
+            })
+        })
+        .find(|steal| !steal.is_retry())
+        .and_then(Steal::success)
+    }
+}
+
+impl RunQueue for Pool {
+    fn schedule(&self, id: u64) {
+        // Actors that are not assigned yet are scheduled once they are (see `AssignActor`).
+        if let Some(cell) = self.cell(id) {
+            self.push(cell);
+        }
+    }
+}
+
+fn lock(cell: &SharedCell) -> MutexGuard<'_, ActorCell> {
+    cell.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// An executor of a work-stealing pool. See the [module documentation](self).
+struct WorkStealingExecutor {
+    name: String,
+    pool: Arc<Pool>,
+    // Runnable actors of this executor, which other executors may steal from
+    local: Worker<SharedCell>,
+    parked: Arc<AtomicBool>,
+    command_channel: CommandChannel<ExecutorCommands>,
+    runtime_manager: RuntimeManagerRef,
+    // Messages scheduled for later delivery by actors run on this executor
+    timers: TimerQueue,
+    backoff: Backoff,
+    throughput: usize,
+}
+
+impl WorkStealingExecutor {
+    /// Handle a command. Returns false if the executor should shut down.
+    fn handle_command(&mut self, command: ExecutorCommands) -> bool {
+        match command {
+            ExecutorCommands::AssignActor(mut cell) => {
+                debug!("received actor assignment for {}", &cell.address.uri);
+                dispatch::start_actor(&mut cell, &self.runtime_manager, &self.command_channel);
+                let id = cell.address.incarnation;
+                // Messages sent while the actor was being assigned could not queue the actor, so
+                // it is queued now.
+                let cell = Arc::new(Mutex::new(cell));
+                self.pool.insert_cell(id, cell.clone());
+                lock(&cell).schedule_pending();
+            }
+            ExecutorCommands::RunActor(id) => {
+                if let Some(cell) = self.pool.cell(id) {
+                    self.run_actor(cell);
+                }
+            }
+            ExecutorCommands::ShutdownActor(address) => {
+                if let Some(cell) = self.pool.cell(address.incarnation) {
+                    dispatch::stop_actor(
+                        &mut lock(&cell),
+                        &self.runtime_manager,
+                        &self.command_channel,
+                    );
+                }
+            }
+            ExecutorCommands::ShutdownActorComplete(address) => {
+                if let Some(cell) = self.pool.remove_cell(address.incarnation) {
+                    dispatch::complete_stop(&mut lock(&cell), &self.runtime_manager);
+                }
+            }
+            ExecutorCommands::ScheduleMessage(message) => {
+                self.timers.push(message);
+            }
+            ExecutorCommands::Drain => {
+                info!("received drain command");
+                self.drain();
+                return false;
+            }
+            ExecutorCommands::Shutdown => {
+                info!("received shutdown command");
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Run a turn of the actor and requeue it locally if it has messages left. Other executors
+    /// may then steal the actor.
+    fn run_actor(&mut self, cell: SharedCell) {
+        let has_more = dispatch::run_turn(
+            &mut lock(&cell),
+            &self.runtime_manager,
+            &self.command_channel,
+            self.throughput,
+        );
+        if has_more {
+            self.local.push(cell);
+            if self.local.len() > 1 {
+                self.pool.wake_one();
+            }
+        }
+    }
+
+    /// Leave the pool, leaving the runnable actors of the executor to the remaining executors,
+    /// and hand the scheduled messages of the executor back to the runtime manager.
+    fn drain(&mut self) {
+        self.pool.remove_executor(&self.name);
+        while let Some(cell) = self.local.pop() {
+            self.pool.push(cell);
+        }
+        while let Some(command) = self.command_channel.try_recv() {
+            if let ExecutorCommands::ScheduleMessage(message) = command {
+                self.timers.push(message);
+            }
+        }
+        self.runtime_manager
+            .executor_drained(self.name.clone(), Vec::new(), self.timers.drain());
+    }
+
+    fn next_runnable(&self) -> Option<SharedCell> {
+        self.local.pop().or_else(|| self.pool.steal(&self.local))
+    }
+
+    fn park(&self) {
+        // Mark the executor as parked before the final check for work, so work queued after the
+        // check wakes the executor (see `Pool::wake_one`).
+        self.parked.store(true, Ordering::SeqCst);
+        if self.pool.has_runnable() || !self.command_channel.recv_is_empty() {
+            self.parked.store(false, Ordering::SeqCst);
+            return;
+        }
+        let timeout = self
+            .timers
+            .next_deadline()
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        trace!("nothing to do, parking...");
+        self.command_channel.wait(timeout);
+        self.parked.store(false, Ordering::SeqCst);
+    }
+}
+
+impl Executor for WorkStealingExecutor {
+    fn run(mut self) {
+        loop {
+            let mut found_work = false;
+            if let Some(command) = self.command_channel.try_recv() {
+                found_work = true;
+                if !self.handle_command(command) {
+                    break;
+                }
+            }
+            let now = Instant::now();
+            while let Some(message) = self.timers.pop_due(now) {
+                found_work = true;
+                dispatch::deliver_scheduled(message);
+            }
+            if let Some(cell) = self.next_runnable() {
+                found_work = true;
+                self.run_actor(cell);
+            }
+
+            if found_work {
+                self.backoff.reset();
+                continue;
+            }
+            match self.backoff.next() {
+                IdleAction::Spin => std::hint::spin_loop(),
+                IdleAction::Yield => thread::yield_now(),
+                IdleAction::Park => self.park(),
+            }
+        }
+
+        self.runtime_manager.notify_shutdown(self.name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::actor::{Actor, ActorInit, Context};
+    use crate::config::{ActorSystemConfig, ExecutorConfig, ExecutorType, IdleStrategy};
+    use crate::message::common_types::StringWrapper;
+    use crate::message::Message;
+    use crate::system::ActorSystem;
+    use crate::util::testing::{string_value, Sink};
+    use std::collections::HashSet;
+    use std::thread;
+    use std::time::Duration;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Replies to every message with the message and the thread it was received on
+    struct WhereAmI;
+
+    impl ActorInit for WhereAmI {
+        type Init = StringWrapper;
+
+        fn init(_init_msg: Self::Init) -> Self {
+            WhereAmI
+        }
+    }
+
+    impl Actor for WhereAmI {
+        fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+            let reply = format!("{} {:?}", string_value(msg), thread::current().id());
+            ctx.send(ctx.sender(), reply);
+        }
+    }
+
+    #[test]
+    fn test_hot_actor_migrates_in_order() {
+        let system = ActorSystem::init(ActorSystemConfig {
+            executor_config: ExecutorConfig {
+                num_executors: 4,
+                executor_type: ExecutorType::WorkStealing,
+                // Parked executors are woken to take the hot actor from the shared queue, so it
+                // doesn't stay on the executor that ran it last
+                idle_strategy: IdleStrategy::Park,
+                throughput: 1,
+                ..ExecutorConfig::default()
+            },
+            ..ActorSystemConfig::default()
+        });
+        let handle = system.handle();
+        let inbox = handle.inbox().unwrap();
+        let hot = handle.spawn_actor::<WhereAmI, _, _>("hot", "").unwrap();
+        let idle: Vec<_> = (0..20)
+            .map(|i| {
+                handle
+                    .spawn_actor::<Sink, _, _>(format!("idle-{}", i).as_str(), "")
+                    .unwrap()
+            })
+            .collect();
+
+        // Messages are sent in batches, so the hot actor is queued on the shared queue again
+        // whenever its mailbox runs empty, and every batch checks the order within the queue
+        let mut threads = HashSet::new();
+        for batch in 0..100 {
+            for i in 0..20 {
+                inbox.send(&hot, format!("{}-{}", batch, i)).unwrap();
+            }
+            if batch % 5 == 0 {
+                inbox.send(&idle[batch / 5], "ping").unwrap();
+            }
+            for i in 0..20 {
+                let reply = string_value(inbox.receive(TIMEOUT).unwrap());
+                let (seq, thread) = reply.split_once(' ').unwrap();
+                assert_eq!(seq, format!("{}-{}", batch, i));
+                threads.insert(thread.to_string());
+            }
+        }
+        assert!(
+            threads.len() > 1,
+            "all turns of the hot actor ran on {:?}",
+            threads
+        );
+        system.shutdown();
+    }
+}
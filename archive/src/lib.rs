@@ -15,6 +15,7 @@ pub mod config;
 pub mod error;
 pub mod executor;
 pub mod message;
+pub mod patterns;
 pub mod prelude;
 pub mod util;
 
@@ -22,7 +23,7 @@ pub mod util;
 pub mod system;
 
 #[doc(inline)]
-pub use system::ActorSystem;
+pub use system::{ActorSystem, Inbox, SystemHandle};
 
 #[allow(unused_imports)]
 #[macro_use]
@@ -115,6 +115,12 @@ macro_rules! impl_busan_message {
             fn merge2(&mut self, bytes: &[u8]) -> Result<(), prost::DecodeError> {
                 prost::Message::merge(self, bytes)
             }
+            fn clear2(&mut self) {
+                prost::Message::clear(self)
+            }
+            fn clone_box(&self) -> Box<dyn Message> {
+                Box::new(self.clone())
+            }
         }
     };
 }
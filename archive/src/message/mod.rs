@@ -1,6 +1,7 @@
 //! Core message types used by Busan and primitive type wrappers
 
 pub mod common_types;
+pub mod registry;
 pub mod system;
 
 pub trait Message: prost::Message {
@@ -17,6 +18,15 @@ pub trait Message: prost::Message {
     #[doc(hidden)]
     fn merge2(&mut self, buf: &[u8]) -> Result<(), prost::DecodeError>;
 
+    /// A version of clear that does not have a [`Sized`] requirement
+    #[doc(hidden)]
+    fn clear2(&mut self);
+
+    /// Clone the message into a new box. This allows for a received message to be sent to
+    /// more than one actor (e.g. when broadcasting).
+    #[doc(hidden)]
+    fn clone_box(&self) -> Box<dyn Message>;
+
     #[doc(hidden)]
     fn encoded_len(&self) -> usize {
         prost::Message::encoded_len(self)
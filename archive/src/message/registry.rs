@@ -0,0 +1,145 @@
+//! A process-wide registry of message types, keyed by their fully-qualified protobuf name.
+//!
+//! Since messages are sent as `Box<dyn Message>`, the concrete type of a message is erased once it
+//! leaves the sender. The registry provides the reverse mapping, from a protobuf type name (such
+//! as `"hello_world.Init"`) back to a concrete type, which allows for serialized messages to be
+//! decoded without knowing the type at compile time.
+//!
+//! Types are typically registered through the `register_messages` function generated by the
+//! `busan-build` crate, but may also be registered by hand with [`register`]. Busan's own message
+//! types (such as the [`common_types`](crate::message::common_types) wrappers) are always
+//! registered.
+
+use crate::message::common_types::*;
+use crate::message::system::proto::*;
+use crate::message::Message;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+/// Function used to decode the bytes of a registered message type
+type Decoder = fn(&[u8]) -> Result<Box<dyn Message>, prost::DecodeError>;
+
+static REGISTRY: OnceLock<RwLock<HashMap<String, Decoder>>> = OnceLock::new();
+
+fn registry() -> &'static RwLock<HashMap<String, Decoder>> {
+    REGISTRY.get_or_init(|| {
+        let mut decoders = HashMap::new();
+        register_builtins(&mut decoders);
+        RwLock::new(decoders)
+    })
+}
+
+fn decoder<M: Message + Default + 'static>(
+    bytes: &[u8],
+) -> Result<Box<dyn Message>, prost::DecodeError> {
+    let mut message = M::default();
+    message.merge2(bytes)?;
+    Ok(Box::new(message))
+}
+
+/// Register a message type under the given (fully-qualified) protobuf type name. Registering
+/// the same name more than once replaces the previous registration.
+pub fn register<M: Message + Default + 'static>(type_name: &str) {
+    registry()
+        .write()
+        .unwrap()
+        .insert(normalize(type_name).to_string(), decoder::<M>);
+}
+
+/// Returns `true` if a message type has been registered under `type_name`.
+pub fn is_registered(type_name: &str) -> bool {
+    registry()
+        .read()
+        .unwrap()
+        .contains_key(normalize(type_name))
+}
+
+/// Returns the names of all registered message types.
+pub fn registered_types() -> Vec<String> {
+    registry().read().unwrap().keys().cloned().collect()
+}
+
+/// Decode `bytes` as the message type registered under `type_name`. Returns `None` if no type
+/// has been registered with the given name.
+pub fn decode(
+    type_name: &str,
+    bytes: &[u8],
+) -> Option<Result<Box<dyn Message>, prost::DecodeError>> {
+    let decode_fn = *registry().read().unwrap().get(normalize(type_name))?;
+    Some(decode_fn(bytes))
+}
+
+/// Type names are accepted both with and without the leading `.` that protobuf uses for
+/// fully-qualified names.
+fn normalize(type_name: &str) -> &str {
+    type_name.strip_prefix('.').unwrap_or(type_name)
+}
+
+fn register_builtins(decoders: &mut HashMap<String, Decoder>) {
+    macro_rules! builtin {
+        ($t:ty, $name:expr) => {
+            decoders.insert($name.to_string(), decoder::<$t>);
+        };
+    }
+    builtin!(I32Wrapper, "message.common_types.i32_wrapper");
+    builtin!(I32ListWrapper, "message.common_types.i32_list_wrapper");
+    builtin!(I64Wrapper, "message.common_types.i64_wrapper");
+    builtin!(I64ListWrapper, "message.common_types.i64_list_wrapper");
+    builtin!(U32Wrapper, "message.common_types.u32_wrapper");
+    builtin!(U32ListWrapper, "message.common_types.u32_list_wrapper");
+    builtin!(U64Wrapper, "message.common_types.u64_wrapper");
+    builtin!(U64ListWrapper, "message.common_types.u64_list_wrapper");
+    builtin!(StringWrapper, "message.common_types.string_wrapper");
+    builtin!(
+        StringListWrapper,
+        "message.common_types.string_list_wrapper"
+    );
+    builtin!(BoolWrapper, "message.common_types.bool_wrapper");
+    builtin!(BoolListWrapper, "message.common_types.bool_list_wrapper");
+    builtin!(FloatWrapper, "message.common_types.float_wrapper");
+    builtin!(FloatListWrapper, "message.common_types.float_list_wrapper");
+    builtin!(DoubleWrapper, "message.common_types.double_wrapper");
+    builtin!(
+        DoubleListWrapper,
+        "message.common_types.double_list_wrapper"
+    );
+    builtin!(Ack, "message.system.Ack");
+    builtin!(Nack, "message.system.Nack");
+    builtin!(Terminated, "message.system.Terminated");
+    builtin!(PoisonPill, "message.system.PoisonPill");
+    builtin!(BlockingTaskFailed, "message.system.BlockingTaskFailed");
+    builtin!(Listing, "message.system.Listing");
+    builtin!(ChildStopped, "message.system.ChildStopped");
+    builtin!(
+        crate::actor::proto::ActorAddress,
+        "actor.proto.ActorAddress"
+    );
+    builtin!(crate::actor::proto::AddressList, "actor.proto.AddressList");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_round_trip() {
+        let bytes = StringWrapper {
+            value: "hello".to_string(),
+        }
+        .encode_to_vec2();
+        let decoded = decode(".message.common_types.string_wrapper", &bytes)
+            .expect("builtin type is registered")
+            .unwrap();
+        let decoded = decoded.as_any().downcast_ref::<StringWrapper>().unwrap();
+        assert_eq!(decoded.value, "hello");
+    }
+
+    #[test]
+    fn test_register() {
+        assert!(!is_registered("registry.test.Custom"));
+        register::<U64Wrapper>(".registry.test.Custom");
+        assert!(is_registered("registry.test.Custom"));
+        assert!(registered_types().contains(&"registry.test.Custom".to_string()));
+        assert!(decode("registry.test.Missing", &[]).is_none());
+    }
+}
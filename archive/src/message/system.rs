@@ -4,11 +4,24 @@ pub mod proto {
 
     include!(concat!(env!("OUT_DIR"), "/message.system.rs"));
     impl_busan_message!(Ack);
+    impl_busan_message!(Nack);
+    impl_busan_message!(Terminated);
+    impl_busan_message!(PoisonPill);
+    impl_busan_message!(Listing);
+    impl_busan_message!(BlockingTaskFailed);
+    impl_busan_message!(ChildStopped);
 }
 
-pub use proto::Ack;
+pub use proto::{Ack, BlockingTaskFailed, ChildStopped, Listing, Nack, PoisonPill, Terminated};
 
 /// Create an ACK message given a nonce.
 pub fn ack(nonce: u32) -> proto::Ack {
     proto::Ack { nonce }
 }
+
+/// Create a NACK message given the reason the message could not be processed.
+pub fn nack(reason: &str) -> proto::Nack {
+    proto::Nack {
+        reason: reason.to_string(),
+    }
+}
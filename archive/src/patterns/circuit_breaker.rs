@@ -0,0 +1,518 @@
+//! Circuit breaker for actors that front unreliable (often external) systems.
+//!
+//! A [`CircuitBreaker`] is placed in front of a target actor. Messages (calls) sent to the
+//! breaker are passed through to the target and replies from the target are relayed back to
+//! the caller. The breaker counts failed calls, which are:
+//!
+//!   + Calls that are not replied to within the call timeout (the caller receives a
+//!     [`CallTimedOut`] message)
+//!   + Calls replied to with a [`Nack`] (which is relayed to the caller)
+//!   + Termination of the target (see [`Context::watch`])
+//!
+//! After `max_failures` consecutive failures, the circuit _opens_ and calls are rejected
+//! immediately with a [`CircuitOpen`] reply rather than piling up in the target's mailbox. Once
+//! the reset timeout has elapsed the circuit becomes _half-open_ and a single trial call is
+//! let through. If it succeeds the circuit _closes_ again, otherwise it re-opens. While
+//! half-open, only the outcome of the trial call counts: late replies to (or timeouts of) calls
+//! made before the circuit opened are ignored.
+//!
+//! Each call is sent to the target on behalf of a short-lived reply handler (a child of the
+//! breaker), so every reply is matched to its own call no matter the order in which the target
+//! replies. The target is expected to reply to the sender of a call at most once. Replies to
+//! calls that have already timed out are dropped. If a reply handler can't be started, the
+//! caller receives a [`Nack`] and the call counts as failed.
+
+use crate::actor::{Actor, ActorAddress, ActorInit, Context};
+use crate::message::system::{nack, Nack, PoisonPill, Terminated};
+use crate::message::{Message, ToMessage};
+use log::{debug, trace, warn};
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub mod proto {
+    use crate::message::common_types::impl_busan_message;
+    use crate::message::Message;
+
+    include!(concat!(env!("OUT_DIR"), "/patterns.circuit_breaker.rs"));
+
+    impl_busan_message!(CircuitBreakerInit);
+    impl_busan_message!(CircuitOpen);
+    impl_busan_message!(CallTimedOut);
+    impl_busan_message!(CallTimeout);
+    impl_busan_message!(ResetTimeout);
+    impl_busan_message!(ReplyHandlerInit);
+}
+
+pub use proto::{CallTimedOut, CircuitBreakerInit, CircuitOpen};
+use proto::{CallTimeout, ReplyHandlerInit, ResetTimeout};
+
+const DEFAULT_MAX_FAILURES: u32 = 5;
+const DEFAULT_CALL_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_RESET_TIMEOUT: Duration = Duration::from_secs(30);
+
+impl CircuitBreakerInit {
+    /// Create the initialization message for a [`CircuitBreaker`] in front of `target`, with
+    /// default thresholds and timeouts.
+    pub fn new(target: &ActorAddress) -> Self {
+        Self {
+            target: Some(target.to_message()),
+            ..Default::default()
+        }
+    }
+}
+
+/// State of a circuit breaker
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls are passed through to the target
+    Closed,
+    /// Calls are rejected
+    Open,
+    /// A single trial call is passed through to decide whether to close or re-open
+    HalfOpen,
+}
+
+/// A call passed through to the target that has not yet been replied to or timed out
+struct PendingCall {
+    caller: Option<ActorAddress>,
+    /// The reply handler the call was sent from
+    handler: ActorAddress,
+}
+
+/// Circuit breaker actor. See the [module documentation](self) for details.
+pub struct CircuitBreaker {
+    target: Option<ActorAddress>,
+    breaker: Breaker,
+    call_timeout: Duration,
+    reset_timeout: Duration,
+    pending: HashMap<u64, PendingCall>,
+    next_call_id: u64,
+}
+
+impl ActorInit for CircuitBreaker {
+    type Init = CircuitBreakerInit;
+
+    fn init(init_msg: Self::Init) -> Self {
+        let millis_or = |millis, default| match millis {
+            0 => default,
+            millis => Duration::from_millis(millis),
+        };
+        let max_failures = match init_msg.max_failures {
+            0 => DEFAULT_MAX_FAILURES,
+            max_failures => max_failures,
+        };
+        Self {
+            target: init_msg
+                .target
+                .map(ActorAddress::try_from)
+                .and_then(Result::ok),
+            breaker: Breaker::new(max_failures),
+            call_timeout: millis_or(init_msg.call_timeout_ms, DEFAULT_CALL_TIMEOUT),
+            reset_timeout: millis_or(init_msg.reset_timeout_ms, DEFAULT_RESET_TIMEOUT),
+            pending: HashMap::new(),
+            next_call_id: 0,
+        }
+    }
+}
+
+impl CircuitBreaker {
+    fn record_failure(&mut self, ctx: &Context, call_id: u64) {
+        if self.breaker.on_failure(call_id) {
+            warn!("{}: circuit opened", ctx.address());
+            ctx.send_after(ctx.address(), ResetTimeout {}, self.reset_timeout);
+        }
+    }
+
+    fn reject(ctx: &Context, caller: Option<&ActorAddress>) {
+        if let Some(caller) = caller {
+            ctx.send(caller, CircuitOpen {});
+        }
+    }
+
+    fn call(&mut self, ctx: &mut Context, target: &ActorAddress, msg: Box<dyn Message>) {
+        let id = self.next_call_id;
+        if !self.breaker.allow_call(id) {
+            trace!("{}: circuit open, rejecting call", ctx.address());
+            Self::reject(ctx, ctx.sender_address());
+            return;
+        }
+        self.next_call_id += 1;
+        let handler = match ctx
+            .spawn_child::<ReplyHandler, _, _>("call", ReplyHandlerInit {})
+            .await_ready()
+        {
+            Ok(handler) => handler,
+            Err(e) => {
+                warn!("{}: unable to pass call through: {}", ctx.address(), e);
+                if let Some(caller) = ctx.sender_address() {
+                    ctx.send(caller, nack(&e.to_string()));
+                }
+                self.record_failure(ctx, id);
+                return;
+            }
+        };
+        ctx.send_from(target, Some(&handler), msg);
+        self.pending.insert(
+            id,
+            PendingCall {
+                caller: ctx.sender_address().cloned(),
+                handler,
+            },
+        );
+        ctx.send_after(
+            ctx.address(),
+            CallTimeout { call_id: id },
+            self.call_timeout,
+        );
+    }
+
+    /// Handle a reply from the target, relayed by the reply handler of the call.
+    fn reply(&mut self, ctx: &Context, target: &ActorAddress, msg: Box<dyn Message>) {
+        let handler = ctx.sender();
+        let id = match self
+            .pending
+            .iter()
+            .find(|(_, call)| call.handler.uri == handler.uri)
+        {
+            Some((id, _)) => *id,
+            None => {
+                trace!("{}: dropping late reply from {}", ctx.address(), handler);
+                return;
+            }
+        };
+        let call = self.pending.remove(&id).unwrap();
+        if msg.as_any().is::<Nack>() {
+            self.record_failure(ctx, id);
+        } else if self.breaker.on_success(id) {
+            debug!("{}: circuit closed", ctx.address());
+        }
+        if let Some(caller) = call.caller {
+            ctx.send_from(&caller, Some(target), msg);
+        }
+    }
+
+    fn timeout(&mut self, ctx: &Context, call_id: u64) {
+        if let Some(call) = self.pending.remove(&call_id) {
+            trace!("{}: call {} timed out", ctx.address(), call_id);
+            // Stop the handler, so that a late reply goes to dead letters
+            ctx.send(&call.handler, PoisonPill {});
+            if let Some(caller) = call.caller.as_ref() {
+                ctx.send(caller, CallTimedOut {});
+            }
+            self.record_failure(ctx, call_id);
+        }
+    }
+
+    /// The target has terminated, so no more replies will be received. Outstanding calls are
+    /// failed and the circuit is opened.
+    fn target_terminated(&mut self, ctx: &Context) {
+        warn!("{}: target terminated", ctx.address());
+        for (_, call) in self.pending.drain() {
+            ctx.send(&call.handler, PoisonPill {});
+            Self::reject(ctx, call.caller.as_ref());
+        }
+        if self.breaker.trip() {
+            ctx.send_after(ctx.address(), ResetTimeout {}, self.reset_timeout);
+        }
+    }
+}
+
+impl Actor for CircuitBreaker {
+    fn before_start(&mut self, ctx: Context) {
+        match self.target.as_ref() {
+            Some(target) => ctx.watch(target),
+            None => warn!("{}: no valid target address given", ctx.address()),
+        }
+    }
+
+    fn receive(&mut self, mut ctx: Context, msg: Box<dyn Message>) {
+        let target = match self.target.clone() {
+            Some(target) => target,
+            None => return Self::reject(&ctx, ctx.sender_address()),
+        };
+
+        if let Some(timeout) = msg.as_any().downcast_ref::<CallTimeout>() {
+            self.timeout(&ctx, timeout.call_id);
+        } else if msg.as_any().is::<ResetTimeout>() {
+            if self.breaker.on_reset_timeout() {
+                debug!("{}: circuit half-open", ctx.address());
+            }
+        } else if let Some(terminated) = msg.as_any().downcast_ref::<Terminated>() {
+            let address = terminated
+                .address
+                .clone()
+                .map(ActorAddress::try_from)
+                .and_then(Result::ok);
+            if address.is_some_and(|address| address.uri == target.uri) {
+                self.target_terminated(&ctx);
+            }
+        } else if ctx
+            .sender_address()
+            .is_some_and(|sender| sender.is_parent(ctx.address()))
+        {
+            self.reply(&ctx, &target, msg);
+        } else {
+            self.call(&mut ctx, &target, msg);
+        }
+    }
+}
+
+/// Receives the reply to a single call and passes it on to the breaker (its parent), with
+/// itself as the sender to identify the call. Stops after the first reply.
+struct ReplyHandler;
+
+impl ActorInit for ReplyHandler {
+    type Init = ReplyHandlerInit;
+
+    fn init(_init_msg: Self::Init) -> Self {
+        ReplyHandler
+    }
+}
+
+impl Actor for ReplyHandler {
+    fn receive(&mut self, mut ctx: Context, msg: Box<dyn Message>) {
+        if let Some(breaker) = ctx.parent() {
+            ctx.send_message(breaker, msg, None);
+        }
+        ctx.shutdown();
+    }
+}
+
+/// The circuit breaker state machine, independent of message handling.
+struct Breaker {
+    state: CircuitState,
+    failures: u32,
+    max_failures: u32,
+    /// The id of the trial call let through while half-open, if it has not completed yet
+    trial: Option<u64>,
+}
+
+impl Breaker {
+    fn new(max_failures: u32) -> Self {
+        Self {
+            state: CircuitState::Closed,
+            failures: 0,
+            max_failures,
+            trial: None,
+        }
+    }
+
+    /// Whether call `call_id` should be let through to the target. While half-open, the first
+    /// call let through becomes the trial call.
+    fn allow_call(&mut self, call_id: u64) -> bool {
+        match self.state {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen if self.trial.is_some() => false,
+            CircuitState::HalfOpen => {
+                self.trial = Some(call_id);
+                true
+            }
+        }
+    }
+
+    /// Record a successful call, returning `true` if the circuit closed as a result.
+    fn on_success(&mut self, call_id: u64) -> bool {
+        match self.state {
+            CircuitState::Closed => {
+                self.failures = 0;
+                false
+            }
+            CircuitState::HalfOpen if self.is_trial(call_id) => {
+                self.state = CircuitState::Closed;
+                self.failures = 0;
+                self.trial = None;
+                true
+            }
+            CircuitState::HalfOpen | CircuitState::Open => false,
+        }
+    }
+
+    /// Record a failed call, returning `true` if the circuit opened as a result.
+    fn on_failure(&mut self, call_id: u64) -> bool {
+        match self.state {
+            CircuitState::Closed => {
+                self.failures += 1;
+                self.failures >= self.max_failures && self.trip()
+            }
+            CircuitState::HalfOpen if self.is_trial(call_id) => self.trip(),
+            CircuitState::HalfOpen | CircuitState::Open => false,
+        }
+    }
+
+    fn is_trial(&self, call_id: u64) -> bool {
+        self.trial == Some(call_id)
+    }
+
+    /// Open the circuit, returning `true` if it was not already open.
+    fn trip(&mut self) -> bool {
+        let opened = self.state != CircuitState::Open;
+        self.state = CircuitState::Open;
+        self.trial = None;
+        opened
+    }
+
+    /// Move from open to half-open, returning `true` if the state changed.
+    fn on_reset_timeout(&mut self) -> bool {
+        if self.state == CircuitState::Open {
+            self.state = CircuitState::HalfOpen;
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ActorSystemConfig, ExecutorConfig, ExecutorType};
+    use crate::message::common_types::StringWrapper;
+    use crate::system::ActorSystem;
+
+    /// Ignores the first message it receives. Other messages are held until two have arrived,
+    /// which are then replied to in reverse order.
+    struct Target {
+        ignored_first: bool,
+        held: Vec<(ActorAddress, Box<dyn Message>)>,
+    }
+
+    impl ActorInit for Target {
+        type Init = StringWrapper;
+
+        fn init(_init_msg: Self::Init) -> Self {
+            Target {
+                ignored_first: false,
+                held: vec![],
+            }
+        }
+    }
+
+    impl Actor for Target {
+        fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+            if !self.ignored_first {
+                self.ignored_first = true;
+                return;
+            }
+            self.held.push((ctx.sender().clone(), msg));
+            if self.held.len() == 2 {
+                while let Some((sender, msg)) = self.held.pop() {
+                    ctx.send_message(&sender, msg, None);
+                }
+            }
+        }
+    }
+
+    fn reply_value(reply: Box<dyn Message>) -> String {
+        reply
+            .as_any()
+            .downcast_ref::<StringWrapper>()
+            .unwrap()
+            .value
+            .clone()
+    }
+
+    #[test]
+    fn test_replies_matched_to_calls() {
+        let system = ActorSystem::init(ActorSystemConfig {
+            executor_config: ExecutorConfig {
+                executor_type: ExecutorType::Deterministic { seed: 7 },
+                ..ExecutorConfig::default()
+            },
+            ..ActorSystemConfig::default()
+        });
+        let handle = system.handle();
+        let target = handle.spawn_actor::<Target, _, _>("target", "").unwrap();
+        let init = CircuitBreakerInit {
+            call_timeout_ms: 1000,
+            ..CircuitBreakerInit::new(&target)
+        };
+        let breaker = handle
+            .spawn_actor::<CircuitBreaker, _, _>("breaker", init)
+            .unwrap();
+        let (first, second) = (handle.inbox().unwrap(), handle.inbox().unwrap());
+
+        // The first call is never replied to and times out
+        first.send(&breaker, "lost").unwrap();
+        let reply = first.receive(Duration::from_secs(2)).unwrap();
+        assert!(reply.as_any().is::<CallTimedOut>());
+
+        // Replies arriving after a timed out call, and out of order, reach their own callers
+        first.send(&breaker, "first").unwrap();
+        system.run_until_idle();
+        second.send(&breaker, "second").unwrap();
+        assert_eq!(
+            reply_value(first.receive(Duration::from_millis(500)).unwrap()),
+            "first"
+        );
+        assert_eq!(
+            reply_value(second.receive(Duration::from_millis(500)).unwrap()),
+            "second"
+        );
+
+        // No further replies (or timeouts) for calls that were replied to
+        system.advance_time(Duration::from_secs(2));
+        assert!(first.try_receive().is_none());
+        assert!(second.try_receive().is_none());
+        system.shutdown();
+    }
+
+    #[test]
+    fn test_breaker_opens_after_max_failures() {
+        let mut breaker = Breaker::new(3);
+        assert!(breaker.allow_call(0));
+        assert!(!breaker.on_failure(0));
+        assert!(!breaker.on_failure(1));
+        // A success resets the count of consecutive failures
+        breaker.on_success(2);
+        assert!(!breaker.on_failure(3));
+        assert!(!breaker.on_failure(4));
+        assert!(breaker.on_failure(5));
+        assert_eq!(breaker.state, CircuitState::Open);
+        assert!(!breaker.allow_call(6));
+        // Further failures (e.g. late timeouts) do not re-open the circuit
+        assert!(!breaker.on_failure(7));
+    }
+
+    #[test]
+    fn test_breaker_half_open() {
+        let mut breaker = Breaker::new(1);
+        assert!(breaker.on_failure(0));
+        assert!(breaker.on_reset_timeout());
+        assert_eq!(breaker.state, CircuitState::HalfOpen);
+
+        // Only a single trial call is allowed, and its failure re-opens the circuit
+        assert!(breaker.allow_call(1));
+        assert!(!breaker.allow_call(2));
+        assert!(breaker.on_failure(1));
+        assert_eq!(breaker.state, CircuitState::Open);
+
+        // A successful trial closes the circuit
+        assert!(breaker.on_reset_timeout());
+        assert!(breaker.allow_call(3));
+        assert!(breaker.on_success(3));
+        assert_eq!(breaker.state, CircuitState::Closed);
+        assert!(breaker.allow_call(4));
+        assert!(breaker.allow_call(5));
+    }
+
+    #[test]
+    fn test_breaker_half_open_ignores_stale_results() {
+        let mut breaker = Breaker::new(1);
+        // Two calls made while closed, the second of which fails and opens the circuit
+        assert!(breaker.allow_call(0));
+        assert!(breaker.allow_call(1));
+        assert!(breaker.on_failure(1));
+        assert!(breaker.on_reset_timeout());
+        assert!(breaker.allow_call(2));
+
+        // A late reply to (or timeout of) the first call doesn't decide the trial
+        assert!(!breaker.on_success(0));
+        assert_eq!(breaker.state, CircuitState::HalfOpen);
+        assert!(!breaker.on_failure(0));
+        assert_eq!(breaker.state, CircuitState::HalfOpen);
+        assert!(!breaker.allow_call(3));
+
+        assert!(breaker.on_success(2));
+        assert_eq!(breaker.state, CircuitState::Closed);
+    }
+}
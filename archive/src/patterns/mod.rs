@@ -0,0 +1,8 @@
+//! Common actor patterns built on top of the core actor primitives.
+
+pub mod circuit_breaker;
+mod resizer;
+pub mod router;
+pub mod scatter_gather;
+pub mod throttle;
+pub mod work_pulling;
@@ -0,0 +1,155 @@
+//! Resizing logic for [`PoolRouter`](super::router::PoolRouter). The resizer only decides
+//! _how much_ the pool should change by; spawning and retiring routees is left to the router.
+
+use crate::patterns::router::ResizerConfig;
+use std::time::{Duration, Instant};
+
+impl ResizerConfig {
+    /// Create a resizer configuration with the given bounds and the following defaults:
+    ///
+    ///   + `pressure_threshold` - 1 (any queued message counts as pressure)
+    ///   + `rampup_rate` - 0.2
+    ///   + `backoff_threshold` - 0.3
+    ///   + `backoff_rate` - 0.1
+    ///   + `messages_per_resize` - 10
+    ///   + `cooldown_ms` - 1000
+    pub fn new(lower_bound: u32, upper_bound: u32) -> Self {
+        Self {
+            lower_bound,
+            upper_bound,
+            pressure_threshold: 1,
+            rampup_rate: 0.2,
+            backoff_threshold: 0.3,
+            backoff_rate: 0.1,
+            messages_per_resize: 10,
+            cooldown_ms: 1000,
+        }
+    }
+}
+
+pub(crate) struct Resizer {
+    config: ResizerConfig,
+    messages_since_check: u32,
+    last_resize: Option<Instant>,
+}
+
+impl Resizer {
+    pub(crate) fn new(mut config: ResizerConfig) -> Self {
+        config.lower_bound = config.lower_bound.max(1);
+        config.upper_bound = config.upper_bound.max(config.lower_bound);
+        config.messages_per_resize = config.messages_per_resize.max(1);
+        Self {
+            config,
+            messages_since_check: 0,
+            last_resize: None,
+        }
+    }
+
+    /// Clamp a pool size to the bounds of the resizer.
+    pub(crate) fn clamp(&self, size: usize) -> usize {
+        size.clamp(
+            self.config.lower_bound as usize,
+            self.config.upper_bound as usize,
+        )
+    }
+
+    /// Record a routed message, returning `true` when a resize check is due.
+    pub(crate) fn is_time_for_resize(&mut self) -> bool {
+        self.messages_since_check += 1;
+        if self.messages_since_check >= self.config.messages_per_resize {
+            self.messages_since_check = 0;
+            return true;
+        }
+        false
+    }
+
+    /// Compute the change in pool size given the current mailbox size of each routee. A
+    /// positive value means routees should be added, a negative value that routees should be
+    /// retired.
+    pub(crate) fn resize(&mut self, mailbox_lens: &[usize], now: Instant) -> isize {
+        let size = mailbox_lens.len();
+        let target = self.clamp(size);
+        // Bounds are always enforced, regardless of cooldown
+        if target != size {
+            self.last_resize = Some(now);
+            return target as isize - size as isize;
+        }
+
+        let cooldown = Duration::from_millis(self.config.cooldown_ms);
+        if let Some(last) = self.last_resize {
+            if now.saturating_duration_since(last) < cooldown {
+                return 0;
+            }
+        }
+
+        let busy = mailbox_lens
+            .iter()
+            .filter(|len| **len >= self.config.pressure_threshold as usize)
+            .count();
+        let proposed = if busy >= size {
+            ((size as f64 * self.config.rampup_rate).ceil() as isize).max(1)
+        } else if (busy as f64 / size as f64) < self.config.backoff_threshold {
+            -((size as f64 * self.config.backoff_rate).floor() as isize).max(1)
+        } else {
+            0
+        };
+
+        let target = self.clamp((size as isize + proposed).max(0) as usize);
+        let delta = target as isize - size as isize;
+        if delta != 0 {
+            self.last_resize = Some(now);
+        }
+        delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resizer(cooldown_ms: u64) -> Resizer {
+        Resizer::new(ResizerConfig {
+            cooldown_ms,
+            ..ResizerConfig::new(2, 6)
+        })
+    }
+
+    #[test]
+    fn test_resize_within_bounds() {
+        let mut resizer = resizer(0);
+        let now = Instant::now();
+        // All routees busy, grow by 20% (at least one)
+        assert_eq!(resizer.resize(&[3, 1, 2], now), 1);
+        // Never grow above the upper bound
+        assert_eq!(resizer.resize(&[3; 6], now), 0);
+        // Mostly idle, shrink by 10% (at least one)
+        assert_eq!(resizer.resize(&[0, 0, 0, 1], now), -1);
+        // Never shrink below the lower bound
+        assert_eq!(resizer.resize(&[0, 0], now), 0);
+        // Some pressure, but not enough to resize
+        assert_eq!(resizer.resize(&[0, 1, 1], now), 0);
+        // Out-of-bounds pools are corrected
+        assert_eq!(resizer.resize(&[0], now), 1);
+        assert_eq!(resizer.resize(&[0; 8], now), -2);
+    }
+
+    #[test]
+    fn test_resize_cooldown() {
+        let mut resizer = resizer(1000);
+        let now = Instant::now();
+        assert_eq!(resizer.resize(&[1, 1, 1], now), 1);
+        assert_eq!(resizer.resize(&[1, 1, 1, 1], now), 0);
+        assert_eq!(
+            resizer.resize(&[1, 1, 1, 1], now + Duration::from_millis(1000)),
+            1
+        );
+    }
+
+    #[test]
+    fn test_messages_per_resize() {
+        let mut resizer = resizer(0);
+        let due: Vec<_> = (0..20).map(|_| resizer.is_time_for_resize()).collect();
+        assert_eq!(due.iter().filter(|d| **d).count(), 2);
+        assert!(due[9] && due[19]);
+    }
+}
@@ -0,0 +1,626 @@
+//! Router actors for distributing messages across a set of actors (routees).
+//!
+//! Two kinds of routers are available:
+//!
+//!   + [`PoolRouter`] - spawns and owns its routees (as children of the router)
+//!   + [`GroupRouter`] - routes to a set of existing actors, given by address
+//!
+//! Both routers support the same set of [`RoutingStrategy`]s and forward messages with the
+//! original sender preserved, so routees reply directly to the sender and not to the router.
+//! Routees are watched by the router and removed from the set of routees once they terminate.
+//! When no routees remain, the router stops itself.
+//!
+//! A [`PoolRouter`] may also be given a [`ResizerConfig`] (see [`PoolRouterInit::with_resizer`])
+//! to grow and shrink the pool, within bounds, based on how many routees have queued messages.
+//! Resize checks happen as messages are routed and are rate-limited by a cooldown. Routees are
+//! retired gracefully with a [`PoisonPill`], so work already queued for them is not lost.
+//!
+//! ```rust,no_run
+//! # use busan::prelude::*;
+//! # use busan::message::common_types::I32Wrapper;
+//! use busan::patterns::router::{PoolRouter, PoolRouterInit, RoutingStrategy};
+//! # struct Worker {}
+//! # impl ActorInit for Worker {
+//! #     type Init = I32Wrapper;
+//! #     fn init(_: I32Wrapper) -> Self { Worker {} }
+//! # }
+//! # impl Actor for Worker {}
+//! # struct Root {}
+//! impl Actor for Root {
+//!     fn before_start(&mut self, mut ctx: Context) {
+//!         let router = ctx
+//!             .spawn_child::<PoolRouter<Worker>, _, _>(
+//!                 "workers",
+//!                 PoolRouterInit::new(RoutingStrategy::RoundRobin, 4, 0),
+//!             )
+//!             .await_unwrap();
+//!         ctx.send(&router, "some work");
+//!     }
+//! }
+//! ```
+
+use crate::actor::{Actor, ActorAddress, ActorInit, Context};
+use crate::message::system::{PoisonPill, Terminated};
+use crate::message::{Message, ToMessage};
+use crate::patterns::resizer::Resizer;
+use crate::util::hash::fnv1a;
+use crate::util::rng::Rng;
+use log::{debug, error, trace, warn};
+use std::marker::PhantomData;
+
+pub mod proto {
+    use crate::message::common_types::impl_busan_message;
+    use crate::message::Message;
+
+    include!(concat!(env!("OUT_DIR"), "/patterns.router.rs"));
+
+    impl_busan_message!(PoolRouterInit);
+    impl_busan_message!(GroupRouterInit);
+    impl_busan_message!(ResizerConfig);
+}
+
+pub use proto::{GroupRouterInit, PoolRouterInit, ResizerConfig, RoutingStrategy};
+
+const DEFAULT_VIRTUAL_NODES: u32 = 10;
+
+impl PoolRouterInit {
+    /// Create the initialization message for a [`PoolRouter`] with `pool_size` routees, each
+    /// initialized with `routee_init`.
+    pub fn new<M: Message, T: ToMessage<M>>(
+        strategy: RoutingStrategy,
+        pool_size: u32,
+        routee_init: T,
+    ) -> Self {
+        Self {
+            strategy: strategy as i32,
+            pool_size,
+            routee_init: routee_init.to_message().encode_to_vec2(),
+            virtual_nodes: 0,
+            resizer: None,
+        }
+    }
+
+    /// Resize the pool based on mailbox pressure. See [`ResizerConfig`] for details.
+    pub fn with_resizer(mut self, resizer: ResizerConfig) -> Self {
+        self.resizer = Some(resizer);
+        self
+    }
+}
+
+impl GroupRouterInit {
+    /// Create the initialization message for a [`GroupRouter`] routing to `routees`.
+    pub fn new(strategy: RoutingStrategy, routees: &[ActorAddress]) -> Self {
+        Self {
+            strategy: strategy as i32,
+            routees: Some(routees.to_message()),
+            virtual_nodes: 0,
+        }
+    }
+}
+
+/// `HashKey` derives the key used by [`RoutingStrategy::ConsistentHash`] from a message.
+///
+/// Implementations typically downcast the message to the types they know about and return an
+/// identifying field (such as an entity ID). When `None` is returned, the encoded bytes of the
+/// message are used as the key.
+pub trait HashKey: Send + 'static {
+    fn hash_key(msg: &dyn Message) -> Option<Vec<u8>>;
+}
+
+/// The default [`HashKey`], which uses the encoded bytes of the message as the key.
+pub struct EncodedMessageKey;
+
+impl HashKey for EncodedMessageKey {
+    fn hash_key(msg: &dyn Message) -> Option<Vec<u8>> {
+        Some(msg.encode_to_vec2())
+    }
+}
+
+/// Router that spawns `pool_size` routees of type `A` as its children. See the
+/// [module documentation](self) for details.
+pub struct PoolRouter<A, K = EncodedMessageKey> {
+    pool_size: u32,
+    routee_init: Vec<u8>,
+    routing: Routing<K>,
+    resizer: Option<Resizer>,
+    _routee: PhantomData<fn() -> A>,
+}
+
+impl<A, K> ActorInit for PoolRouter<A, K>
+where
+    A: ActorInit + Actor + 'static,
+    A::Init: Default,
+    K: HashKey,
+{
+    type Init = PoolRouterInit;
+
+    fn init(init_msg: Self::Init) -> Self {
+        let resizer = init_msg.resizer.clone().map(Resizer::new);
+        let pool_size = match &resizer {
+            Some(resizer) => resizer.clamp(init_msg.pool_size as usize) as u32,
+            None => init_msg.pool_size,
+        };
+        Self {
+            pool_size,
+            routing: Routing::new(init_msg.strategy(), init_msg.virtual_nodes),
+            routee_init: init_msg.routee_init,
+            resizer,
+            _routee: PhantomData,
+        }
+    }
+}
+
+impl<A, K> PoolRouter<A, K>
+where
+    A: ActorInit + Actor + 'static,
+    A::Init: Default,
+    K: HashKey,
+{
+    /// Spawn a new routee and add it to the set of routees. Returns `None` (after logging the
+    /// error) if the routee could not be spawned.
+    fn spawn_routee(&mut self, ctx: &mut Context) -> Option<ActorAddress> {
+        let mut init = A::Init::default();
+        if let Err(e) = init.merge2(&self.routee_init) {
+            error!(
+                "{}: unable to decode routee init message: {}",
+                ctx.address(),
+                e
+            );
+            return None;
+        }
+        let routee = match ctx
+            .spawn_child::<A, A::Init, A::Init>("routee", init)
+            .await_ready()
+        {
+            Ok(routee) => routee,
+            Err(e) => {
+                error!("{}: unable to spawn routee: {}", ctx.address(), e);
+                return None;
+            }
+        };
+        ctx.watch(&routee);
+        self.routing.add_routee(routee.clone());
+        Some(routee)
+    }
+
+    /// Grow or shrink the pool if a resize is due. Retired routees are removed from routing
+    /// immediately and sent a [`PoisonPill`] so that any messages already queued for them are
+    /// still processed.
+    fn maybe_resize(&mut self, ctx: &mut Context) {
+        let resizer = match self.resizer.as_mut() {
+            Some(resizer) => resizer,
+            None => return,
+        };
+        if !resizer.is_time_for_resize() {
+            return;
+        }
+        let mailbox_lens: Vec<_> = self
+            .routing
+            .routees
+            .iter()
+            .map(|routee| routee.mailbox_len().unwrap_or(0))
+            .collect();
+        let delta = resizer.resize(&mailbox_lens, ctx.now());
+        if delta != 0 {
+            debug!(
+                "{}: resizing pool from {} by {}",
+                ctx.address(),
+                mailbox_lens.len(),
+                delta
+            );
+        }
+        if delta > 0 {
+            for _ in 0..delta {
+                self.spawn_routee(ctx);
+            }
+        } else if delta < 0 {
+            // Retire the least busy routees, which will finish their queued work soonest
+            let mut retiring: Vec<_> = self
+                .routing
+                .routees
+                .iter()
+                .zip(mailbox_lens)
+                .map(|(routee, len)| (len, routee.clone()))
+                .collect();
+            retiring.sort_by_key(|(len, _)| *len);
+            for (_, routee) in retiring.into_iter().take(delta.unsigned_abs()) {
+                self.routing.remove_routee(&routee);
+                ctx.send(&routee, PoisonPill {});
+            }
+        }
+    }
+}
+
+impl<A, K> Actor for PoolRouter<A, K>
+where
+    A: ActorInit + Actor + 'static,
+    A::Init: Default,
+    K: HashKey,
+{
+    fn before_start(&mut self, mut ctx: Context) {
+        self.routing.rng = ctx.rng();
+        for _ in 0..self.pool_size {
+            self.spawn_routee(&mut ctx);
+        }
+        if self.pool_size > 0 && self.routing.routees.is_empty() {
+            warn!("{}: no routees could be spawned, stopping", ctx.address());
+            ctx.shutdown();
+        }
+    }
+
+    fn receive(&mut self, mut ctx: Context, msg: Box<dyn Message>) {
+        if !msg.as_any().is::<Terminated>() {
+            self.maybe_resize(&mut ctx);
+        }
+        self.routing.receive(&mut ctx, msg);
+    }
+}
+
+/// Router that routes to a group of existing actors. See the [module documentation](self) for
+/// details.
+pub struct GroupRouter<K = EncodedMessageKey> {
+    routing: Routing<K>,
+}
+
+impl<K: HashKey> ActorInit for GroupRouter<K> {
+    type Init = GroupRouterInit;
+
+    fn init(init_msg: Self::Init) -> Self {
+        let mut routing = Routing::new(init_msg.strategy(), init_msg.virtual_nodes);
+        for address in init_msg.routees.unwrap_or_default().addresses {
+            match ActorAddress::try_from(address) {
+                Ok(address) => routing.add_routee(address),
+                Err(e) => warn!("ignoring invalid routee address: {}", e),
+            }
+        }
+        Self { routing }
+    }
+}
+
+impl<K: HashKey> Actor for GroupRouter<K> {
+    fn before_start(&mut self, ctx: Context) {
+        self.routing.rng = ctx.rng();
+        for routee in self.routing.routees.iter() {
+            // Resolve up-front so that mailbox sizes are available for routing decisions
+            ctx.resolve(routee);
+            ctx.watch(routee);
+        }
+    }
+
+    fn receive(&mut self, mut ctx: Context, msg: Box<dyn Message>) {
+        self.routing.receive(&mut ctx, msg);
+    }
+}
+
+/// The routee(s) a message should be sent to
+#[derive(Debug, PartialEq)]
+enum Selection {
+    One(usize),
+    All,
+    Nobody,
+}
+
+/// Routee selection state shared by the pool and group routers.
+struct Routing<K> {
+    strategy: RoutingStrategy,
+    routees: Vec<ActorAddress>,
+    round_robin_state: usize,
+    rng: Rng,
+    /// Hash ring for consistent hashing, as sorted `(hash, routee index)` pairs
+    ring: Vec<(u64, usize)>,
+    virtual_nodes: u32,
+    _key: PhantomData<fn() -> K>,
+}
+
+impl<K: HashKey> Routing<K> {
+    fn new(strategy: RoutingStrategy, virtual_nodes: u32) -> Self {
+        Self {
+            strategy,
+            routees: Vec::new(),
+            round_robin_state: 0,
+            rng: Rng::from_entropy(),
+            ring: Vec::new(),
+            virtual_nodes: if virtual_nodes == 0 {
+                DEFAULT_VIRTUAL_NODES
+            } else {
+                virtual_nodes
+            },
+            _key: PhantomData,
+        }
+    }
+
+    fn add_routee(&mut self, routee: ActorAddress) {
+        self.routees.push(routee);
+        self.rebuild_ring();
+    }
+
+    /// Remove a routee, returning `true` if the routee was found.
+    fn remove_routee(&mut self, routee: &ActorAddress) -> bool {
+        let len = self.routees.len();
+        self.routees.retain(|r| r.uri != routee.uri);
+        self.rebuild_ring();
+        len != self.routees.len()
+    }
+
+    fn rebuild_ring(&mut self) {
+        if self.strategy != RoutingStrategy::ConsistentHash {
+            return;
+        }
+        self.ring = self
+            .routees
+            .iter()
+            .enumerate()
+            .flat_map(|(index, routee)| {
+                (0..self.virtual_nodes)
+                    .map(move |node| (fnv1a(format!("{}-{}", routee.uri, node).as_bytes()), index))
+            })
+            .collect();
+        self.ring.sort_unstable();
+    }
+
+    fn select(&mut self, msg: &dyn Message) -> Selection {
+        if self.routees.is_empty() {
+            return Selection::Nobody;
+        }
+        match self.strategy {
+            RoutingStrategy::RoundRobin => {
+                let index = self.round_robin_state % self.routees.len();
+                self.round_robin_state = self.round_robin_state.wrapping_add(1);
+                Selection::One(index)
+            }
+            RoutingStrategy::Random => Selection::One(self.rng.gen_range(self.routees.len())),
+            RoutingStrategy::Broadcast => Selection::All,
+            RoutingStrategy::ConsistentHash => {
+                let key = K::hash_key(msg).unwrap_or_else(|| msg.encode_to_vec2());
+                let hash = fnv1a(&key);
+                // First point on the ring at or after the hash, wrapping around to the start
+                let point = self.ring.partition_point(|(h, _)| *h < hash) % self.ring.len();
+                Selection::One(self.ring[point].1)
+            }
+            RoutingStrategy::SmallestMailbox => {
+                let (index, _) = self
+                    .routees
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, routee)| routee.mailbox_len().unwrap_or(0))
+                    .unwrap();
+                Selection::One(index)
+            }
+        }
+    }
+
+    fn receive(&mut self, ctx: &mut Context, msg: Box<dyn Message>) {
+        // Routers only watch their routees, so termination notices are never routed. Retired
+        // routees have already been removed by the time their notice arrives.
+        if let Some(terminated) = msg.as_any().downcast_ref::<Terminated>() {
+            let address = terminated
+                .address
+                .clone()
+                .map(ActorAddress::try_from)
+                .and_then(Result::ok);
+            if let Some(address) = address {
+                if self.remove_routee(&address) {
+                    trace!("{}: routee {} terminated", ctx.address(), address);
+                    if self.routees.is_empty() {
+                        warn!("{}: all routees terminated, stopping", ctx.address());
+                        ctx.shutdown();
+                    }
+                }
+            }
+            return;
+        }
+
+        match self.select(msg.as_ref()) {
+            Selection::One(index) => ctx.forward(&self.routees[index], msg),
+            Selection::All => {
+                let (last, rest) = self.routees.split_last().unwrap();
+                for routee in rest {
+                    ctx.forward(routee, msg.clone_box());
+                }
+                ctx.forward(last, msg);
+            }
+            Selection::Nobody => {
+                warn!("{}: no routees available for message", ctx.address());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ActorSystemConfig, ExecutorConfig, ExecutorType};
+    use crate::message::common_types::{StringWrapper, U32Wrapper};
+    use crate::system::ActorSystem;
+    use crate::util::testing::{string_value, Echo};
+    use std::time::Duration;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn routing(strategy: RoutingStrategy, routees: usize) -> Routing<EncodedMessageKey> {
+        let mut routing = Routing::new(strategy, 0);
+        for i in 0..routees {
+            routing.add_routee(ActorAddress::new_root(&format!("routee-{}", i)));
+        }
+        routing
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let mut routing = routing(RoutingStrategy::RoundRobin, 3);
+        let msg = U32Wrapper { value: 1 };
+        let selected: Vec<_> = (0..6).map(|_| routing.select(&msg)).collect();
+        assert_eq!(
+            selected,
+            vec![
+                Selection::One(0),
+                Selection::One(1),
+                Selection::One(2),
+                Selection::One(0),
+                Selection::One(1),
+                Selection::One(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_routees() {
+        let mut routing = routing(RoutingStrategy::Random, 0);
+        assert_eq!(routing.select(&U32Wrapper { value: 1 }), Selection::Nobody);
+    }
+
+    #[test]
+    fn test_broadcast() {
+        let mut routing = routing(RoutingStrategy::Broadcast, 3);
+        assert_eq!(routing.select(&U32Wrapper { value: 1 }), Selection::All);
+    }
+
+    struct ValueKey;
+    impl HashKey for ValueKey {
+        fn hash_key(msg: &dyn Message) -> Option<Vec<u8>> {
+            msg.as_any()
+                .downcast_ref::<StringWrapper>()
+                .map(|m| m.value.as_bytes().to_vec())
+        }
+    }
+
+    #[test]
+    fn test_consistent_hash() {
+        let mut routing: Routing<ValueKey> = Routing::new(RoutingStrategy::ConsistentHash, 0);
+        for i in 0..5 {
+            routing.add_routee(ActorAddress::new_root(&format!("routee-{}", i)));
+        }
+        let keys: Vec<_> = (0..100)
+            .map(|i| StringWrapper {
+                value: format!("entity-{}", i),
+            })
+            .collect();
+        let before: Vec<_> = keys.iter().map(|k| routing.select(k)).collect();
+
+        // The same key is always routed to the same routee
+        let again: Vec<_> = keys.iter().map(|k| routing.select(k)).collect();
+        assert_eq!(before, again);
+
+        // Routees are placed on the ring by their URI, so restarted routees (with a new
+        // incarnation) are assigned the same keys
+        let mut restarted: Routing<ValueKey> = Routing::new(RoutingStrategy::ConsistentHash, 0);
+        for i in 0..5 {
+            restarted.add_routee(ActorAddress::new_root(&format!("routee-{}", i)));
+        }
+        assert_ne!(
+            restarted.routees[0].incarnation(),
+            routing.routees[0].incarnation()
+        );
+        let after_restart: Vec<_> = keys.iter().map(|k| restarted.select(k)).collect();
+        assert_eq!(before, after_restart);
+
+        // Removing a routee only moves the keys that were assigned to it
+        let removed = routing.routees[2].clone();
+        assert!(routing.remove_routee(&removed));
+        for (key, previous) in keys.iter().zip(before.iter()) {
+            let previous = match previous {
+                Selection::One(index) => *index,
+                _ => panic!("expected a single routee"),
+            };
+            if previous == 2 {
+                continue;
+            }
+            let current = match routing.select(key) {
+                Selection::One(index) => index,
+                _ => panic!("expected a single routee"),
+            };
+            // Routees after the removed one have shifted down by one
+            let expected = if previous > 2 { previous - 1 } else { previous };
+            assert_eq!(current, expected);
+        }
+    }
+
+    fn deterministic_system() -> ActorSystem {
+        ActorSystem::init(ActorSystemConfig {
+            executor_config: ExecutorConfig {
+                executor_type: ExecutorType::Deterministic { seed: 3 },
+                ..ExecutorConfig::default()
+            },
+            ..ActorSystemConfig::default()
+        })
+    }
+
+    /// Spawn a round-robin pool of `size` echo routees, returning the router and its routees.
+    fn spawn_echo_pool(system: &ActorSystem, size: u32) -> (ActorAddress, Vec<ActorAddress>) {
+        let router = system
+            .handle()
+            .spawn_actor::<PoolRouter<Echo>, _, _>(
+                "router",
+                PoolRouterInit::new(RoutingStrategy::RoundRobin, size, 0),
+            )
+            .unwrap();
+        system.run_until_idle();
+        let routees = system
+            .select(&format!("{}/*", router.uri))
+            .unwrap()
+            .resolve();
+        assert_eq!(routees.len(), size as usize);
+        (router, routees)
+    }
+
+    #[test]
+    fn test_forward_keeps_sender() {
+        let system = deterministic_system();
+        let (router, _) = spawn_echo_pool(&system, 3);
+        let inbox = system.handle().inbox().unwrap();
+
+        // Routees echo to the sender of the message, which is the inbox and not the router
+        for i in 0..3 {
+            inbox.send(&router, i.to_string()).unwrap();
+        }
+        let mut replies: Vec<_> = (0..3)
+            .map(|_| string_value(inbox.receive(TIMEOUT).unwrap()))
+            .collect();
+        replies.sort();
+        assert_eq!(replies, vec!["0", "1", "2"]);
+        system.shutdown();
+    }
+
+    #[test]
+    fn test_terminated_routees_removed() {
+        let system = deterministic_system();
+        let (router, routees) = spawn_echo_pool(&system, 2);
+        let inbox = system.handle().inbox().unwrap();
+
+        inbox.send(&routees[0], PoisonPill {}).unwrap();
+        system.run_until_idle();
+
+        // No messages are routed to the terminated routee (and lost)
+        for i in 0..4 {
+            inbox.send(&router, i.to_string()).unwrap();
+        }
+        for _ in 0..4 {
+            assert!(inbox.receive(TIMEOUT).is_ok());
+        }
+        let remaining = system
+            .select(&format!("{}/*", router.uri))
+            .unwrap()
+            .resolve();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].uri, routees[1].uri);
+        system.shutdown();
+    }
+
+    #[test]
+    fn test_router_stops_without_routees() {
+        let system = deterministic_system();
+        let (router, routees) = spawn_echo_pool(&system, 2);
+        let inbox = system.handle().inbox().unwrap();
+
+        for routee in &routees {
+            inbox.send(routee, PoisonPill {}).unwrap();
+        }
+        system.run_until_idle();
+        assert!(system
+            .select(&router.uri.to_string())
+            .unwrap()
+            .resolve()
+            .is_empty());
+        system.shutdown();
+    }
+}
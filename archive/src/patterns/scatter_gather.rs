@@ -0,0 +1,390 @@
+//! Scatter-gather: send a request to many actors and collect the replies into a single result.
+//!
+//! [`scatter_gather`] spawns an aggregator (as a child of the current actor) and sends the
+//! request to every recipient on the aggregator's behalf, so that recipients reply to the
+//! aggregator with a regular `ctx.send(ctx.sender(), ...)`. The aggregator collects replies
+//! until all recipients have replied, the quorum has been reached or the timeout has passed.
+//! It then sends a single [`ScatterGatherResult`] to the requester and stops.
+//!
+//! ```rust,no_run
+//! # use busan::prelude::*;
+//! # use busan::actor::ActorAddress;
+//! # use busan::message::common_types::{StringWrapper, U64Wrapper};
+//! use busan::patterns::scatter_gather::{scatter_gather, ScatterGatherInit, ScatterGatherResult};
+//! # struct Query { shards: Vec<ActorAddress> }
+//! impl Actor for Query {
+//!     fn before_start(&mut self, mut ctx: Context) {
+//!         let init = ScatterGatherInit::new(&self.shards).with_quorum(2);
+//!         if let Err(e) = scatter_gather::<StringWrapper, _>(&mut ctx, init, "count(*)") {
+//!             eprintln!("unable to query shards: {}", e);
+//!         }
+//!     }
+//!
+//!     fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+//!         if let Some(result) = msg.as_any().downcast_ref::<ScatterGatherResult>() {
+//!             for response in result.responses.iter() {
+//!                 let count = response.decode::<U64Wrapper>().unwrap();
+//!                 // ...
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Replies are kept as encoded bytes since they may be of any type. Use [`Response::decode`]
+//! to decode them into the expected reply type.
+
+use crate::actor::{Actor, ActorAddress, ActorInit, Context};
+use crate::error::BusanError;
+use crate::message::{Message, ToMessage};
+use log::{trace, warn};
+use prost::DecodeError;
+use std::time::Duration;
+
+pub mod proto {
+    use crate::message::common_types::impl_busan_message;
+    use crate::message::Message;
+
+    include!(concat!(env!("OUT_DIR"), "/patterns.scatter_gather.rs"));
+
+    impl_busan_message!(ScatterGatherInit);
+    impl_busan_message!(Response);
+    impl_busan_message!(ScatterGatherResult);
+    impl_busan_message!(GatherTimeout);
+}
+
+use proto::GatherTimeout;
+pub use proto::{Response, ScatterGatherInit, ScatterGatherResult};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl ScatterGatherInit {
+    /// Create the initialization message for an aggregator sending to `recipients`.
+    pub fn new(recipients: &[ActorAddress]) -> Self {
+        Self {
+            recipients: Some(recipients.to_message()),
+            ..Default::default()
+        }
+    }
+
+    /// Deliver the result once `quorum` replies have been received.
+    pub fn with_quorum(mut self, quorum: u32) -> Self {
+        self.quorum = quorum;
+        self
+    }
+
+    /// Deliver the result after `timeout`, even if not all replies have been received.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout_ms = timeout.as_millis() as u64;
+        self
+    }
+}
+
+impl Response {
+    /// Decode the reply into the expected message type.
+    pub fn decode<M: Message + Default>(&self) -> Result<M, DecodeError> {
+        let mut message = M::default();
+        message.merge2(&self.payload)?;
+        Ok(message)
+    }
+}
+
+impl ScatterGatherResult {
+    /// The recipients that had not replied when the result was delivered.
+    pub fn missing_addresses(&self) -> Vec<ActorAddress> {
+        self.missing
+            .iter()
+            .flat_map(|list| list.addresses.iter())
+            .filter_map(|address| ActorAddress::try_from(address.clone()).ok())
+            .collect()
+    }
+}
+
+/// Send `request` to all recipients in `init` and gather the replies. The result is sent to the
+/// current actor as a [`ScatterGatherResult`] from the returned aggregator address, which can
+/// be used to tell concurrent scatter-gathers apart. Returns an error (and sends nothing) if the
+/// aggregator could not be spawned.
+pub fn scatter_gather<M: Message + 'static, T: ToMessage<M>>(
+    ctx: &mut Context,
+    init: ScatterGatherInit,
+    request: T,
+) -> Result<ActorAddress, BusanError> {
+    let recipients = recipient_addresses(&init);
+    let aggregator = ctx
+        .spawn_child::<Aggregator, _, _>("scatter-gather", init)
+        .await_ready()?;
+    let request = request.to_message();
+    for recipient in recipients.iter() {
+        ctx.send_from(recipient, Some(&aggregator), request.clone_box());
+    }
+    Ok(aggregator)
+}
+
+fn recipient_addresses(init: &ScatterGatherInit) -> Vec<ActorAddress> {
+    init.recipients
+        .iter()
+        .flat_map(|list| list.addresses.iter())
+        .filter_map(|address| match ActorAddress::try_from(address.clone()) {
+            Ok(address) => Some(address),
+            Err(e) => {
+                warn!("ignoring invalid recipient address: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Aggregator actor spawned by [`scatter_gather`].
+pub struct Aggregator {
+    gather: Gather,
+    timeout: Duration,
+}
+
+impl ActorInit for Aggregator {
+    type Init = ScatterGatherInit;
+
+    fn init(init_msg: Self::Init) -> Self {
+        let timeout = match init_msg.timeout_ms {
+            0 => DEFAULT_TIMEOUT,
+            millis => Duration::from_millis(millis),
+        };
+        Self {
+            gather: Gather::new(recipient_addresses(&init_msg), init_msg.quorum as usize),
+            timeout,
+        }
+    }
+}
+
+impl Aggregator {
+    fn deliver(&mut self, mut ctx: Context, timed_out: bool) {
+        let result = self.gather.result(timed_out);
+        if let Some(requester) = ctx.parent() {
+            ctx.send(requester, result);
+        }
+        ctx.shutdown();
+    }
+}
+
+impl Actor for Aggregator {
+    fn before_start(&mut self, ctx: Context) {
+        if self.gather.quorum == 0 {
+            // Nothing to wait for (no recipients)
+            return self.deliver(ctx, false);
+        }
+        ctx.send_after(ctx.address(), GatherTimeout {}, self.timeout);
+    }
+
+    fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+        if self.gather.delivered {
+            return;
+        }
+        if msg.as_any().is::<GatherTimeout>() {
+            trace!("{}: timed out waiting for replies", ctx.address());
+            return self.deliver(ctx, true);
+        }
+        let responder = match ctx.sender_address() {
+            Some(responder) => responder.clone(),
+            None => return,
+        };
+        if self.gather.reply(&responder, msg.as_ref()) {
+            self.deliver(ctx, false);
+        }
+    }
+}
+
+/// Reply bookkeeping for the aggregator, independent of message handling.
+struct Gather {
+    pending: Vec<ActorAddress>,
+    responses: Vec<Response>,
+    quorum: usize,
+    delivered: bool,
+}
+
+impl Gather {
+    fn new(recipients: Vec<ActorAddress>, quorum: usize) -> Self {
+        let quorum = match quorum {
+            0 => recipients.len(),
+            quorum => quorum.min(recipients.len()),
+        };
+        Self {
+            pending: recipients,
+            responses: Vec::new(),
+            quorum,
+            delivered: false,
+        }
+    }
+
+    /// Record a reply, returning `true` once the result should be delivered. Replies from
+    /// actors that are not recipients (or have already replied) are ignored.
+    fn reply(&mut self, responder: &ActorAddress, msg: &dyn Message) -> bool {
+        let index = self.pending.iter().position(|p| p.uri == responder.uri);
+        if let Some(index) = index {
+            self.pending.remove(index);
+            self.responses.push(Response {
+                responder: Some(responder.to_message()),
+                payload: msg.encode_to_vec2(),
+            });
+        }
+        self.responses.len() >= self.quorum
+    }
+
+    fn result(&mut self, timed_out: bool) -> ScatterGatherResult {
+        self.delivered = true;
+        ScatterGatherResult {
+            responses: std::mem::take(&mut self.responses),
+            missing: Some(self.pending.as_slice().to_message()),
+            timed_out,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ActorSystemConfig, ExecutorConfig, ExecutorType};
+    use crate::message::common_types::{StringWrapper, U32Wrapper};
+    use crate::system::{ActorSystem, Inbox};
+    use crate::util::testing::{string_value, Echo, Sink};
+
+    fn recipients(n: usize) -> Vec<ActorAddress> {
+        (0..n)
+            .map(|i| ActorAddress::new_root(&format!("shard-{}", i)))
+            .collect()
+    }
+
+    #[test]
+    fn test_gather_all() {
+        let shards = recipients(3);
+        let mut gather = Gather::new(shards.clone(), 0);
+        assert!(!gather.reply(&shards[1], &U32Wrapper { value: 1 }));
+        // Duplicate replies and replies from unknown actors are ignored
+        assert!(!gather.reply(&shards[1], &U32Wrapper { value: 1 }));
+        assert!(!gather.reply(&ActorAddress::new_root("other"), &U32Wrapper { value: 9 }));
+        assert!(!gather.reply(&shards[0], &U32Wrapper { value: 0 }));
+        assert!(gather.reply(&shards[2], &U32Wrapper { value: 2 }));
+
+        let result = gather.result(false);
+        let values: Vec<_> = result
+            .responses
+            .iter()
+            .map(|r| r.decode::<U32Wrapper>().unwrap().value)
+            .collect();
+        assert_eq!(values, vec![1, 0, 2]);
+        assert!(result.missing_addresses().is_empty());
+    }
+
+    #[test]
+    fn test_gather_quorum_and_missing() {
+        let shards = recipients(3);
+        let mut gather = Gather::new(shards.clone(), 2);
+        assert!(!gather.reply(&shards[0], &U32Wrapper { value: 0 }));
+        assert!(gather.reply(&shards[2], &U32Wrapper { value: 2 }));
+
+        let result = gather.result(false);
+        assert_eq!(result.responses.len(), 2);
+        let missing: Vec<_> = result.missing_addresses().iter().map(|a| a.uri()).collect();
+        assert_eq!(missing, vec![shards[1].uri()]);
+    }
+
+    /// Scatter-gathers a `"ping"` to two echoing children and, except in mode `"all"`, a child
+    /// that never replies. The mode (`"all"`, `"quorum"` or `"deadline"`) is given as a
+    /// `StringWrapper` and results are forwarded to the address the actor is initialized with.
+    /// Replies to `"children"` with its number of children.
+    struct Requester {
+        to: ActorAddress,
+    }
+
+    impl ActorInit for Requester {
+        type Init = StringWrapper;
+
+        fn init(init_msg: Self::Init) -> Self {
+            Requester {
+                to: init_msg.value.parse().unwrap(),
+            }
+        }
+    }
+
+    impl Actor for Requester {
+        fn receive(&mut self, mut ctx: Context, msg: Box<dyn Message>) {
+            if msg.as_any().is::<ScatterGatherResult>() {
+                return ctx.forward(&self.to, msg);
+            }
+            let mode = string_value(msg);
+            if mode == "children" {
+                return ctx.send(ctx.sender(), ctx.children().len().to_string());
+            }
+            let mut recipients: Vec<_> = (0..2)
+                .map(|_| ctx.spawn_child::<Echo, _, _>("echo", "").await_unwrap())
+                .collect();
+            if mode != "all" {
+                recipients.push(ctx.spawn_child::<Sink, _, _>("sink", "").await_unwrap());
+            }
+            let init = ScatterGatherInit::new(&recipients);
+            let init = match mode.as_str() {
+                "quorum" => init.with_quorum(2),
+                _ => init.with_timeout(Duration::from_millis(100)),
+            };
+            scatter_gather::<StringWrapper, _>(&mut ctx, init, "ping").unwrap();
+        }
+    }
+
+    fn receive_result(inbox: &Inbox) -> ScatterGatherResult {
+        let result = inbox.receive(Duration::from_secs(5)).unwrap();
+        let result = result.as_any().downcast_ref::<ScatterGatherResult>();
+        result.unwrap().clone()
+    }
+
+    #[test]
+    fn test_scatter_gather() {
+        let system = ActorSystem::init(ActorSystemConfig {
+            executor_config: ExecutorConfig {
+                executor_type: ExecutorType::Deterministic { seed: 5 },
+                ..ExecutorConfig::default()
+            },
+            ..ActorSystemConfig::default()
+        });
+        let handle = system.handle();
+        let inbox = handle.inbox().unwrap();
+        let requester = handle
+            .spawn_actor::<Requester, _, _>("requester", inbox.address().to_string())
+            .unwrap();
+
+        // All recipients reply
+        inbox.send(&requester, "all").unwrap();
+        let result = receive_result(&inbox);
+        assert!(!result.timed_out);
+        assert_eq!(result.responses.len(), 2);
+        for response in result.responses.iter() {
+            assert_eq!(response.decode::<StringWrapper>().unwrap().value, "ping");
+        }
+        assert!(result.missing_addresses().is_empty());
+
+        // The quorum is reached without the non-responder
+        inbox.send(&requester, "quorum").unwrap();
+        let result = receive_result(&inbox);
+        assert!(!result.timed_out);
+        assert_eq!(result.responses.len(), 2);
+        assert_eq!(result.missing_addresses().len(), 1);
+
+        // The deadline passes waiting for the non-responder, which is listed as missing
+        inbox.send(&requester, "deadline").unwrap();
+        system.run_until_idle();
+        assert!(inbox.try_receive().is_none());
+        system.advance_time(Duration::from_millis(100));
+        let result = receive_result(&inbox);
+        assert!(result.timed_out);
+        assert_eq!(result.responses.len(), 2);
+        let missing = result.missing_addresses();
+        assert_eq!(missing.len(), 1);
+        assert!(missing[0].uri.to_string().contains("sink"));
+
+        // Aggregators are no longer children once they have delivered their result
+        system.run_until_idle();
+        inbox.send(&requester, "children").unwrap();
+        assert_eq!(
+            string_value(inbox.receive(Duration::from_secs(5)).unwrap()),
+            "8"
+        );
+        system.shutdown();
+    }
+}
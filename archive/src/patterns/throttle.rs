@@ -0,0 +1,251 @@
+//! Throttling proxy that limits the rate of messages forwarded to a target actor.
+//!
+//! A [`Throttle`] forwards at most `rate` messages per `period` to its target, allowing bursts
+//! of up to `burst` messages after an idle period (a token bucket). Messages that cannot be
+//! forwarded right away are queued and forwarded as tokens become available. Once the queue is
+//! full, further messages are dropped or handed to the dead letter queue, depending on the
+//! [`OverflowStrategy`].
+//!
+//! Messages are forwarded with their original sender, so the target replies directly to the
+//! sender. When `back_pressure` is enabled, senders whose messages are delayed or dropped are
+//! sent a [`Throttled`] message so they can slow down.
+
+use crate::actor::{Actor, ActorAddress, ActorInit, Context};
+use crate::message::{Message, ToMessage};
+use log::{trace, warn};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+pub mod proto {
+    use crate::message::common_types::impl_busan_message;
+    use crate::message::Message;
+
+    include!(concat!(env!("OUT_DIR"), "/patterns.throttle.rs"));
+
+    impl_busan_message!(ThrottleInit);
+    impl_busan_message!(Throttled);
+    impl_busan_message!(ThrottleTick);
+}
+
+use proto::ThrottleTick;
+pub use proto::{OverflowStrategy, ThrottleInit, Throttled};
+
+const DEFAULT_RATE: u32 = 100;
+const DEFAULT_PERIOD: Duration = Duration::from_secs(1);
+const DEFAULT_QUEUE_CAPACITY: usize = 1024;
+
+impl ThrottleInit {
+    /// Create the initialization message for a [`Throttle`] forwarding at most `rate` messages
+    /// per `period` to `target`. A `rate` of zero falls back to the default of 100 messages.
+    pub fn new(target: &ActorAddress, rate: u32, period: Duration) -> Self {
+        Self {
+            target: Some(target.to_message()),
+            rate,
+            period_ms: period.as_millis() as u64,
+            ..Default::default()
+        }
+    }
+}
+
+/// Throttling proxy actor. See the [module documentation](self) for details.
+pub struct Throttle {
+    target: Option<ActorAddress>,
+    bucket: TokenBucket,
+    queue: VecDeque<(Option<ActorAddress>, Box<dyn Message>)>,
+    queue_capacity: usize,
+    overflow: OverflowStrategy,
+    back_pressure: bool,
+    /// Whether a [`ThrottleTick`] has been scheduled and not yet received
+    tick_scheduled: bool,
+}
+
+impl ActorInit for Throttle {
+    type Init = ThrottleInit;
+
+    fn init(init_msg: Self::Init) -> Self {
+        let rate = match init_msg.rate {
+            0 => DEFAULT_RATE,
+            rate => rate,
+        };
+        let period = match init_msg.period_ms {
+            0 => DEFAULT_PERIOD,
+            millis => Duration::from_millis(millis),
+        };
+        let burst = match init_msg.burst {
+            0 => rate,
+            burst => burst,
+        };
+        let queue_capacity = match init_msg.queue_capacity {
+            0 => DEFAULT_QUEUE_CAPACITY,
+            capacity => capacity as usize,
+        };
+        Self {
+            target: init_msg
+                .target
+                .clone()
+                .map(ActorAddress::try_from)
+                .and_then(Result::ok),
+            bucket: TokenBucket::new(rate, period, burst, Instant::now()),
+            queue: VecDeque::new(),
+            queue_capacity,
+            overflow: init_msg.overflow(),
+            back_pressure: init_msg.back_pressure,
+            tick_scheduled: false,
+        }
+    }
+}
+
+impl Throttle {
+    /// Forward as many queued messages as there are tokens for, scheduling a tick for the
+    /// remainder.
+    fn drain(&mut self, ctx: &Context, target: &ActorAddress) {
+        let now = ctx.now();
+        while !self.queue.is_empty() && self.bucket.try_acquire(now) {
+            let (sender, msg) = self.queue.pop_front().unwrap();
+            ctx.send_from(target, sender.as_ref(), msg);
+        }
+        if self.queue.is_empty() || self.tick_scheduled {
+            return;
+        }
+        if let Some(delay) = self.bucket.time_until_available(now) {
+            self.tick_scheduled = true;
+            ctx.send_after(ctx.address(), ThrottleTick {}, delay);
+        }
+    }
+
+    fn signal(&self, ctx: &Context, dropped: bool) {
+        if let Some(sender) = ctx.sender_address().filter(|_| self.back_pressure) {
+            let throttled = Throttled {
+                dropped,
+                queue_len: self.queue.len() as u32,
+            };
+            ctx.send(sender, throttled);
+        }
+    }
+}
+
+impl Actor for Throttle {
+    fn before_start(&mut self, ctx: Context) {
+        // The bucket starts out full, so it only refills from the time the actor starts
+        self.bucket.last_refill = ctx.now();
+        if self.target.is_none() {
+            warn!("{}: no valid target address given", ctx.address());
+        }
+    }
+
+    fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+        let target = match self.target.clone() {
+            Some(target) => target,
+            None => return self.unhandled(ctx, msg),
+        };
+
+        if msg.as_any().is::<ThrottleTick>() {
+            self.tick_scheduled = false;
+            self.drain(&ctx, &target);
+            return;
+        }
+
+        if self.queue.is_empty() && self.bucket.try_acquire(ctx.now()) {
+            ctx.forward(&target, msg);
+        } else if self.queue.len() < self.queue_capacity {
+            trace!("{}: throttling message", ctx.address());
+            self.queue.push_back((ctx.sender_address().cloned(), msg));
+            self.drain(&ctx, &target);
+            self.signal(&ctx, false);
+        } else {
+            warn!("{}: throttle queue is full", ctx.address());
+            if self.overflow == OverflowStrategy::DeadLetter {
+                ctx.dead_letter(&target, msg);
+            }
+            self.signal(&ctx, true);
+        }
+    }
+}
+
+/// Token bucket that refills at `rate` tokens per `period`, holding at most `capacity` tokens.
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    /// Tokens added per second
+    refill_rate: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full token bucket
+    fn new(rate: u32, period: Duration, capacity: u32, now: Instant) -> Self {
+        Self {
+            capacity: capacity as f64,
+            tokens: capacity as f64,
+            refill_rate: rate as f64 / period.as_secs_f64(),
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_rate).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Take a token if one is available
+    fn try_acquire(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return true;
+        }
+        false
+    }
+
+    /// Time until the next token is available, or `None` if the bucket never refills
+    fn time_until_available(&mut self, now: Instant) -> Option<Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            return Some(Duration::ZERO);
+        }
+        if self.refill_rate <= 0.0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(
+            (1.0 - self.tokens) / self.refill_rate,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_burst() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(10, Duration::from_secs(1), 3, now);
+        assert!(bucket.try_acquire(now));
+        assert!(bucket.try_acquire(now));
+        assert!(bucket.try_acquire(now));
+        assert!(!bucket.try_acquire(now));
+        assert_eq!(
+            bucket.time_until_available(now),
+            Some(Duration::from_millis(100))
+        );
+
+        // Tokens refill at the rate, but never beyond the burst capacity
+        assert!(bucket.try_acquire(now + Duration::from_millis(100)));
+        assert!(!bucket.try_acquire(now + Duration::from_millis(100)));
+        let later = now + Duration::from_secs(10);
+        assert!((0..3).all(|_| bucket.try_acquire(later)));
+        assert!(!bucket.try_acquire(later));
+    }
+
+    #[test]
+    fn test_init_defaults() {
+        // A zero rate (the protobuf default) would never forward anything
+        let throttle = Throttle::init(ThrottleInit::default());
+        assert_eq!(throttle.bucket.capacity, DEFAULT_RATE as f64);
+        assert_eq!(throttle.bucket.refill_rate, DEFAULT_RATE as f64);
+        assert_eq!(throttle.queue_capacity, DEFAULT_QUEUE_CAPACITY);
+    }
+}
@@ -0,0 +1,408 @@
+//! Work-pulling pool, where workers ask for work rather than having work pushed to them.
+//!
+//! A [`WorkPullingMaster`] spawns a fixed number of workers (as children) and accepts work items
+//! of type `W` from any actor. Rather than routing work as soon as it arrives, the master holds
+//! work in a bounded queue and only hands an item to a worker that has asked for one. This keeps
+//! fast workers busy without flooding slow workers' mailboxes.
+//!
+//! The protocol between master and workers is:
+//!
+//!   + A worker calls [`request_work`] when it starts (typically in `before_start`)
+//!   + The master sends a single work item to the worker once one is available
+//!   + The worker calls [`complete_work`] when it has finished the item, which also asks for the
+//!     next item
+//!
+//! Completion is signalled explicitly rather than through delivery ACKs, so the master knows the
+//! work was actually processed and not just received. Work handed to a worker that terminates
+//! before completing it is put back at the front of the queue.
+//!
+//! When the queue is full, submitted work is returned to the submitter in a [`WorkRejected`]
+//! message.
+
+use crate::actor::{Actor, ActorAddress, ActorInit, Context, SenderType, Uri};
+use crate::message::system::Terminated;
+use crate::message::{Message, ToMessage};
+use log::{debug, error, trace, warn};
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+
+pub mod proto {
+    use crate::message::common_types::impl_busan_message;
+    use crate::message::Message;
+
+    include!(concat!(env!("OUT_DIR"), "/patterns.work_pulling.rs"));
+
+    impl_busan_message!(WorkPullingInit);
+    impl_busan_message!(RequestWork);
+    impl_busan_message!(WorkComplete);
+    impl_busan_message!(WorkRejected);
+}
+
+pub use proto::{RequestWork, WorkComplete, WorkPullingInit, WorkRejected};
+
+const DEFAULT_QUEUE_CAPACITY: usize = 1024;
+
+impl WorkPullingInit {
+    /// Create the initialization message for a [`WorkPullingMaster`] with `worker_count`
+    /// workers, each initialized with `worker_init`.
+    pub fn new<M: Message, T: ToMessage<M>>(worker_count: u32, worker_init: T) -> Self {
+        Self {
+            worker_count,
+            worker_init: worker_init.to_message().encode_to_vec2(),
+            queue_capacity: 0,
+        }
+    }
+
+    /// Set the maximum number of queued work items.
+    pub fn with_queue_capacity(mut self, queue_capacity: u32) -> Self {
+        self.queue_capacity = queue_capacity;
+        self
+    }
+}
+
+/// Ask the master (the parent of the current actor) for a work item.
+pub fn request_work(ctx: &Context) {
+    if let Some(master) = ctx.parent() {
+        ctx.send(master, RequestWork {});
+    }
+}
+
+/// Tell the master (the parent of the current actor) that the current work item is complete
+/// and that the worker is ready for the next item.
+pub fn complete_work(ctx: &Context) {
+    if let Some(master) = ctx.parent() {
+        ctx.send(master, WorkComplete {});
+    }
+}
+
+/// Master of a work-pulling pool of `A` workers, accepting work items of type `W`. See the
+/// [module documentation](self) for details.
+pub struct WorkPullingMaster<A, W> {
+    worker_count: u32,
+    worker_init: Vec<u8>,
+    work: WorkQueue,
+    _types: PhantomData<fn() -> (A, W)>,
+}
+
+impl<A, W> ActorInit for WorkPullingMaster<A, W>
+where
+    A: ActorInit + Actor + 'static,
+    A::Init: Default,
+    W: Message + 'static,
+{
+    type Init = WorkPullingInit;
+
+    fn init(init_msg: Self::Init) -> Self {
+        let capacity = match init_msg.queue_capacity {
+            0 => DEFAULT_QUEUE_CAPACITY,
+            capacity => capacity as usize,
+        };
+        Self {
+            worker_count: init_msg.worker_count,
+            worker_init: init_msg.worker_init,
+            work: WorkQueue::new(capacity),
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<A, W> Actor for WorkPullingMaster<A, W>
+where
+    A: ActorInit + Actor + 'static,
+    A::Init: Default,
+    W: Message + 'static,
+{
+    fn before_start(&mut self, mut ctx: Context) {
+        for _ in 0..self.worker_count {
+            let mut init = A::Init::default();
+            if let Err(e) = init.merge2(&self.worker_init) {
+                error!(
+                    "{}: unable to decode worker init message: {}",
+                    ctx.address(),
+                    e
+                );
+                return;
+            }
+            match ctx
+                .spawn_child::<A, A::Init, A::Init>("worker", init)
+                .await_ready()
+            {
+                Ok(worker) => ctx.watch(&worker),
+                Err(e) => error!("{}: unable to spawn worker: {}", ctx.address(), e),
+            }
+        }
+    }
+
+    fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+        if msg.as_any().is::<W>() {
+            match self.work.submit(msg) {
+                Ok(Some((worker, work))) => ctx.send_message(&worker, work, None),
+                Ok(None) => trace!("{}: queued work item", ctx.address()),
+                Err(work) => {
+                    warn!("{}: work queue is full, rejecting work", ctx.address());
+                    if !matches!(ctx.sender, SenderType::System) {
+                        let rejected = WorkRejected {
+                            work: work.encode_to_vec2(),
+                        };
+                        ctx.send(ctx.sender(), rejected);
+                    }
+                }
+            }
+        } else if msg.as_any().is::<RequestWork>() {
+            let worker = ctx.sender().clone();
+            if let Some(work) = self.work.request(&worker) {
+                ctx.send_message(&worker, work, None);
+            }
+        } else if msg.as_any().is::<WorkComplete>() {
+            let worker = ctx.sender().clone();
+            self.work.complete(&worker);
+            if let Some(work) = self.work.request(&worker) {
+                ctx.send_message(&worker, work, None);
+            }
+        } else if let Some(terminated) = msg.as_any().downcast_ref::<Terminated>() {
+            let worker = terminated
+                .address
+                .clone()
+                .map(ActorAddress::try_from)
+                .and_then(Result::ok);
+            if let Some(worker) = worker {
+                debug!("{}: worker {} terminated", ctx.address(), worker);
+                if let Some((worker, work)) = self.work.worker_terminated(&worker) {
+                    ctx.send_message(&worker, work, None);
+                }
+            }
+        } else {
+            self.unhandled(ctx, msg);
+        }
+    }
+}
+
+/// Bookkeeping for queued and in-flight work, independent of message delivery.
+struct WorkQueue {
+    capacity: usize,
+    queue: VecDeque<Box<dyn Message>>,
+    /// Workers that have requested work while the queue was empty
+    idle: VecDeque<ActorAddress>,
+    /// A copy of the work item each busy worker is processing
+    in_flight: HashMap<Uri, Box<dyn Message>>,
+}
+
+impl WorkQueue {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            queue: VecDeque::new(),
+            idle: VecDeque::new(),
+            in_flight: HashMap::new(),
+        }
+    }
+
+    /// Submit a work item. Returns the worker to send the work to if one is idle, or `None` if
+    /// the work was queued. If the queue is full, the work is returned as an error.
+    #[allow(clippy::type_complexity)]
+    fn submit(
+        &mut self,
+        work: Box<dyn Message>,
+    ) -> Result<Option<(ActorAddress, Box<dyn Message>)>, Box<dyn Message>> {
+        if let Some(worker) = self.idle.pop_front() {
+            return Ok(Some(self.assign(worker, work)));
+        }
+        if self.queue.len() >= self.capacity {
+            return Err(work);
+        }
+        self.queue.push_back(work);
+        Ok(None)
+    }
+
+    /// Handle a worker asking for work. Returns the work to send, if any is queued. Otherwise
+    /// the worker is marked idle and is handed the next submitted item.
+    fn request(&mut self, worker: &ActorAddress) -> Option<Box<dyn Message>> {
+        if self.in_flight.contains_key(&worker.uri) {
+            // Already busy, the worker will ask again once the current item is complete
+            return None;
+        }
+        match self.queue.pop_front() {
+            Some(work) => Some(self.assign(worker.clone(), work).1),
+            None => {
+                if !self.idle.iter().any(|w| w.uri == worker.uri) {
+                    self.idle.push_back(worker.clone());
+                }
+                None
+            }
+        }
+    }
+
+    fn complete(&mut self, worker: &ActorAddress) {
+        self.in_flight.remove(&worker.uri);
+    }
+
+    /// Forget a terminated worker, re-queueing any work it had in flight. If there is an idle
+    /// worker available, the re-queued work is returned along with the worker to send it to.
+    fn worker_terminated(
+        &mut self,
+        worker: &ActorAddress,
+    ) -> Option<(ActorAddress, Box<dyn Message>)> {
+        self.idle.retain(|w| w.uri != worker.uri);
+        // Re-queued work goes to the front of the queue and may exceed the queue capacity, since
+        // it was already accepted.
+        let work = self.in_flight.remove(&worker.uri)?;
+        match self.idle.pop_front() {
+            Some(idle) => Some(self.assign(idle, work)),
+            None => {
+                self.queue.push_front(work);
+                None
+            }
+        }
+    }
+
+    fn assign(
+        &mut self,
+        worker: ActorAddress,
+        work: Box<dyn Message>,
+    ) -> (ActorAddress, Box<dyn Message>) {
+        self.in_flight.insert(worker.uri.clone(), work.clone_box());
+        (worker, work)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ActorSystemConfig, ExecutorConfig, ExecutorType};
+    use crate::message::common_types::{StringWrapper, U32Wrapper};
+    use crate::message::system::PoisonPill;
+    use crate::system::{ActorSystem, Inbox};
+    use crate::util::testing::string_value;
+
+    fn work(value: u32) -> Box<dyn Message> {
+        Box::new(U32Wrapper { value })
+    }
+
+    fn value(work: &dyn Message) -> u32 {
+        work.as_any().downcast_ref::<U32Wrapper>().unwrap().value
+    }
+
+    #[test]
+    fn test_pull_work() {
+        let mut queue = WorkQueue::new(2);
+        let worker = ActorAddress::new_root("worker");
+
+        // Nothing queued, so the worker becomes idle and receives the next submission
+        assert!(queue.request(&worker).is_none());
+        let (to, sent) = queue.submit(work(1)).unwrap().unwrap();
+        assert_eq!(to.uri, worker.uri);
+        assert_eq!(value(sent.as_ref()), 1);
+
+        // Worker is busy, so submissions queue up to capacity
+        assert!(queue.submit(work(2)).unwrap().is_none());
+        assert!(queue.submit(work(3)).unwrap().is_none());
+        assert_eq!(value(queue.submit(work(4)).unwrap_err().as_ref()), 4);
+
+        // Busy workers are not handed more work until completing the current item
+        assert!(queue.request(&worker).is_none());
+        queue.complete(&worker);
+        assert_eq!(value(queue.request(&worker).unwrap().as_ref()), 2);
+    }
+
+    #[test]
+    fn test_requeue_on_termination() {
+        let mut queue = WorkQueue::new(1);
+        let worker_1 = ActorAddress::new_root("worker-1");
+        let worker_2 = ActorAddress::new_root("worker-2");
+
+        assert!(queue.submit(work(1)).unwrap().is_none());
+        assert_eq!(value(queue.request(&worker_1).unwrap().as_ref()), 1);
+        assert!(queue.submit(work(2)).unwrap().is_none());
+
+        // In-flight work is put back at the front of the (full) queue
+        assert!(queue.worker_terminated(&worker_1).is_none());
+        assert_eq!(value(queue.request(&worker_2).unwrap().as_ref()), 1);
+
+        // ... or handed straight to an idle worker
+        let worker_3 = ActorAddress::new_root("worker-3");
+        queue.complete(&worker_2);
+        assert_eq!(value(queue.request(&worker_2).unwrap().as_ref()), 2);
+        assert!(queue.request(&worker_3).is_none());
+        let (to, sent) = queue.worker_terminated(&worker_2).unwrap();
+        assert_eq!(to.uri, worker_3.uri);
+        assert_eq!(value(sent.as_ref()), 2);
+    }
+
+    /// Reports each work item to the address it is initialized with, as `<worker> <value>`, and
+    /// holds on to it until told to `"complete"` it.
+    struct Worker {
+        to: ActorAddress,
+    }
+
+    impl ActorInit for Worker {
+        type Init = StringWrapper;
+
+        fn init(init_msg: Self::Init) -> Self {
+            Worker {
+                to: init_msg.value.parse().unwrap(),
+            }
+        }
+    }
+
+    impl Actor for Worker {
+        fn before_start(&mut self, ctx: Context) {
+            request_work(&ctx);
+        }
+
+        fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+            if let Some(work) = msg.as_any().downcast_ref::<U32Wrapper>() {
+                let report = format!("{} {}", ctx.address().uri, work.value);
+                ctx.send(&self.to, report);
+            } else if string_value(msg) == "complete" {
+                complete_work(&ctx);
+            }
+        }
+    }
+
+    /// Receive the next report, returning the worker and the value of its work item.
+    fn receive_report(system: &ActorSystem, inbox: &Inbox) -> (ActorAddress, u32) {
+        system.run_until_idle();
+        let report = string_value(inbox.try_receive().unwrap());
+        let (worker, value) = report.split_once(' ').unwrap();
+        (worker.parse().unwrap(), value.parse().unwrap())
+    }
+
+    #[test]
+    fn test_work_pulling_pool() {
+        let system = ActorSystem::init(ActorSystemConfig {
+            executor_config: ExecutorConfig {
+                executor_type: ExecutorType::Deterministic { seed: 11 },
+                ..ExecutorConfig::default()
+            },
+            ..ActorSystemConfig::default()
+        });
+        let handle = system.handle();
+        let inbox = handle.inbox().unwrap();
+        let master = handle
+            .spawn_actor::<WorkPullingMaster<Worker, U32Wrapper>, _, _>(
+                "master",
+                WorkPullingInit::new(2, inbox.address().to_string()),
+            )
+            .unwrap();
+
+        inbox.send(&master, 1u32).unwrap();
+        let (first, value) = receive_report(&system, &inbox);
+        assert_eq!(value, 1);
+
+        // A worker stopped in the middle of an item has it handed to the other (idle) worker
+        inbox.send(&first, PoisonPill {}).unwrap();
+        let (second, value) = receive_report(&system, &inbox);
+        assert_ne!(second.uri, first.uri);
+        assert_eq!(value, 1);
+
+        // The remaining worker is busy until it completes its item
+        inbox.send(&master, 2u32).unwrap();
+        system.run_until_idle();
+        assert!(inbox.try_receive().is_none());
+        inbox.send(&second, "complete").unwrap();
+        let (worker, value) = receive_report(&system, &inbox);
+        assert_eq!(worker.uri, second.uri);
+        assert_eq!(value, 2);
+        system.shutdown();
+    }
+}
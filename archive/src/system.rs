@@ -1,14 +1,30 @@
-use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender};
 use log::{info, trace, warn};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::iter;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, TryLockError, Weak};
 use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::actor::{Actor, ActorAddress, ActorCell, ActorInit, Envelope, Uri};
+use crate::actor::{
+    cell_state, debug_serialize_msg, validate_name, Actor, ActorAddress, ActorCell, ActorInit,
+    ActorSelection, Envelope, PathPattern, ServiceRegistry, SpawnOptions, Uri,
+};
+use crate::config::{ExecutorGroupKind, ExecutorType, DEFAULT_EXECUTOR_GROUP};
 use crate::error::BusanError;
-use crate::executor::{get_executor_factory, ExecutorCommands, ExecutorHandle};
+use crate::executor::blocking::BlockingPool;
+use crate::executor::deterministic::{DeterministicExecutor, VirtualClock};
+use crate::executor::placement::{PlacementContext, PlacementStrategy};
+use crate::executor::thread_executor::ThreadExecutorFactory;
+use crate::executor::{
+    executor_factory, ExecutorCommands, ExecutorFactory, ExecutorHandle, RunQueue, ScheduledMessage,
+};
+use crate::message::system::{ChildStopped, Terminated};
 use crate::message::ToMessage;
 use crate::prelude::Message;
-use crate::util::lib_macros::{channel_must_recv, channel_send};
+use crate::util::lib_macros::channel_send;
+use crate::util::rng::Rng;
 use crate::util::CommandChannel;
 use crate::{actor, config};
 
@@ -20,14 +36,20 @@ use crate::{actor, config};
 /// and [`shutdown`](ActorSystem::shutdown), respectively).
 ///
 /// Once the ActorSystem is initialized through [`init`](ActorSystem::init), control and management
-/// of the executors and actors is delegated to a runtime management thread.
+/// of the executors and actors is delegated to a runtime management thread (unless the system is
+/// deterministic, see `ExecutorType::Deterministic`).
 pub struct ActorSystem {
-    executors: HashMap<String, ExecutorHandle>,
     runtime_manager: RuntimeManagerRef,
-    runtime_thread_handle: thread::JoinHandle<()>,
+    runtime: SystemRuntime,
     root_actor_assigned: bool,
 }
 
+/// Where the runtime manager of a system runs
+enum SystemRuntime {
+    Thread(thread::JoinHandle<()>),
+    Deterministic(Arc<DeterministicRuntime>),
+}
+
 impl ActorSystem {
     /// Initialize (and start) a new actor system. This will start up the runtime management thread
     /// and spawn executors for running actor-related actions. The nature and number of executors
@@ -54,23 +76,60 @@ impl ActorSystem {
     /// ```
     pub fn init(config: config::ActorSystemConfig) -> ActorSystem {
         config.validate().unwrap();
+        if let ExecutorType::Deterministic { seed } = config.executor_config.executor_type {
+            return Self::init_deterministic(config, seed);
+        }
 
-        let mut runtime_manager = RuntimeManager::init();
-        let executor_factory = get_executor_factory(&config.executor_config.executor_type);
-        let mut executors = HashMap::new();
-
-        // create a pre-configured number of executors
-        for i in 0..(config.executor_config.num_executors) {
-            let command_channel = CommandChannel::new();
-            let executor_name = format!("executor-{}", i);
-            let executor_handle = executor_factory.spawn_executor(
-                executor_name.clone(),
-                command_channel.clone(),
-                runtime_manager.get_ref(),
-            );
+        let executor_config = &config.executor_config;
+        let mut runtime_manager = RuntimeManager::init(
+            config.placement_strategy,
+            BlockingPool::new(executor_config.blocking_threads),
+        );
 
-            executors.insert(executor_name.clone(), executor_handle);
-            runtime_manager.add_executor(executor_name.clone(), command_channel);
+        // create a pre-configured number of executors for the default group and each shared
+        // group. Executors of pinned groups are created as actors are spawned in the group.
+        let shared_groups = iter::once((
+            DEFAULT_EXECUTOR_GROUP,
+            executor_config.num_executors,
+            &executor_config.executor_type,
+        ))
+        .chain(executor_config.groups.iter().filter_map(|group| {
+            match group.kind {
+                ExecutorGroupKind::Shared { num_executors } => Some((
+                    group.name.as_str(),
+                    num_executors,
+                    group
+                        .executor_type
+                        .as_ref()
+                        .unwrap_or(&executor_config.executor_type),
+                )),
+                ExecutorGroupKind::Pinned => None,
+            }
+        }));
+        for (group, num_executors, executor_type) in shared_groups {
+            runtime_manager.add_executor_group(
+                group,
+                false,
+                executor_type.runs_async_actors(),
+                executor_factory(executor_type, executor_config),
+            );
+            for _ in 0..num_executors {
+                runtime_manager.start_executor(group);
+            }
+        }
+        for group in &executor_config.groups {
+            if let ExecutorGroupKind::Pinned = group.kind {
+                let executor_factory = ThreadExecutorFactory {
+                    idle_strategy: executor_config.idle_strategy.clone(),
+                    throughput: executor_config.throughput,
+                };
+                runtime_manager.add_executor_group(
+                    &group.name,
+                    true,
+                    false,
+                    Box::new(executor_factory),
+                );
+            }
         }
 
         let runtime_manager_ref = runtime_manager.get_ref();
@@ -79,9 +138,52 @@ impl ActorSystem {
         });
 
         ActorSystem {
-            executors,
             runtime_manager: runtime_manager_ref,
-            runtime_thread_handle,
+            runtime: SystemRuntime::Thread(runtime_thread_handle),
+            root_actor_assigned: false,
+        }
+    }
+
+    /// Initialize a deterministic system, whose runtime manager and executors are run by the
+    /// thread driving the system.
+    fn init_deterministic(config: config::ActorSystemConfig, seed: u64) -> ActorSystem {
+        let executor_config = config.executor_config;
+        let runtime = Arc::new_cyclic(|runtime| {
+            let mut runtime_manager = RuntimeManager::init(
+                config.placement_strategy,
+                BlockingPool::new(executor_config.blocking_threads),
+            );
+            runtime_manager.deterministic = Some(runtime.clone());
+
+            // Scheduling and the random number generators of actors use separate sequences, so
+            // actors using randomness don't change the order in which actors are run.
+            let mut rng = Rng::with_seed(seed);
+            let actor_rng = Rng::with_seed(rng.next_u64());
+            let clock = Arc::new(VirtualClock::new());
+            let executor =
+                DeterministicExecutor::new(clock.clone(), rng, runtime_manager.get_ref());
+            runtime_manager.add_executor_group(
+                DEFAULT_EXECUTOR_GROUP,
+                false,
+                false,
+                Box::new(executor.factory()),
+            );
+            for _ in 0..executor_config.num_executors {
+                runtime_manager.start_executor(DEFAULT_EXECUTOR_GROUP);
+            }
+
+            DeterministicRuntime {
+                manager_command_channel: runtime_manager.manager_command_channel.clone(),
+                manager: Mutex::new(Some(runtime_manager)),
+                executor: Mutex::new(executor),
+                clock,
+                actor_rng: Mutex::new(actor_rng),
+            }
+        });
+
+        ActorSystem {
+            runtime_manager: runtime.get_ref(),
+            runtime: SystemRuntime::Deterministic(runtime),
             root_actor_assigned: false,
         }
     }
@@ -89,7 +191,8 @@ impl ActorSystem {
     /// Spawn the root actor for the system. The root actor will be the actor at the top
     /// of the actor hierarchy and all other actors must be created from here. Note that
     /// there may only be a single root actor per system and can, in some ways, be considered
-    /// the "main" function of the actor system.
+    /// the "main" function of the actor system. Additional top-level actors can be spawned with
+    /// [`SystemHandle::spawn_actor`].
     pub fn spawn_root_actor<
         A: ActorInit<Init = M> + Actor + 'static,
         T: ToMessage<M>,
@@ -99,20 +202,39 @@ impl ActorSystem {
         name: &str,
         init_msg: T,
     ) {
-        debug_assert!(
-            !self.executors.is_empty(),
-            "No executors available to spawn actor"
-        );
         debug_assert!(!self.root_actor_assigned, "Root actor already assigned");
+        if let Err(e) = validate_name(name) {
+            panic!("Invalid root actor name: {}", e);
+        }
 
         self.root_actor_assigned = true;
         let ready = self.runtime_manager.assign_actor(
             Box::new(A::init(init_msg.to_message())),
+            name,
             ActorAddress::new_root(name),
             None,
+            SpawnOptions::default(),
         );
         // Discard the address of the root actor
-        let _ = channel_must_recv!(ready);
+        let _ = self
+            .runtime_manager
+            .await_reply(&ready)
+            .expect("runtime manager stopped before assigning the root actor");
+    }
+
+    /// Get a handle for interacting with the system from non-actor code, such as `main` or
+    /// threads not managed by the actor system. See [`SystemHandle`].
+    pub fn handle(&self) -> SystemHandle {
+        SystemHandle {
+            runtime_manager: self.runtime_manager.clone(),
+        }
+    }
+
+    /// Select the running actors matching an absolute path, which may contain wildcards (e.g.
+    /// `local://root/workers/*`). See [`ActorSelection`] for the supported path syntax.
+    pub fn select(&self, path: &str) -> Result<ActorSelection, BusanError> {
+        let pattern = PathPattern::parse(path)?;
+        Ok(ActorSelection::new(pattern, self.runtime_manager.clone()))
     }
 
     /// Send shutdown message to all executors and wait for them to finish. This includes
@@ -124,15 +246,337 @@ impl ActorSystem {
 
     /// Await shutdown of all executors. Similar to shutdown, but doesn't send
     /// shutdown messages to begin shutdown. Will wait indefinitely until all
-    /// executors and the runtime manager have shutdown.
+    /// executors and the runtime manager have shutdown (the runtime manager stops once all of its
+    /// executors have).
+    ///
+    /// Deterministic systems are run until they have shut down, advancing virtual time as
+    /// needed. Panics if such a system is idle without any timers left, as it would never shut
+    /// down.
     pub fn await_shutdown(self) {
-        self.executors
-            .into_iter()
-            .for_each(|(_, manager)| manager.await_close());
-        self.runtime_thread_handle.join().unwrap();
+        match self.runtime {
+            SystemRuntime::Thread(runtime_thread_handle) => runtime_thread_handle.join().unwrap(),
+            SystemRuntime::Deterministic(runtime) => {
+                if !runtime.run_until(None, || runtime.is_terminated()) {
+                    panic!("deterministic actor system is idle and would never shut down");
+                }
+            }
+        }
+    }
+
+    /// Run a deterministic system (see `ExecutorType::Deterministic`) until it is idle: its
+    /// actors have no messages left to process and no timers are due. Virtual time does not
+    /// advance. Panics if the system is not deterministic.
+    ///
+    /// ```rust
+    /// # use busan::config::{ExecutorConfig, ExecutorType};
+    /// # use busan::message::common_types::{StringWrapper, U32Wrapper};
+    /// # use busan::prelude::*;
+    /// # use std::time::Duration;
+    /// # struct Delay;
+    /// # impl Actor for Delay {
+    /// #     fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+    /// #         let value = msg.as_any().downcast_ref::<U32Wrapper>().unwrap().value;
+    /// #         ctx.send_after(ctx.sender(), value, Duration::from_secs(60));
+    /// #     }
+    /// # }
+    /// # impl ActorInit for Delay {
+    /// #     type Init = StringWrapper;
+    /// #     fn init(_init_msg: StringWrapper) -> Self { Delay }
+    /// # }
+    /// // `Delay` replies to each message after a minute
+    /// let system = ActorSystem::init(ActorSystemConfig {
+    ///     executor_config: ExecutorConfig {
+    ///         executor_type: ExecutorType::Deterministic { seed: 42 },
+    ///         ..ExecutorConfig::default()
+    ///     },
+    ///     ..ActorSystemConfig::default()
+    /// });
+    /// let handle = system.handle();
+    /// let delay = handle.spawn_actor::<Delay, _, _>("delay", "").unwrap();
+    /// let inbox = handle.inbox().unwrap();
+    /// inbox.send(&delay, 7u32).unwrap();
+    ///
+    /// system.run_until_idle();
+    /// assert!(inbox.try_receive().is_none());
+    /// system.advance_time(Duration::from_secs(60));
+    /// assert!(inbox.try_receive().is_some());
+    /// system.shutdown();
+    /// ```
+    pub fn run_until_idle(&self) {
+        self.deterministic_runtime()
+            .run_until(Some(self.runtime_manager.now()), || false);
+    }
+
+    /// Advance the virtual time of a deterministic system (see `ExecutorType::Deterministic`) by
+    /// `duration`, running the system and delivering the timers that are due along the way.
+    /// Panics if the system is not deterministic.
+    pub fn advance_time(&self, duration: Duration) {
+        let until = self.runtime_manager.now() + duration;
+        self.deterministic_runtime()
+            .run_until(Some(until), || false);
+    }
+
+    fn deterministic_runtime(&self) -> &DeterministicRuntime {
+        match &self.runtime {
+            SystemRuntime::Deterministic(runtime) => runtime,
+            SystemRuntime::Thread(_) => panic!("only deterministic actor systems can be driven"),
+        }
+    }
+}
+
+/// `SystemHandle` is a clonable handle for interacting with an actor system from non-actor code,
+/// such as `main`, an HTTP handler thread or any other thread not managed by the actor system.
+/// Handles are created with [`ActorSystem::handle`] and remain valid until the system starts
+/// shutting down, after which all actions return [`BusanError::SystemShutdown`].
+///
+/// Messages sent with [`send`](SystemHandle::send) have no sender (like messages sent by the
+/// system itself). To receive replies, send messages from an [`Inbox`] or use
+/// [`ask`](SystemHandle::ask).
+///
+/// ```rust,no_run
+/// # use busan::prelude::*;
+/// # use busan::message::common_types::StringWrapper;
+/// # use std::time::Duration;
+/// # struct Echo;
+/// # impl Actor for Echo {
+/// #     fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+/// #         ctx.send_message(ctx.sender(), msg, None);
+/// #     }
+/// # }
+/// # impl ActorInit for Echo {
+/// #     type Init = StringWrapper;
+/// #     fn init(_init_msg: StringWrapper) -> Self { Echo }
+/// # }
+/// let system = ActorSystem::init(ActorSystemConfig::default());
+/// let handle = system.handle();
+/// let echo = handle.spawn_actor::<Echo, _, _>("echo", "").unwrap();
+///
+/// let gateway = std::thread::spawn(move || {
+///     let reply = handle.ask(&echo, "hello", Duration::from_secs(1)).unwrap();
+///     println!("{:?}", reply);
+/// });
+/// gateway.join().unwrap();
+/// system.shutdown();
+/// ```
+#[derive(Clone)]
+pub struct SystemHandle {
+    runtime_manager: RuntimeManagerRef,
+}
+
+impl SystemHandle {
+    /// Returns true until the system has started shutting down.
+    pub fn is_running(&self) -> bool {
+        self.runtime_manager.is_running()
+    }
+
+    fn ensure_running(&self) -> Result<(), BusanError> {
+        if self.is_running() {
+            Ok(())
+        } else {
+            Err(BusanError::SystemShutdown)
+        }
+    }
+
+    /// Send a message to `addr` without a sender. Like messages sent by actors, messages that
+    /// can't be delivered are sent to the dead letter queue.
+    pub fn send<M: Message + 'static, T: ToMessage<M>>(
+        &self,
+        addr: &ActorAddress,
+        message: T,
+    ) -> Result<(), BusanError> {
+        self.send_message(addr, Box::new(message.to_message()))
+    }
+
+    /// Send a boxed message to `addr` without a sender. See [`SystemHandle::send`].
+    pub fn send_message(
+        &self,
+        addr: &ActorAddress,
+        mut message: Box<dyn Message>,
+    ) -> Result<(), BusanError> {
+        self.ensure_running()?;
+        let message = debug_serialize_msg!(message);
+        self.runtime_manager.deliver(addr, None, message, None);
+        Ok(())
+    }
+
+    /// Create an [`Inbox`] with a temporary address that actors can reply to.
+    pub fn inbox(&self) -> Result<Inbox, BusanError> {
+        self.ensure_running()?;
+        // Inboxes are not run by an executor, so there is nothing to schedule
+        let address = ActorAddress::new_system("inbox");
+        let (mailbox, receiver) = actor::Mailbox::new(address.incarnation, None);
+        self.runtime_manager.register_inbox(&address, mailbox);
+        Ok(Inbox {
+            address,
+            receiver,
+            runtime_manager: self.runtime_manager.clone(),
+        })
+    }
+
+    /// Send a message to `addr` from a temporary address and wait (up to `timeout`) for the
+    /// first reply.
+    pub fn ask<M: Message + 'static, T: ToMessage<M>>(
+        &self,
+        addr: &ActorAddress,
+        message: T,
+        timeout: Duration,
+    ) -> Result<Box<dyn Message>, BusanError> {
+        let inbox = self.inbox()?;
+        inbox.send(addr, message)?;
+        inbox.receive(timeout)
+    }
+
+    /// Spawn an additional top-level actor (an actor without a parent) named `name`, returning
+    /// its address once it has been assigned to an executor.
+    pub fn spawn_actor<A: ActorInit<Init = M> + Actor + 'static, T: ToMessage<M>, M: Message>(
+        &self,
+        name: &str,
+        init_msg: T,
+    ) -> Result<ActorAddress, BusanError> {
+        self.spawn_actor_with::<A, T, M>(name, init_msg, SpawnOptions::default())
+    }
+
+    /// Spawn an additional top-level actor with [`SpawnOptions`]. See
+    /// [`spawn_actor`](SystemHandle::spawn_actor).
+    pub fn spawn_actor_with<
+        A: ActorInit<Init = M> + Actor + 'static,
+        T: ToMessage<M>,
+        M: Message,
+    >(
+        &self,
+        name: &str,
+        init_msg: T,
+        options: SpawnOptions,
+    ) -> Result<ActorAddress, BusanError> {
+        self.ensure_running()?;
+        validate_name(name).map_err(BusanError::InvalidName)?;
+        let ready = self.runtime_manager.assign_actor(
+            Box::new(A::init(init_msg.to_message())),
+            name,
+            ActorAddress::new_root(name),
+            None,
+            options,
+        );
+        self.runtime_manager
+            .await_reply(&ready)
+            .unwrap_or(Err(BusanError::SystemShutdown))
+    }
+
+    /// Start an additional executor in the executor group `group` (see
+    /// [`DEFAULT_EXECUTOR_GROUP`](config::DEFAULT_EXECUTOR_GROUP) for the default group),
+    /// returning its name. Actors spawned from now on may be placed on the new executor. Executors
+    /// can't be added to pinned groups, which start an executor for each actor.
+    pub fn add_executor(&self, group: &str) -> Result<String, BusanError> {
+        self.ensure_running()?;
+        let reply = self.runtime_manager.add_executor(group);
+        self.runtime_manager
+            .await_reply(&reply)
+            .unwrap_or(Err(BusanError::SystemShutdown))
+    }
+
+    /// Remove the executor `name` from its executor group. No new actors are placed on the
+    /// executor, and once it has finished its current work, its actors and scheduled messages are
+    /// moved to the remaining executors of the group (chosen by the placement strategy of the
+    /// system). Moved actors keep their state and pending messages, and are not started again.
+    ///
+    /// Returns once the removal has begun, while the actors are moved in the background. The last
+    /// executor of a group and executors of pinned groups (which stop with their actor) can't be
+    /// removed.
+    pub fn remove_executor(&self, name: &str) -> Result<(), BusanError> {
+        self.ensure_running()?;
+        let reply = self.runtime_manager.remove_executor(name);
+        self.runtime_manager
+            .await_reply(&reply)
+            .unwrap_or(Err(BusanError::SystemShutdown))
+    }
+
+    /// The names of the executors of the executor group `group`, in the order they were added.
+    /// Executors that are being removed are not included.
+    pub fn executors(&self, group: &str) -> Result<Vec<String>, BusanError> {
+        self.ensure_running()?;
+        let reply = self.runtime_manager.list_executors(group);
+        self.runtime_manager
+            .await_reply(&reply)
+            .unwrap_or(Err(BusanError::SystemShutdown))
+    }
+
+    /// Begin shutting down the system. Use [`ActorSystem::await_shutdown`] to wait for the
+    /// shutdown to complete.
+    pub fn shutdown(&self) {
+        self.runtime_manager.shutdown_system();
+    }
+}
+
+/// A temporary address for receiving messages outside of the actor system, created with
+/// [`SystemHandle::inbox`]. Messages sent from the inbox carry its address as the sender, so
+/// replies from actors are received by the inbox. The address stops resolving once the inbox is
+/// dropped.
+pub struct Inbox {
+    address: ActorAddress,
+    receiver: Receiver<Envelope>,
+    runtime_manager: RuntimeManagerRef,
+}
+
+impl Inbox {
+    /// The temporary address of the inbox (under `local://system`).
+    pub fn address(&self) -> &ActorAddress {
+        &self.address
+    }
+
+    /// Send a message to `addr`, with the inbox as the sender.
+    pub fn send<M: Message + 'static, T: ToMessage<M>>(
+        &self,
+        addr: &ActorAddress,
+        message: T,
+    ) -> Result<(), BusanError> {
+        if !self.runtime_manager.is_running() {
+            return Err(BusanError::SystemShutdown);
+        }
+        let mut message: Box<dyn Message> = Box::new(message.to_message());
+        let message = debug_serialize_msg!(message);
+        self.runtime_manager
+            .deliver(addr, Some(self.address.clone()), message, None);
+        Ok(())
+    }
+
+    /// Wait up to `timeout` for the next message. Deterministic systems are run until the message
+    /// arrives, or `timeout` has passed in virtual time.
+    pub fn receive(&self, timeout: Duration) -> Result<Box<dyn Message>, BusanError> {
+        if let Some(runtime) = self.runtime_manager.deterministic_runtime() {
+            let until = runtime.clock.now() + timeout;
+            runtime.run_until(Some(until), || !self.receiver.is_empty());
+            return self.try_receive().ok_or(BusanError::Timeout(timeout));
+        }
+        self.receiver
+            .recv_timeout(timeout)
+            .map(|envelope| envelope.payload)
+            .map_err(|_| BusanError::Timeout(timeout))
+    }
+
+    /// Receive the next message if one has already arrived.
+    pub fn try_receive(&self) -> Option<Box<dyn Message>> {
+        self.receiver
+            .try_recv()
+            .ok()
+            .map(|envelope| envelope.payload)
+    }
+}
+
+impl Drop for Inbox {
+    fn drop(&mut self) {
+        self.runtime_manager.deregister_inbox(&self.address);
     }
 }
 
+/// Run states of an actor system, in order. Only ever moves forward.
+mod system_state {
+    pub(super) const RUNNING: u8 = 0;
+    /// Shutdown has been initiated, but executors may still be running
+    pub(super) const SHUTTING_DOWN: u8 = 1;
+    /// The runtime manager has stopped and no longer handles commands
+    pub(super) const TERMINATED: u8 = 2;
+}
+
 /// `RuntimeManager` is an administrative process that manages the executors and runs in
 /// it's own thread. It is responsible for coordinating amongst executors as well as
 /// proxying commands from the `ActorSystem`.
@@ -143,263 +587,1048 @@ impl ActorSystem {
 struct RuntimeManager {
     /// Map of executor names to their command-channel (for sending commands)
     executor_command_channels: HashMap<String, CommandChannel<ExecutorCommands>>,
+    /// Map of executor names to the run queue actors on the executor are scheduled on
+    executor_run_queues: HashMap<String, Arc<dyn RunQueue>>,
+    /// Map of executor group names to the executors of the group
+    executor_groups: HashMap<String, ExecutorGroup>,
+    /// Map of executor names to the name of their group
+    executor_group_names: HashMap<String, String>,
+    /// Handles of the running executors, which are closed once the executor has shut down.
+    /// Executors are started (and stopped) by the runtime manager as the system runs, e.g. for
+    /// actors of pinned groups or when executors are added to a group.
+    executor_handles: HashMap<String, ExecutorHandle>,
+    /// Commands for executors that are being drained (see [`ExecutorCommands::Drain`]). They
+    /// are sent to the executors the actors are moved to once the executor has drained.
+    draining_executors: HashMap<String, Vec<ExecutorCommands>>,
+    /// Number of running actors assigned to each executor
+    executor_actor_counts: HashMap<String, usize>,
+    /// Mailboxes of the running actors assigned to each executor, by actor URI. Kept so that the
+    /// queue depth of an executor can be computed without scanning the whole registry.
+    executor_mailboxes: HashMap<String, HashMap<Uri, actor::Mailbox>>,
+    /// Decides which executor new actors are assigned to
+    placement_strategy: Box<dyn PlacementStrategy>,
     actor_registry: HashMap<Uri, ActorRegistryEntry>,
 
     /// State-tracking for actors that are in the process of terminating. Necessary
     /// to track state while actor sub-tree's are terminated.
     actor_shutdown_staging: HashMap<Uri, ActorShutdownHandle>,
 
+    /// Map of watched actors to the set of actors watching them (see [`Context::watch`]).
+    ///
+    /// [`Context::watch`]: crate::actor::Context::watch
+    watchers: HashMap<Uri, Vec<ActorAddress>>,
+
+    /// Services registered with the receptionist (see [`ServiceKey`]).
+    ///
+    /// [`ServiceKey`]: crate::actor::ServiceKey
+    services: ServiceRegistry,
+
+    /// Mailboxes of the temporary addresses created for [`Inbox`]es, so that actors can
+    /// resolve (and reply to) them.
+    inboxes: HashMap<Uri, actor::Mailbox>,
+
     manager_command_channel: CommandChannel<ManagerCommands>,
 
-    round_robin_state: usize,
+    /// Run state of the system (see [`system_state`]), shared with all `RuntimeManagerRef`s.
+    state: Arc<AtomicU8>,
+
+    /// Runs blocking work offloaded by actors, shared with all `RuntimeManagerRef`s
+    blocking_pool: Arc<BlockingPool>,
+
     shutdown_initiated: bool,
+
+    /// Set for deterministic systems, which run the runtime manager on the thread driving the
+    /// system (see [`DeterministicRuntime`]).
+    deterministic: Option<Weak<DeterministicRuntime>>,
 }
 
 impl RuntimeManager {
-    fn init() -> RuntimeManager {
+    fn init(
+        placement_strategy: Box<dyn PlacementStrategy>,
+        blocking_pool: BlockingPool,
+    ) -> RuntimeManager {
         RuntimeManager {
             executor_command_channels: HashMap::new(),
+            executor_run_queues: HashMap::new(),
+            executor_groups: HashMap::new(),
+            executor_group_names: HashMap::new(),
+            executor_handles: HashMap::new(),
+            draining_executors: HashMap::new(),
+            executor_actor_counts: HashMap::new(),
+            executor_mailboxes: HashMap::new(),
+            placement_strategy,
             actor_registry: HashMap::new(),
             actor_shutdown_staging: HashMap::new(),
+            watchers: HashMap::new(),
+            services: ServiceRegistry::default(),
+            inboxes: HashMap::new(),
             manager_command_channel: CommandChannel::new(),
-            round_robin_state: 0,
+            state: Arc::new(AtomicU8::new(system_state::RUNNING)),
+            blocking_pool: Arc::new(blocking_pool),
             shutdown_initiated: false,
+            deterministic: None,
         }
     }
 
-    fn add_executor(&mut self, name: String, command_channel: CommandChannel<ExecutorCommands>) {
-        self.executor_command_channels.insert(name, command_channel);
+    fn add_executor_group(
+        &mut self,
+        name: &str,
+        pinned: bool,
+        runs_async_actors: bool,
+        factory: Box<dyn ExecutorFactory>,
+    ) {
+        self.executor_groups.insert(
+            name.to_string(),
+            ExecutorGroup {
+                executors: Vec::new(),
+                pinned,
+                runs_async_actors,
+                factory,
+                next_executor_id: 0,
+            },
+        );
+    }
+
+    /// Start a new executor in the group `group_name`, returning its name. Executors of the
+    /// default group are named `executor-<n>`, those of other groups `executor-<group>-<n>`.
+    fn start_executor(&mut self, group_name: &str) -> String {
+        let manager_ref = self.get_ref();
+        let group = self
+            .executor_groups
+            .get_mut(group_name)
+            .expect("executor group does not exist");
+        let name = if group_name == DEFAULT_EXECUTOR_GROUP {
+            format!("executor-{}", group.next_executor_id)
+        } else {
+            format!("executor-{}-{}", group_name, group.next_executor_id)
+        };
+        group.next_executor_id += 1;
+
+        let command_channel = CommandChannel::new();
+        let executor_handle =
+            group
+                .factory
+                .spawn_executor(name.clone(), command_channel.clone(), manager_ref);
+        let run_queue = executor_handle
+            .run_queue()
+            .unwrap_or_else(|| Arc::new(command_channel.clone()));
+        group.executors.push(name.clone());
+        self.executor_command_channels
+            .insert(name.clone(), command_channel);
+        self.executor_run_queues.insert(name.clone(), run_queue);
+        self.executor_group_names
+            .insert(name.clone(), group_name.to_string());
+        self.executor_handles.insert(name.clone(), executor_handle);
+        name
+    }
+
+    /// The group of `executor_name`, if the executor is running.
+    fn executor_group(&self, executor_name: &str) -> Option<&ExecutorGroup> {
+        self.executor_group_names
+            .get(executor_name)
+            .and_then(|group| self.executor_groups.get(group))
+    }
+
+    /// Send `command` to the executor `executor_name`. Commands for executors that are being
+    /// drained are held back until their actors have moved (see [`Self::move_actors`]).
+    fn send_to_executor(&mut self, executor_name: &str, command: ExecutorCommands) {
+        if let Some(pending) = self.draining_executors.get_mut(executor_name) {
+            pending.push(command);
+            return;
+        }
+        channel_send!(
+            self.executor_command_channels.get(executor_name).unwrap(),
+            command
+        );
     }
 
     fn get_ref(&self) -> RuntimeManagerRef {
-        RuntimeManagerRef::new(self.manager_command_channel.clone())
+        RuntimeManagerRef::new(
+            self.manager_command_channel.clone(),
+            self.state.clone(),
+            self.blocking_pool.clone(),
+            self.deterministic.clone(),
+        )
     }
 
     fn run(mut self) {
         loop {
-            match self.manager_command_channel.recv() {
-                Ok(ManagerCommands::Shutdown) => {
-                    if self.shutdown_initiated {
-                        continue;
-                    }
-                    self.shutdown_initiated = true;
-                    self.executor_command_channels
-                        .iter()
-                        .for_each(|(_, channel)| {
-                            channel_send!(channel, ExecutorCommands::Shutdown);
-                        });
+            if let Ok(command) = self.manager_command_channel.recv() {
+                if !self.handle(command) {
+                    break;
+                }
+            }
+        }
+        self.terminate();
+    }
+
+    /// Handle a single command. Returns `false` once all executors have shut down and the
+    /// runtime manager should stop.
+    fn handle(&mut self, command: ManagerCommands) -> bool {
+        match command {
+            ManagerCommands::Shutdown => {
+                if self.shutdown_initiated {
+                    return true;
                 }
+                self.shutdown_initiated = true;
+                self.state
+                    .store(system_state::SHUTTING_DOWN, Ordering::Release);
+                self.executor_command_channels
+                    .iter()
+                    .for_each(|(_, channel)| {
+                        channel_send!(channel, ExecutorCommands::Shutdown);
+                    });
+            }
 
-                Ok(ManagerCommands::ExecutorShutdown { name }) => {
-                    if self.executor_command_channels.contains_key(&name) {
-                        self.executor_command_channels.remove(&name);
-                        if self.executor_command_channels.is_empty() {
-                            break;
+            ManagerCommands::ExecutorShutdown { name } => {
+                if self.executor_command_channels.contains_key(&name) {
+                    self.executor_command_channels.remove(&name);
+                    self.executor_run_queues.remove(&name);
+                    self.executor_actor_counts.remove(&name);
+                    self.executor_mailboxes.remove(&name);
+                    self.draining_executors.remove(&name);
+                    if let Some(group) = self.executor_group_names.remove(&name) {
+                        if let Some(group) = self.executor_groups.get_mut(&group) {
+                            group.executors.retain(|executor| *executor != name);
                         }
                     }
+                    if let Some(executor_handle) = self.executor_handles.remove(&name) {
+                        executor_handle.await_close();
+                    }
+                    if self.executor_command_channels.is_empty() {
+                        return false;
+                    }
                 }
-                Ok(ManagerCommands::AssignActor {
-                    actor,
-                    address,
-                    parent,
-                    ready_channel,
-                }) => {
-                    let executor_name = self.get_next_executor();
-                    let (sender, receiver) = unbounded::<Envelope>();
-                    let address_uri = address.uri.clone();
-                    address.set_mailbox(sender.clone());
-                    let cell = ActorCell::new(actor, receiver, address.clone(), parent);
-
-                    self.actor_registry.insert(
-                        address_uri,
-                        ActorRegistryEntry {
-                            mailbox: sender,
-                            executor: executor_name.clone(),
-                        },
-                    );
-
+            }
+            ManagerCommands::AddExecutor {
+                group,
+                return_channel,
+            } => {
+                let result = match self.resizable_group(&group) {
+                    Ok(_) => Ok(self.start_executor(&group)),
+                    Err(e) => Err(e),
+                };
+                if let Ok(name) = &result {
+                    info!("added executor {} to group {}", name, group);
+                }
+                channel_send!(return_channel, result);
+            }
+            ManagerCommands::RemoveExecutor {
+                name,
+                return_channel,
+            } => {
+                let result = self.remove_executor(&name);
+                channel_send!(return_channel, result);
+            }
+            ManagerCommands::ListExecutors {
+                group,
+                return_channel,
+            } => {
+                let executors = match self.executor_groups.get(&group) {
+                    Some(executor_group) => Ok(executor_group.executors.clone()),
+                    None => Err(BusanError::InvalidExecutor(format!(
+                        "unknown executor group {}",
+                        group
+                    ))),
+                };
+                channel_send!(return_channel, executors);
+            }
+            ManagerCommands::ExecutorDrained {
+                name,
+                actors,
+                timers,
+            } => self.move_actors(&name, actors, timers),
+            ManagerCommands::AssignActor {
+                mut actor,
+                name,
+                address,
+                parent,
+                options,
+                ready_channel,
+            } => {
+                // Paths of running (or stopping) actors can't be reused, which is only
+                // possible for top-level actors since children are named uniquely.
+                if self.actor_registry.contains_key(&address.uri)
+                    || self.actor_shutdown_staging.contains_key(&address.uri)
+                {
                     channel_send!(
-                        self.executor_command_channels.get(&executor_name).unwrap(),
-                        ExecutorCommands::AssignActor(cell)
+                        ready_channel,
+                        Err(BusanError::UnassignableActor(format!(
+                            "an actor is already running at {}",
+                            address.uri
+                        )))
                     );
-
-                    channel_send!(ready_channel, Ok(address));
+                    if let Some(parent) = &parent {
+                        self.send_child_stopped(parent, &address);
+                    }
+                    return true;
                 }
-                Ok(ManagerCommands::ActorShutdownNotice {
-                    address,
-                    parent,
-                    children,
-                }) => {
-                    trace!("system received shutdown notice for {}", address);
-                    // Remove the parent from the registry
-                    let self_lookup = self.actor_registry.remove(&address.uri);
-                    if self_lookup.is_some() {
-                        // Send notice to executors to perform local shutdown actions
-                        let num_children = children.len();
-                        for child in children {
-                            let child_lookup = self.actor_registry.get(&child.uri);
-                            if let Some(entry) = child_lookup {
-                                trace!(
-                                    "shutting down actor {} due to parent shutdown ({})",
-                                    child.uri,
-                                    address.uri
-                                );
-                                channel_send!(
-                                    self.executor_command_channels.get(&entry.executor).unwrap(),
-                                    ExecutorCommands::ShutdownActor(child)
-                                );
-                            }
+                let executor_name = match self.place_actor(
+                    &name,
+                    &address,
+                    parent.as_ref(),
+                    actor::is_async(actor.as_mut()),
+                    options,
+                ) {
+                    Ok(executor_name) => executor_name,
+                    Err(e) => {
+                        channel_send!(ready_channel, Err(e));
+                        if let Some(parent) = &parent {
+                            self.send_child_stopped(parent, &address);
                         }
+                        return true;
+                    }
+                };
+                let run_queue = self.executor_run_queues.get(&executor_name).cloned();
+                let (sender, receiver) = actor::Mailbox::new(address.incarnation, run_queue);
+                let address_uri = address.uri.clone();
+                address.set_mailbox(sender.clone());
+                let cell =
+                    ActorCell::new(actor, receiver, sender.schedule(), address.clone(), parent);
+
+                self.add_to_executor(&executor_name, &address_uri, &sender);
+                self.actor_registry.insert(
+                    address_uri,
+                    ActorRegistryEntry {
+                        incarnation: address.incarnation,
+                        mailbox: sender,
+                        executor: executor_name.clone(),
+                    },
+                );
+
+                channel_send!(
+                    self.executor_command_channels.get(&executor_name).unwrap(),
+                    ExecutorCommands::AssignActor(cell)
+                );
+
+                channel_send!(ready_channel, Ok(address));
+            }
+            ManagerCommands::ActorShutdownNotice {
+                address,
+                parent,
+                children,
+            } => {
+                trace!("system received shutdown notice for {}", address);
+                // Remove the parent from the registry
+                let self_lookup = self.actor_registry.remove(&address.uri);
+                if let Some(entry) = &self_lookup {
+                    self.remove_from_executor(&entry.executor, &address.uri);
+                    // Close the mailbox, so addresses resolved to it are re-resolved (and found
+                    // to be stopped) on their next use
+                    entry.mailbox.close();
+                    // The actor no longer accepts messages, so remove it from any services
+                    for service in self.services.remove_actor(&address.uri) {
+                        self.publish_listing(&service);
+                    }
 
-                        if num_children == 0 {
-                            // If there are no children, then we can go ahead and complete the
-                            // shutdown process for the actor.
-                            self.complete_actor_shutdown(
-                                &self_lookup.unwrap().executor,
-                                address.clone(),
-                                parent.clone(),
+                    // Send notice to executors to perform local shutdown actions. Children
+                    // that have already stopped are not waited on, but children that are
+                    // part-way through their own shutdown are.
+                    let mut pending_children = HashSet::new();
+                    for child in children {
+                        let child_executor = self
+                            .actor_registry
+                            .get(&child.uri)
+                            .map(|entry| entry.executor.clone());
+                        if let Some(executor) = child_executor {
+                            trace!(
+                                "shutting down actor {} due to parent shutdown ({})",
+                                child.uri,
+                                address.uri
                             );
-                        } else {
-                            // Otherwise, we'll need to create a shutdown handle for the current
-                            // actor. This will be accessed/updated each time a child has completed
-                            // shutdown and will let us know when it is safe to shutdown the current
-                            // actor.
-                            self.actor_shutdown_staging.insert(
-                                address.uri.clone(),
-                                ActorShutdownHandle {
-                                    parent,
-                                    wait_count: num_children,
-                                    executor: self_lookup.unwrap().executor.clone(),
-                                },
+                            pending_children.insert(child.uri.clone());
+                            self.send_to_executor(
+                                &executor,
+                                ExecutorCommands::ShutdownActor(child),
                             );
-                        }
-                    }
-                }
-                Ok(ManagerCommands::ActorChildShutdownNotice(parent_address)) => {
-                    // Handle notice that a child has shutdown. If the handle is not found, it means
-                    // the parent is not shutting down and no action is required.
-                    let mut is_complete = false;
-                    if let Some(handle) = self.actor_shutdown_staging.get_mut(&parent_address.uri) {
-                        handle.wait_count -= 1;
-                        // Check if all children have finished shutting down. If so, mark is_complete
-                        // to finish shutdown (below).
-                        if handle.wait_count == 0 {
-                            is_complete = true;
+                        } else if self.actor_shutdown_staging.contains_key(&child.uri) {
+                            pending_children.insert(child.uri.clone());
                         }
                     }
 
-                    if is_complete {
-                        let handle = self
-                            .actor_shutdown_staging
-                            .remove(&parent_address.uri)
-                            .unwrap();
+                    if pending_children.is_empty() {
+                        // If there are no children, then we can go ahead and complete the
+                        // shutdown process for the actor.
                         self.complete_actor_shutdown(
-                            &handle.executor,
-                            parent_address,
-                            handle.parent,
+                            &self_lookup.unwrap().executor,
+                            address.clone(),
+                            parent.clone(),
+                        );
+                    } else {
+                        // Otherwise, we'll need to create a shutdown handle for the current
+                        // actor. This will be accessed/updated each time a child has completed
+                        // shutdown and will let us know when it is safe to shutdown the current
+                        // actor.
+                        self.actor_shutdown_staging.insert(
+                            address.uri.clone(),
+                            ActorShutdownHandle {
+                                parent,
+                                pending_children,
+                                executor: self_lookup.unwrap().executor.clone(),
+                            },
                         );
                     }
                 }
-                Ok(ManagerCommands::ResolveAddress {
-                    address_uri,
-                    return_channel,
-                }) => {
-                    let lookup = self.actor_registry.get(&address_uri);
-                    let result = match lookup {
-                        Some(registry) => return_channel.try_send(Some(registry.mailbox.clone())),
-                        None => return_channel.try_send(None),
-                    };
-                    if result.is_err() {
-                        warn!(
-                            "Failed to send address resolution result on return channel: {}",
-                            result.err().unwrap(),
-                        );
+            }
+            ManagerCommands::ActorChildShutdownNotice {
+                parent: parent_address,
+                child,
+            } => {
+                // Handle notice that a child has shutdown. If the handle is not found, it means
+                // the parent is not shutting down and no action is required.
+                let mut is_complete = false;
+                if let Some(handle) = self.actor_shutdown_staging.get_mut(&parent_address.uri) {
+                    // Check if all children have finished shutting down. If so, mark is_complete
+                    // to finish shutdown (below).
+                    if handle.pending_children.remove(&child.uri)
+                        && handle.pending_children.is_empty()
+                    {
+                        is_complete = true;
+                    }
+                }
+
+                if is_complete {
+                    let handle = self
+                        .actor_shutdown_staging
+                        .remove(&parent_address.uri)
+                        .unwrap();
+                    self.complete_actor_shutdown(&handle.executor, parent_address, handle.parent);
+                }
+            }
+            ManagerCommands::Watch { watcher, target } => {
+                if self.lookup(&target).is_some() {
+                    let watchers = self.watchers.entry(target.uri.clone()).or_default();
+                    if !watchers.iter().any(|w| w.uri == watcher.uri) {
+                        watchers.push(watcher);
                     }
+                } else {
+                    // The actor has already terminated (or never existed), so the watcher
+                    // is notified right away.
+                    self.send_terminated(&watcher, &target);
+                }
+            }
+            ManagerCommands::Unwatch { watcher, target } => {
+                if let Some(watchers) = self.watchers.get_mut(&target.uri) {
+                    watchers.retain(|w| w.uri != watcher.uri);
                 }
-                Err(_) => {}
+            }
+            ManagerCommands::RegisterService { service, address } => {
+                if self.lookup(&address).is_none() {
+                    trace!("not registering stopped actor {} for {}", address, service);
+                } else if self.services.register(&service, address) {
+                    self.publish_listing(&service);
+                }
+            }
+            ManagerCommands::DeregisterService { service, address } => {
+                if self.services.deregister(&service, &address.uri) {
+                    self.publish_listing(&service);
+                }
+            }
+            ManagerCommands::SubscribeService {
+                service,
+                subscriber,
+            } => {
+                self.send_listing(&subscriber, &service);
+                self.services.subscribe(&service, subscriber);
+            }
+            ManagerCommands::UnsubscribeService {
+                service,
+                subscriber,
+            } => {
+                self.services.unsubscribe(&service, &subscriber.uri);
+            }
+            ManagerCommands::FindService {
+                service,
+                return_channel,
+            } => {
+                let found: Vec<ActorAddress> = self
+                    .services
+                    .registered(&service)
+                    .iter()
+                    .filter_map(|address| {
+                        let entry = self.lookup(address)?;
+                        Some(entry.resolved_address(&address.uri))
+                    })
+                    .collect();
+                if let Err(e) = return_channel.try_send(found) {
+                    warn!("Failed to send service lookup on return channel: {}", e);
+                }
+            }
+            ManagerCommands::DeadLetter {
+                recipient,
+                sender,
+                message,
+            } => {
+                info!(
+                    "dead letter for {} from {} ({} bytes)",
+                    recipient,
+                    sender
+                        .map(|sender| sender.to_string())
+                        .unwrap_or_else(|| "System".to_string()),
+                    Message::encoded_len(message.as_ref()),
+                );
+            }
+            ManagerCommands::Select {
+                pattern,
+                return_channel,
+            } => {
+                let mut matches: Vec<ActorAddress> = self
+                    .actor_registry
+                    .iter()
+                    .filter(|(uri, _)| pattern.matches(uri))
+                    .map(|(uri, entry)| entry.resolved_address(uri))
+                    .collect();
+                matches.sort_by(|a, b| a.uri.path_segments.cmp(&b.uri.path_segments));
+                if let Err(e) = return_channel.try_send(matches) {
+                    warn!("Failed to send selection result on return channel: {}", e);
+                }
+            }
+            ManagerCommands::ResolveAddress {
+                address,
+                return_channel,
+            } => {
+                // Addresses of a previous incarnation of the actor are not resolved, so
+                // messages for the stopped actor are not delivered to its replacement.
+                let mailbox = match self.lookup(&address) {
+                    Some(registry) => Some(registry.mailbox.clone()),
+                    None => self.inboxes.get(&address.uri).cloned(),
+                };
+                let result = return_channel.try_send(mailbox);
+                if result.is_err() {
+                    warn!(
+                        "Failed to send address resolution result on return channel: {}",
+                        result.err().unwrap(),
+                    );
+                }
+            }
+            ManagerCommands::RegisterInbox { address, mailbox } => {
+                self.inboxes.insert(address.uri, mailbox);
+            }
+            ManagerCommands::DeregisterInbox { uri } => {
+                self.inboxes.remove(&uri);
             }
         }
+        true
+    }
 
+    /// Stop handling commands once all executors have shut down.
+    fn terminate(&mut self) {
         info!("Runtime manager shutting down");
+        // Drop any remaining commands (and their return channels), so nothing waits on a reply
+        // from the stopped manager. See `RuntimeManagerRef::await_reply`.
+        self.state
+            .store(system_state::TERMINATED, Ordering::Release);
+        self.blocking_pool.shutdown();
+        while !self.manager_command_channel.recv_is_empty() {
+            let _ = self.manager_command_channel.recv();
+        }
     }
 
     fn complete_actor_shutdown(
-        &self,
-        executor: &String,
+        &mut self,
+        executor: &str,
         address: ActorAddress,
         parent: Option<ActorAddress>,
     ) {
+        // Let any watchers know the actor has terminated
+        if let Some(watchers) = self.watchers.remove(&address.uri) {
+            for watcher in watchers {
+                self.send_terminated(&watcher, &address);
+            }
+        }
+
         // Notify the executor the actor has completed shutdown so the executor
         // can do any final, necessary cleanup.
-        channel_send!(
-            self.executor_command_channels.get(executor).unwrap(),
-            ExecutorCommands::ShutdownActorComplete(address)
+        self.send_to_executor(
+            executor,
+            ExecutorCommands::ShutdownActorComplete(address.clone()),
         );
+        // Executors of pinned groups only run a single actor, so stop them with their actor
+        if self
+            .executor_group(executor)
+            .is_some_and(|group| group.pinned)
+        {
+            self.send_to_executor(executor, ExecutorCommands::Shutdown);
+        }
         // Send notice to the runtime manager that signals a child has been
         // shut down. This is necessary in case the parent is also shutting
-        // down (and must wait for child actor to shutdown first).
+        // down (and must wait for child actor to shutdown first). The parent
+        // itself is told as well, so the child is removed from its children.
         if let Some(p) = parent {
+            self.send_child_stopped(&p, &address);
             channel_send!(
                 self.manager_command_channel,
-                ManagerCommands::ActorChildShutdownNotice(p)
+                ManagerCommands::ActorChildShutdownNotice {
+                    parent: p,
+                    child: address,
+                }
             );
         }
         // Check if the system should shutdown
         self.maybe_shutdown();
     }
 
-    /// Checks if the system should shutdown (e.g. due to no running actors) and send
-    /// the shutdown signal if appropriate.
-    fn maybe_shutdown(&self) {
-        if self.actor_shutdown_staging.is_empty() && self.actor_registry.is_empty() {
-            trace!("No running actors or actors pending stop. Shutting down system");
-            channel_send!(self.manager_command_channel, ManagerCommands::Shutdown);
+    /// Deliver a [`Terminated`] message for `terminated` to `watcher`. The message is dropped
+    /// if the watcher itself is no longer running.
+    fn send_terminated(&self, watcher: &ActorAddress, terminated: &ActorAddress) {
+        if let Some(entry) = self.lookup(watcher) {
+            trace!("notifying {} of termination of {}", watcher, terminated);
+            let message = Terminated {
+                address: Some(terminated.to_message()),
+            };
+            let envelope = Envelope::new(None, watcher, Box::new(message), None);
+            channel_send!(entry.mailbox, envelope);
         }
     }
 
-    fn get_next_executor(&mut self) -> String {
-        let mut iter = self.executor_command_channels.iter();
-        debug_assert!(iter.len() > 0, "No executors found");
-        if self.round_robin_state >= iter.len() {
-            self.round_robin_state = 0;
+    /// Deliver a [`ChildStopped`] message for `child` to its `parent`, so the child is removed
+    /// from the parent's children. The message is dropped if the parent is no longer running.
+    fn send_child_stopped(&self, parent: &ActorAddress, child: &ActorAddress) {
+        if let Some(entry) = self.lookup(parent) {
+            let message = ChildStopped {
+                address: Some(child.to_message()),
+            };
+            let envelope = Envelope::new(None, parent, Box::new(message), None);
+            channel_send!(entry.mailbox, envelope);
         }
-        let executor = iter.nth(self.round_robin_state).unwrap().0.clone();
-        self.round_robin_state += 1;
-        executor
     }
-}
 
-/// An accounting structure for actors that are shutting down. Tracks the number of
-/// children pending shutdown (`wait_count`) and the executor of the actor.
-struct ActorShutdownHandle {
-    parent: Option<ActorAddress>,
-    wait_count: usize,
-    executor: String,
-}
+    /// Look up the registry entry of the actor `address` refers to, if it is running. Entries of
+    /// a different incarnation than the address are not returned.
+    fn lookup(&self, address: &ActorAddress) -> Option<&ActorRegistryEntry> {
+        self.actor_registry
+            .get(&address.uri)
+            .filter(|entry| address.matches_incarnation(entry.incarnation))
+    }
 
-/// `RuntimeManagerRef` is a handle for communicating to the runtime manager in a thread-safe
-/// manner. The `RuntimeManager` may only be interacted with through the `RuntimeManagerRef`.
-pub struct RuntimeManagerRef {
-    manager_command_channel: CommandChannel<ManagerCommands>,
-}
+    /// Send the current listing of `service` to all of its subscribers.
+    fn publish_listing(&self, service: &str) {
+        for subscriber in self.services.subscribers(service) {
+            self.send_listing(subscriber, service);
+        }
+    }
 
-impl RuntimeManagerRef {
-    fn new(manager_command_channel: CommandChannel<ManagerCommands>) -> RuntimeManagerRef {
-        RuntimeManagerRef {
-            manager_command_channel,
+    /// Send the current listing of `service` to `subscriber`. The message is dropped if the
+    /// subscriber is no longer running.
+    fn send_listing(&self, subscriber: &ActorAddress, service: &str) {
+        if let Some(entry) = self.lookup(subscriber) {
+            let listing = self.services.listing(service);
+            let envelope = Envelope::new(None, subscriber, Box::new(listing), None);
+            channel_send!(entry.mailbox, envelope);
         }
     }
 
-    /// Signal to the runtime manager to begin shutting down the system. This will result in
-    /// shutdown notifications being sent to all of the executors.
-    pub(crate) fn shutdown_system(&self) {
-        channel_send!(self.manager_command_channel, ManagerCommands::Shutdown);
+    /// Checks if the system should shutdown (e.g. due to no running actors) and send
+    /// the shutdown signal if appropriate.
+    fn maybe_shutdown(&self) {
+        if self.actor_shutdown_staging.is_empty() && self.actor_registry.is_empty() {
+            trace!("No running actors or actors pending stop. Shutting down system");
+            channel_send!(self.manager_command_channel, ManagerCommands::Shutdown);
+        }
     }
 
-    /// Signal to the runtime manager the the executor has completed (or is very near completing)
-    /// shutdown. This should only be called by the executor itself as the final step of it's
-    /// shutdown process.
-    pub(crate) fn notify_shutdown(&self, executor_name: String) {
+    /// Choose the executor for a new actor from the executor group of the spawn `options` (or
+    /// the default group), which must be able to run the actor if it `is_async`. Actors of pinned
+    /// groups get a new executor, otherwise the executor is chosen with the placement strategy of
+    /// the spawn `options`, or the system's placement strategy if there is none.
+    fn place_actor(
+        &mut self,
+        name: &str,
+        address: &ActorAddress,
+        parent: Option<&ActorAddress>,
+        is_async: bool,
+        options: SpawnOptions,
+    ) -> Result<String, BusanError> {
+        let group_name = options.group.as_deref().unwrap_or(DEFAULT_EXECUTOR_GROUP);
+        let group = self.executor_groups.get(group_name).ok_or_else(|| {
+            BusanError::UnassignableActor(format!("unknown executor group {}", group_name))
+        })?;
+        if is_async && !group.runs_async_actors {
+            return Err(BusanError::UnassignableActor(format!(
+                "async actors can't be run by the executors of group {}",
+                group_name
+            )));
+        }
+        if group.pinned {
+            // Executors started after shutdown has begun would never be stopped
+            if self.shutdown_initiated {
+                return Err(BusanError::SystemShutdown);
+            }
+            return Ok(self.start_executor(group_name));
+        }
+        let executors = &group.executors;
+        if executors.is_empty() {
+            return Err(BusanError::UnassignableActor(format!(
+                "no running executors in group {}",
+                group_name
+            )));
+        }
+
+        let parent_executor = parent
+            .and_then(|parent| self.actor_registry.get(&parent.uri))
+            .and_then(|entry| {
+                executors
+                    .iter()
+                    .position(|executor| *executor == entry.executor)
+            });
+        let mailboxes = &self.executor_mailboxes;
+        let queue_depth = |executor: &str| {
+            mailboxes.get(executor).map_or(0, |mailboxes| {
+                mailboxes.values().map(actor::Mailbox::len).sum()
+            })
+        };
+        let ctx = PlacementContext::new(
+            name,
+            address,
+            parent_executor,
+            executors,
+            &self.executor_actor_counts,
+            &queue_depth,
+        );
+        let index = match options.placement {
+            Some(mut strategy) => strategy.place(&ctx),
+            None => self.placement_strategy.place(&ctx),
+        };
+        Ok(executors[index % executors.len()].clone())
+    }
+
+    /// Record that the actor at `uri`, with `mailbox`, runs on `executor`.
+    fn add_to_executor(&mut self, executor: &str, uri: &Uri, mailbox: &actor::Mailbox) {
+        *self
+            .executor_actor_counts
+            .entry(executor.to_string())
+            .or_default() += 1;
+        self.executor_mailboxes
+            .entry(executor.to_string())
+            .or_default()
+            .insert(uri.clone(), mailbox.clone());
+    }
+
+    /// Record that the actor at `uri` no longer runs on `executor`.
+    fn remove_from_executor(&mut self, executor: &str, uri: &Uri) {
+        if let Some(count) = self.executor_actor_counts.get_mut(executor) {
+            *count -= 1;
+        }
+        if let Some(mailboxes) = self.executor_mailboxes.get_mut(executor) {
+            mailboxes.remove(uri);
+        }
+    }
+
+    /// The executor group `group_name`, if executors can be added to or removed from it.
+    fn resizable_group(&self, group_name: &str) -> Result<&ExecutorGroup, BusanError> {
+        // Executors started after shutdown has begun would never be stopped
+        if self.shutdown_initiated {
+            return Err(BusanError::SystemShutdown);
+        }
+        let group = self.executor_groups.get(group_name).ok_or_else(|| {
+            BusanError::InvalidExecutor(format!("unknown executor group {}", group_name))
+        })?;
+        if group.pinned {
+            return Err(BusanError::InvalidExecutor(format!(
+                "executors of pinned group {} are started and stopped with their actor",
+                group_name
+            )));
+        }
+        Ok(group)
+    }
+
+    /// Remove the executor `name` from its group and drain it. Its actors are moved to the
+    /// remaining executors of the group once the executor has drained (see
+    /// [`Self::move_actors`]).
+    fn remove_executor(&mut self, name: &str) -> Result<(), BusanError> {
+        if self.shutdown_initiated {
+            return Err(BusanError::SystemShutdown);
+        }
+        let group_name = match self.executor_group_names.get(name) {
+            Some(group_name) if !self.draining_executors.contains_key(name) => group_name.clone(),
+            _ => {
+                return Err(BusanError::InvalidExecutor(format!(
+                    "unknown executor {}",
+                    name
+                )))
+            }
+        };
+        if self.resizable_group(&group_name)?.executors.len() == 1 {
+            return Err(BusanError::InvalidExecutor(format!(
+                "can't remove the last executor of group {}",
+                group_name
+            )));
+        }
+
+        info!("removing executor {} from group {}", name, group_name);
+        if let Some(group) = self.executor_groups.get_mut(&group_name) {
+            group.executors.retain(|executor| executor != name);
+        }
+        self.draining_executors.insert(name.to_string(), Vec::new());
+        channel_send!(
+            self.executor_command_channels.get(name).unwrap(),
+            ExecutorCommands::Drain
+        );
+        Ok(())
+    }
+
+    /// Move the `actors` and `timers` handed back by the drained executor `executor_name` to the
+    /// remaining executors of its group. Actors are placed with the placement strategy of the
+    /// system, and actors the executor did not hand back (as they are shared with other executors
+    /// of the group) are spread over the remaining executors. Commands held back while the
+    /// executor was draining are then sent to the executors the actors moved to.
+    fn move_actors(
+        &mut self,
+        executor_name: &str,
+        actors: Vec<ActorCell>,
+        timers: Vec<ScheduledMessage>,
+    ) {
+        let held_back = self
+            .draining_executors
+            .remove(executor_name)
+            .unwrap_or_default();
+        // Executors are shutting down, so there is nothing to move the actors to
+        if self.shutdown_initiated {
+            return;
+        }
+        let group_name = self.executor_group_names[executor_name].clone();
+        let executors = self.executor_groups[&group_name].executors.clone();
+
+        // Incarnations of the moved actors and the executors they moved to
+        let mut moved = HashMap::new();
+        for mut cell in actors {
+            let address = cell.address.clone();
+            let name = address
+                .uri
+                .path_segments
+                .last()
+                .cloned()
+                .unwrap_or_default();
+            let parent = cell.parent.clone();
+            let placed = self.place_actor(
+                &name,
+                &address,
+                parent.as_ref(),
+                actor::is_async(cell.actor.as_mut()),
+                SpawnOptions::new().with_group(&group_name),
+            );
+            // Actors that can't be moved within their group are stopped on another executor, so
+            // they still go through the regular stop (and their parent and watchers are notified)
+            let (target, stop) = match placed {
+                Ok(target) => (target, false),
+                Err(e) => match self.fallback_executor() {
+                    Some(target) => {
+                        warn!(
+                            "unable to move actor {}: {}, stopping it on executor {}",
+                            address, e, target
+                        );
+                        // Queued messages are sent to dead letters rather than processed
+                        cell_state::set_shutdown(&mut cell.state);
+                        (target, true)
+                    }
+                    None => {
+                        warn!("unable to move actor {}: {}", address, e);
+                        continue;
+                    }
+                },
+            };
+            trace!("moving actor {} to executor {}", address, target);
+
+            let mailbox = self
+                .actor_registry
+                .get_mut(&address.uri)
+                .filter(|entry| entry.incarnation == address.incarnation)
+                .map(|entry| {
+                    entry.executor = target.clone();
+                    entry.mailbox.clone()
+                });
+            if let Some(mailbox) = mailbox {
+                self.add_to_executor(&target, &address.uri, &mailbox);
+            }
+            if let Some(handle) = self.actor_shutdown_staging.get_mut(&address.uri) {
+                handle.executor = target.clone();
+            }
+            // Messages sent from now on schedule the actor on its new executor, which schedules
+            // the actor once assigned if it has pending messages.
+            cell.schedule
+                .set_run_queue(self.executor_run_queues[&target].clone());
+            channel_send!(
+                self.executor_command_channels.get(&target).unwrap(),
+                ExecutorCommands::AssignActor(cell)
+            );
+            if stop {
+                channel_send!(
+                    self.executor_command_channels.get(&target).unwrap(),
+                    ExecutorCommands::ShutdownActor(address.clone())
+                );
+            }
+            moved.insert(address.incarnation, target);
+        }
+
+        let mut next_executor = executors.iter().cycle();
+        let mut shared = Vec::new();
+        for (uri, entry) in self.actor_registry.iter_mut() {
+            if entry.executor == executor_name {
+                let target = next_executor.next().unwrap();
+                entry.executor = target.clone();
+                moved.insert(entry.incarnation, target.clone());
+                shared.push((target, uri.clone(), entry.mailbox.clone()));
+            }
+        }
+        for (target, uri, mailbox) in shared {
+            self.add_to_executor(target, &uri, &mailbox);
+        }
+        for handle in self.actor_shutdown_staging.values_mut() {
+            if handle.executor == executor_name {
+                handle.executor = next_executor.next().unwrap().clone();
+            }
+        }
+
+        for message in timers {
+            channel_send!(
+                self.executor_command_channels.get(&executors[0]).unwrap(),
+                ExecutorCommands::ScheduleMessage(message)
+            );
+        }
+        for command in held_back {
+            let target = match &command {
+                ExecutorCommands::ShutdownActor(address)
+                | ExecutorCommands::ShutdownActorComplete(address) => {
+                    moved.get(&address.incarnation)
+                }
+                _ => None,
+            };
+            let target = target.unwrap_or(&executors[0]);
+            channel_send!(self.executor_command_channels.get(target).unwrap(), command);
+        }
+    }
+
+    /// A running executor of any (shared) executor group, preferring the default group. Used for
+    /// stopping actors that could not be moved to another executor of their own group.
+    fn fallback_executor(&self) -> Option<String> {
+        iter::once(DEFAULT_EXECUTOR_GROUP)
+            .chain(self.executor_groups.keys().map(String::as_str))
+            .filter_map(|group| self.executor_groups.get(group))
+            .filter(|group| !group.pinned)
+            .find_map(|group| group.executors.first().cloned())
+    }
+}
+
+/// The executors of an executor group (see [`ExecutorGroupConfig`](config::ExecutorGroupConfig)),
+/// in the order they were added. Executors are identified by their index in this list for
+/// placement (see [`PlacementContext`]).
+struct ExecutorGroup {
+    executors: Vec<String>,
+    /// Whether each actor of the group is given an executor of its own
+    pinned: bool,
+    /// Whether the executors of the group can run async actors
+    runs_async_actors: bool,
+    /// Starts the executors of the group
+    factory: Box<dyn ExecutorFactory>,
+    /// Number of the next executor started in the group, used to name the executor
+    next_executor_id: usize,
+}
+
+/// An accounting structure for actors that are shutting down. Tracks the children pending
+/// shutdown (`pending_children`) and the executor of the actor.
+struct ActorShutdownHandle {
+    parent: Option<ActorAddress>,
+    pending_children: HashSet<Uri>,
+    executor: String,
+}
+
+/// `RuntimeManagerRef` is a handle for communicating to the runtime manager in a thread-safe
+/// manner. The `RuntimeManager` may only be interacted with through the `RuntimeManagerRef`.
+#[derive(Clone)]
+pub struct RuntimeManagerRef {
+    manager_command_channel: CommandChannel<ManagerCommands>,
+    state: Arc<AtomicU8>,
+    blocking_pool: Arc<BlockingPool>,
+    deterministic: Option<Weak<DeterministicRuntime>>,
+}
+
+impl RuntimeManagerRef {
+    fn new(
+        manager_command_channel: CommandChannel<ManagerCommands>,
+        state: Arc<AtomicU8>,
+        blocking_pool: Arc<BlockingPool>,
+        deterministic: Option<Weak<DeterministicRuntime>>,
+    ) -> RuntimeManagerRef {
+        RuntimeManagerRef {
+            manager_command_channel,
+            state,
+            blocking_pool,
+            deterministic,
+        }
+    }
+
+    fn deterministic_runtime(&self) -> Option<Arc<DeterministicRuntime>> {
+        self.deterministic.as_ref().and_then(Weak::upgrade)
+    }
+
+    /// The current time of the system, which is virtual for deterministic systems.
+    pub(crate) fn now(&self) -> Instant {
+        match self.deterministic_runtime() {
+            Some(runtime) => runtime.clock.now(),
+            None => Instant::now(),
+        }
+    }
+
+    /// A random number generator for an actor. The generators of deterministic systems are
+    /// seeded from the seed of the system.
+    pub(crate) fn rng(&self) -> Rng {
+        match self.deterministic_runtime() {
+            Some(runtime) => Rng::with_seed(runtime.actor_rng.lock().unwrap().next_u64()),
+            None => Rng::from_entropy(),
+        }
+    }
+
+    /// Run `job` on the blocking thread pool of the system. Deterministic systems run the job
+    /// right away instead, as the order of jobs running on other threads is not reproducible.
+    pub(crate) fn spawn_blocking(&self, job: impl FnOnce() + Send + 'static) {
+        if self.deterministic.is_some() {
+            job();
+        } else {
+            self.blocking_pool.spawn(Box::new(job));
+        }
+    }
+
+    /// Returns true until the system has started shutting down.
+    pub(crate) fn is_running(&self) -> bool {
+        self.state.load(Ordering::Acquire) == system_state::RUNNING
+    }
+
+    /// Wait for the reply to a request sent to the runtime manager, or `None` if the manager
+    /// stopped without replying.
+    pub(crate) fn await_reply<T>(&self, receiver: &Receiver<T>) -> Option<T> {
+        if let Some(runtime) = self.deterministic_runtime() {
+            // The runtime manager of a deterministic system runs on the waiting thread, so the
+            // request is handled right away.
+            while receiver.is_empty() && runtime.handle_manager_command() {}
+            return receiver.try_recv().ok();
+        }
+        const POLL_INTERVAL: Duration = Duration::from_millis(10);
+        loop {
+            match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(reply) => return Some(reply),
+                Err(RecvTimeoutError::Disconnected) => return None,
+                // Requests sent before the manager stopped have been dropped (disconnecting
+                // the return channel), but requests sent since will never be handled.
+                Err(RecvTimeoutError::Timeout)
+                    if self.state.load(Ordering::Acquire) == system_state::TERMINATED =>
+                {
+                    return receiver.try_recv().ok()
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+        }
+    }
+
+    /// Signal to the runtime manager to begin shutting down the system. This will result in
+    /// shutdown notifications being sent to all of the executors.
+    pub(crate) fn shutdown_system(&self) {
+        channel_send!(self.manager_command_channel, ManagerCommands::Shutdown);
+    }
+
+    /// Signal to the runtime manager the the executor has completed (or is very near completing)
+    /// shutdown. This should only be called by the executor itself as the final step of it's
+    /// shutdown process.
+    pub fn notify_shutdown(&self, executor_name: String) {
         channel_send!(
             self.manager_command_channel,
             ManagerCommands::ExecutorShutdown {
@@ -408,22 +1637,85 @@ impl RuntimeManagerRef {
         );
     }
 
+    /// Hand the actors and scheduled messages of a drained executor (see
+    /// [`ExecutorCommands::Drain`]) back to the runtime manager, which moves them to the other
+    /// executors of the group. This should only be called by the executor itself, before
+    /// [`notify_shutdown`](RuntimeManagerRef::notify_shutdown).
+    pub fn executor_drained(
+        &self,
+        executor_name: String,
+        actors: Vec<ActorCell>,
+        timers: Vec<ScheduledMessage>,
+    ) {
+        channel_send!(
+            self.manager_command_channel,
+            ManagerCommands::ExecutorDrained {
+                name: executor_name,
+                actors,
+                timers,
+            }
+        );
+    }
+
+    /// Request that an executor is added to the executor group `group`.
+    pub(crate) fn add_executor(&self, group: &str) -> Receiver<Result<String, BusanError>> {
+        let (sender, receiver) = bounded(1);
+        channel_send!(
+            self.manager_command_channel,
+            ManagerCommands::AddExecutor {
+                group: group.to_string(),
+                return_channel: sender,
+            }
+        );
+        receiver
+    }
+
+    /// Request that the executor `name` is removed from its group.
+    pub(crate) fn remove_executor(&self, name: &str) -> Receiver<Result<(), BusanError>> {
+        let (sender, receiver) = bounded(1);
+        channel_send!(
+            self.manager_command_channel,
+            ManagerCommands::RemoveExecutor {
+                name: name.to_string(),
+                return_channel: sender,
+            }
+        );
+        receiver
+    }
+
+    /// Request the names of the executors of the executor group `group`.
+    pub(crate) fn list_executors(&self, group: &str) -> Receiver<Result<Vec<String>, BusanError>> {
+        let (sender, receiver) = bounded(1);
+        channel_send!(
+            self.manager_command_channel,
+            ManagerCommands::ListExecutors {
+                group: group.to_string(),
+                return_channel: sender,
+            }
+        );
+        receiver
+    }
+
     /// Request that a new actor be assigned to a runtime executor. This may be called when assigning
     /// either a root actor or a child actor. This should be used to avoid blocking actor creation
     /// on a single executor.
     pub(crate) fn assign_actor(
         &self,
         actor: Box<dyn Actor>,
+        name: &str,
         address: ActorAddress,
         parent: Option<ActorAddress>,
+        options: SpawnOptions,
     ) -> Receiver<Result<ActorAddress, BusanError>> {
         let (sender, receiver) = bounded::<Result<ActorAddress, BusanError>>(1);
         channel_send!(
             self.manager_command_channel,
             ManagerCommands::AssignActor {
                 actor,
+                name: name.to_string(),
                 address,
                 parent,
+                options,
                 ready_channel: sender,
             }
         );
@@ -446,20 +1738,304 @@ impl RuntimeManagerRef {
         );
     }
 
+    /// Register `watcher` to be notified when `target` terminates.
+    pub(crate) fn watch(&self, watcher: &ActorAddress, target: &ActorAddress) {
+        channel_send!(
+            self.manager_command_channel,
+            ManagerCommands::Watch {
+                watcher: watcher.clone(),
+                target: target.clone(),
+            }
+        );
+    }
+
+    /// Remove a registration previously made with [`RuntimeManagerRef::watch`].
+    pub(crate) fn unwatch(&self, watcher: &ActorAddress, target: &ActorAddress) {
+        channel_send!(
+            self.manager_command_channel,
+            ManagerCommands::Unwatch {
+                watcher: watcher.clone(),
+                target: target.clone(),
+            }
+        );
+    }
+
+    /// Register `address` with the receptionist under `service`.
+    pub(crate) fn register_service(&self, service: &str, address: &ActorAddress) {
+        channel_send!(
+            self.manager_command_channel,
+            ManagerCommands::RegisterService {
+                service: service.to_string(),
+                address: address.clone(),
+            }
+        );
+    }
+
+    /// Remove a registration made with [`RuntimeManagerRef::register_service`].
+    pub(crate) fn deregister_service(&self, service: &str, address: &ActorAddress) {
+        channel_send!(
+            self.manager_command_channel,
+            ManagerCommands::DeregisterService {
+                service: service.to_string(),
+                address: address.clone(),
+            }
+        );
+    }
+
+    /// Subscribe `subscriber` to changes in the actors registered under `service`.
+    pub(crate) fn subscribe_service(&self, service: &str, subscriber: &ActorAddress) {
+        channel_send!(
+            self.manager_command_channel,
+            ManagerCommands::SubscribeService {
+                service: service.to_string(),
+                subscriber: subscriber.clone(),
+            }
+        );
+    }
+
+    /// Remove a subscription made with [`RuntimeManagerRef::subscribe_service`].
+    pub(crate) fn unsubscribe_service(&self, service: &str, subscriber: &ActorAddress) {
+        channel_send!(
+            self.manager_command_channel,
+            ManagerCommands::UnsubscribeService {
+                service: service.to_string(),
+                subscriber: subscriber.clone(),
+            }
+        );
+    }
+
+    /// Look up the actors registered under `service`, returning resolved addresses in order of
+    /// registration. Note that this will block until the management thread has performed the
+    /// lookup.
+    pub(crate) fn find_service(&self, service: &str) -> Vec<ActorAddress> {
+        let (sender, receiver) = bounded::<Vec<ActorAddress>>(1);
+        channel_send!(
+            self.manager_command_channel,
+            ManagerCommands::FindService {
+                service: service.to_string(),
+                return_channel: sender,
+            }
+        );
+
+        self.await_reply(&receiver).unwrap_or_default()
+    }
+
+    /// Hand a message that could not be delivered to the dead letter queue.
+    pub(crate) fn dead_letter(
+        &self,
+        recipient: &ActorAddress,
+        sender: Option<ActorAddress>,
+        message: Box<dyn Message>,
+    ) {
+        channel_send!(
+            self.manager_command_channel,
+            ManagerCommands::DeadLetter {
+                recipient: recipient.clone(),
+                sender,
+                message,
+            }
+        );
+    }
+
+    /// Look up all actors in the global registry matching `pattern`, returning resolved
+    /// addresses ordered by path. Note that this will block until the management thread has
+    /// performed the lookup.
+    pub(crate) fn select(&self, pattern: &PathPattern) -> Vec<ActorAddress> {
+        let (sender, receiver) = bounded::<Vec<ActorAddress>>(1);
+        channel_send!(
+            self.manager_command_channel,
+            ManagerCommands::Select {
+                pattern: pattern.clone(),
+                return_channel: sender,
+            }
+        );
+
+        self.await_reply(&receiver).unwrap_or_default()
+    }
+
     /// Resolve an address to mailbox by looking up the actor in the global registry. Note that this
     /// will block until the management thread has performed the lookup.
     pub(crate) fn resolve_address(&self, address: &ActorAddress) -> Option<actor::Mailbox> {
-        let uri = address.uri.clone();
         let (sender, receiver) = bounded::<Option<actor::Mailbox>>(1);
         channel_send!(
             self.manager_command_channel,
             ManagerCommands::ResolveAddress {
-                address_uri: uri,
+                address: address.clone(),
                 return_channel: sender,
             }
         );
 
-        channel_must_recv!(receiver)
+        self.await_reply(&receiver).flatten()
+    }
+
+    /// Resolve the mailbox of the address if it is not already resolved, or if the actor it was
+    /// resolved to has stopped since. This is a blocking call to the runtime manager if the
+    /// address needs to be resolved. Returns `false` if the address could not be resolved.
+    pub(crate) fn resolve(&self, addr: &ActorAddress) -> bool {
+        if addr.is_resolved_open() {
+            return true;
+        }
+        trace!("Resolving address: {}", addr);
+        match self.resolve_address(addr) {
+            Some(resolved) => {
+                addr.set_mailbox(resolved);
+                true
+            }
+            None => {
+                addr.clear_mailbox();
+                false
+            }
+        }
+    }
+
+    /// Deliver a message to `addr`, resolving the address first if necessary. Messages that
+    /// can't be delivered are handed to the dead letter queue.
+    pub(crate) fn deliver(
+        &self,
+        addr: &ActorAddress,
+        sender: Option<ActorAddress>,
+        message: Box<dyn Message>,
+        ack_nonce: Option<u32>,
+    ) {
+        // Validate that the address is resolved (this is a blocking call to the runtime
+        // manager if unresolved).
+        if !self.resolve(addr) {
+            self.dead_letter(addr, sender, message);
+            return;
+        }
+
+        // Send the message to the resolved address. If the mailbox has been closed, the actor
+        // stopped after the address was resolved, so the address is resolved once more in case
+        // another actor has taken its place (only possible if the incarnation is unknown). The
+        // address may also have been cleared by another thread sharing it, which is handled the
+        // same way.
+        let (sender, message) = match addr.send(sender, message, ack_nonce) {
+            Ok(()) => return,
+            Err(undelivered) => undelivered,
+        };
+        addr.clear_mailbox();
+        if !self.resolve(addr) {
+            self.dead_letter(addr, sender, message);
+            return;
+        }
+        if let Err((sender, message)) = addr.send(sender, message, ack_nonce) {
+            self.dead_letter(addr, sender, message);
+        }
+    }
+
+    /// Make the temporary address of an [`Inbox`] resolvable.
+    fn register_inbox(&self, address: &ActorAddress, mailbox: actor::Mailbox) {
+        channel_send!(
+            self.manager_command_channel,
+            ManagerCommands::RegisterInbox {
+                address: address.clone(),
+                mailbox,
+            }
+        );
+    }
+
+    fn deregister_inbox(&self, address: &ActorAddress) {
+        channel_send!(
+            self.manager_command_channel,
+            ManagerCommands::DeregisterInbox {
+                uri: address.uri.clone(),
+            }
+        );
+    }
+}
+
+/// Runtime of a deterministic system (see `ExecutorType::Deterministic`). The runtime manager and
+/// the executors have no threads of their own, they are run by the thread driving the system one
+/// unit of work at a time. Each step, the PRNG of the system picks the next unit of work among the
+/// commands of the runtime manager and the work of the executors.
+struct DeterministicRuntime {
+    /// The runtime manager, until it has stopped
+    manager: Mutex<Option<RuntimeManager>>,
+    manager_command_channel: CommandChannel<ManagerCommands>,
+    executor: Mutex<DeterministicExecutor>,
+    clock: Arc<VirtualClock>,
+    /// Seeds the random number generators of actors (see [`RuntimeManagerRef::rng`])
+    actor_rng: Mutex<Rng>,
+}
+
+impl DeterministicRuntime {
+    fn get_ref(&self) -> RuntimeManagerRef {
+        self.manager.lock().unwrap().as_ref().unwrap().get_ref()
+    }
+
+    fn is_terminated(&self) -> bool {
+        self.manager.lock().unwrap().is_none()
+    }
+
+    /// Handle the next command of the runtime manager. Returns `false` if there is none, or the
+    /// manager has stopped.
+    fn handle_manager_command(&self) -> bool {
+        let mut manager = self.manager.lock().unwrap();
+        let runtime_manager = match manager.as_mut() {
+            Some(runtime_manager) => runtime_manager,
+            None => return false,
+        };
+        let command = match self.manager_command_channel.try_recv() {
+            Some(command) => command,
+            None => return false,
+        };
+        if !runtime_manager.handle(command) {
+            runtime_manager.terminate();
+            *manager = None;
+        }
+        true
+    }
+
+    /// Perform the next unit of work. Returns `false` if the system is idle.
+    fn step(&self) -> bool {
+        let mut executor = match self.executor.try_lock() {
+            Ok(executor) => executor,
+            Err(TryLockError::WouldBlock) => {
+                panic!("deterministic actor systems can't be driven from within their actors")
+            }
+            Err(TryLockError::Poisoned(e)) => panic!("{}", e),
+        };
+        let mut work = executor.pending_work();
+        let manager_pending =
+            !self.manager_command_channel.recv_is_empty() && !self.is_terminated();
+        let choices = work.len() + usize::from(manager_pending);
+        if choices == 0 {
+            return false;
+        }
+        let choice = executor.choose(choices);
+        if choice < work.len() {
+            executor.perform(work.swap_remove(choice));
+        } else {
+            drop(executor);
+            self.handle_manager_command();
+        }
+        true
+    }
+
+    /// Run the system until `done` returns true, in which case `true` is returned. Whenever the
+    /// system is idle, virtual time advances to the next timer, but not past `until`. Returns the
+    /// result of `done` once the system is idle and no timer is due until then.
+    fn run_until(&self, until: Option<Instant>, mut done: impl FnMut() -> bool) -> bool {
+        loop {
+            if done() {
+                return true;
+            }
+            if self.step() {
+                continue;
+            }
+            let next_deadline = self.executor.lock().unwrap().next_deadline();
+            match next_deadline {
+                Some(deadline) if until.is_none_or(|until| deadline <= until) => {
+                    self.clock.advance_to(deadline);
+                }
+                _ => {
+                    if let Some(until) = until {
+                        self.clock.advance_to(until);
+                    }
+                    return done();
+                }
+            }
+        }
     }
 }
 
@@ -473,6 +2049,34 @@ enum ManagerCommands {
     /// Notification from an executor (identified by the name field) that it has completed shutdown
     ExecutorShutdown { name: String },
 
+    /// A request to start an additional executor in `group`. The name of the executor is sent
+    /// on the return channel.
+    AddExecutor {
+        group: String,
+        return_channel: Sender<Result<String, BusanError>>,
+    },
+
+    /// A request to remove the executor `name` from its group. The executor is drained and its
+    /// actors moved to the other executors of the group once it has sent `ExecutorDrained`.
+    RemoveExecutor {
+        name: String,
+        return_channel: Sender<Result<(), BusanError>>,
+    },
+
+    /// A request for the names of the executors of `group`
+    ListExecutors {
+        group: String,
+        return_channel: Sender<Result<Vec<String>, BusanError>>,
+    },
+
+    /// The actors and scheduled messages handed back by a drained executor (identified by the
+    /// name field), which are moved to the remaining executors of its group
+    ExecutorDrained {
+        name: String,
+        actors: Vec<ActorCell>,
+        timers: Vec<ScheduledMessage>,
+    },
+
     /// A request that a newly constructed `Actor` be "realized" in the actor system.
     ///   + Wrap the actor into an `ActorCell` with a mailbox and address
     ///   + Store the `ActorAddress` in a global registry for address resolution
@@ -480,8 +2084,11 @@ enum ManagerCommands {
     ///   + Return a fully realized address of the assigned actor through the `ready_channel`
     AssignActor {
         actor: Box<dyn Actor>,
+        /// The name the actor was spawned with
+        name: String,
         address: ActorAddress,
         parent: Option<ActorAddress>,
+        options: SpawnOptions,
         ready_channel: Sender<Result<ActorAddress, BusanError>>,
     },
 
@@ -494,21 +2101,611 @@ enum ManagerCommands {
         children: Vec<ActorAddress>,
     },
 
-    /// TODO: Document
-    ActorChildShutdownNotice(ActorAddress),
+    /// Notice that `child` has completed shutdown. Used to complete the shutdown of `parent`
+    /// once all of its children have stopped.
+    ActorChildShutdownNotice {
+        parent: ActorAddress,
+        child: ActorAddress,
+    },
+
+    /// Register `watcher` to receive a `Terminated` message once `target` has stopped
+    Watch {
+        watcher: ActorAddress,
+        target: ActorAddress,
+    },
+
+    /// Remove a previous `Watch` registration
+    Unwatch {
+        watcher: ActorAddress,
+        target: ActorAddress,
+    },
+
+    /// Register `address` with the receptionist under `service`. Subscribers of the service
+    /// are sent the updated listing.
+    RegisterService {
+        service: String,
+        address: ActorAddress,
+    },
+
+    /// Remove a previous `RegisterService` registration
+    DeregisterService {
+        service: String,
+        address: ActorAddress,
+    },
+
+    /// Send `subscriber` the listing of `service` now and whenever it changes
+    SubscribeService {
+        service: String,
+        subscriber: ActorAddress,
+    },
+
+    /// Remove a previous `SubscribeService` subscription
+    UnsubscribeService {
+        service: String,
+        subscriber: ActorAddress,
+    },
+
+    /// A request for the mailboxes of the actors registered under `service`
+    FindService {
+        service: String,
+        return_channel: Sender<Vec<ActorAddress>>,
+    },
+
+    /// A message that could not be delivered to `recipient`. Dead letters are currently logged
+    /// and then dropped.
+    DeadLetter {
+        recipient: ActorAddress,
+        sender: Option<ActorAddress>,
+        message: Box<dyn Message>,
+    },
+
+    /// A request for the mailboxes of all actors matching `pattern` (see [`ActorSelection`]).
+    /// Like `ResolveAddress`, the result is sent on a direct return channel.
+    Select {
+        pattern: PathPattern,
+        return_channel: Sender<Vec<ActorAddress>>,
+    },
 
     /// A request to resolve an actor address to a mailbox. This is given a direct return
     /// channel so the sender can block on the result of the lookup if desired.
     ResolveAddress {
-        address_uri: Uri,
+        address: ActorAddress,
         return_channel: Sender<Option<actor::Mailbox>>,
     },
+
+    /// Register the temporary address of an [`Inbox`], so it can be resolved by actors
+    RegisterInbox {
+        address: ActorAddress,
+        mailbox: actor::Mailbox,
+    },
+
+    /// Remove a previous `RegisterInbox` registration
+    DeregisterInbox { uri: Uri },
 }
 
 /// Value of actor-registry in the runtime manager. See [`RuntimeManager`] for details.
 struct ActorRegistryEntry {
+    /// The incarnation of the actor running at the path (see [`ActorAddress::incarnation`])
+    incarnation: u64,
     mailbox: actor::Mailbox,
     /// The name of the executor the actor is running on (may be used to lookup the executor's
     /// command channel).
     executor: String,
 }
+
+impl ActorRegistryEntry {
+    /// Create a resolved address for the actor at `uri`.
+    fn resolved_address(&self, uri: &Uri) -> ActorAddress {
+        ActorAddress::new_resolved(uri.clone(), self.incarnation, self.mailbox.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::Context;
+    use crate::config::{ActorSystemConfig, ExecutorConfig, ExecutorGroupConfig};
+    use crate::executor::placement::{LeastLoaded, LoadMetric};
+    use crate::message::common_types::{StringWrapper, U32ListWrapper, U32Wrapper};
+    use crate::message::system::PoisonPill;
+    use crate::util::testing::{string_value, Block, Echo, Forward, Sink};
+    use std::sync::atomic::AtomicBool;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn test_handle_send() {
+        let system = ActorSystem::init(ActorSystemConfig::default());
+        let handle = system.handle();
+        let inbox = handle.inbox().unwrap();
+        let forward = handle
+            .spawn_actor::<Forward, _, _>("forward", inbox.address().to_string())
+            .unwrap();
+
+        handle.send(&forward, "hello").unwrap();
+        assert_eq!(string_value(inbox.receive(TIMEOUT).unwrap()), "hello");
+        system.shutdown();
+    }
+
+    #[test]
+    fn test_handle_ask() {
+        let system = ActorSystem::init(ActorSystemConfig::default());
+        let handle = system.handle();
+        let echo = handle.spawn_actor::<Echo, _, _>("echo", "").unwrap();
+        let sink = handle.spawn_actor::<Sink, _, _>("sink", "").unwrap();
+
+        let reply = handle.ask(&echo, "ping", TIMEOUT).unwrap();
+        assert_eq!(string_value(reply), "ping");
+
+        let timeout = Duration::from_millis(50);
+        assert!(matches!(
+            handle.ask(&sink, "ping", timeout),
+            Err(BusanError::Timeout(waited)) if waited == timeout
+        ));
+        system.shutdown();
+    }
+
+    /// Sent messages are serialized and deserialized again in debug builds, which must not
+    /// duplicate the elements of repeated fields.
+    #[test]
+    fn test_send_repeated_field() {
+        let system = ActorSystem::init(ActorSystemConfig::default());
+        let handle = system.handle();
+        let echo = handle.spawn_actor::<Echo, _, _>("echo", "").unwrap();
+
+        let values = vec![1u32, 2, 3];
+        let reply = handle.ask(&echo, values.clone(), TIMEOUT).unwrap();
+        let reply = reply.as_any().downcast_ref::<U32ListWrapper>().unwrap();
+        assert_eq!(reply.values, values);
+        system.shutdown();
+    }
+
+    /// Actors placed by queue depth avoid the executor of an actor with a backlog
+    #[test]
+    fn test_place_by_queue_depth() {
+        let system = ActorSystem::init(ActorSystemConfig {
+            executor_config: ExecutorConfig {
+                num_executors: 2,
+                ..ExecutorConfig::default()
+            },
+            placement_strategy: Box::new(LeastLoaded::new(LoadMetric::QueueDepth)),
+        });
+        let handle = system.handle();
+        let inbox = handle.inbox().unwrap();
+        let block = handle.spawn_actor::<Block, _, _>("block", "").unwrap();
+        for _ in 0..5 {
+            inbox.send(&block, "work").unwrap();
+        }
+
+        // Both are placed on the other executor (where round-robin would alternate). Sharing
+        // an executor with the blocking actor would delay the reply by over a second.
+        handle.spawn_actor::<Sink, _, _>("sink", "").unwrap();
+        let echo = handle.spawn_actor::<Echo, _, _>("echo", "").unwrap();
+        let reply = handle
+            .ask(&echo, "ping", Duration::from_millis(250))
+            .unwrap();
+        assert_eq!(string_value(reply), "ping");
+        system.shutdown();
+    }
+
+    #[test]
+    fn test_handle_spawn_duplicate_name() {
+        let system = ActorSystem::init(ActorSystemConfig::default());
+        let handle = system.handle();
+        handle.spawn_actor::<Sink, _, _>("sink", "").unwrap();
+        assert!(matches!(
+            handle.spawn_actor::<Sink, _, _>("sink", ""),
+            Err(BusanError::UnassignableActor(_))
+        ));
+        system.shutdown();
+    }
+
+    #[test]
+    fn test_handle_after_shutdown() {
+        let system = ActorSystem::init(ActorSystemConfig::default());
+        let handle = system.handle();
+        let echo = handle.spawn_actor::<Echo, _, _>("echo", "").unwrap();
+        let inbox = handle.inbox().unwrap();
+        system.shutdown();
+
+        assert!(!handle.is_running());
+        assert!(matches!(
+            handle.send(&echo, "hello"),
+            Err(BusanError::SystemShutdown)
+        ));
+        assert!(matches!(
+            handle.ask(&echo, "hello", TIMEOUT),
+            Err(BusanError::SystemShutdown)
+        ));
+        assert!(matches!(
+            handle.spawn_actor::<Echo, _, _>("other", ""),
+            Err(BusanError::SystemShutdown)
+        ));
+        assert!(matches!(handle.inbox(), Err(BusanError::SystemShutdown)));
+        assert!(matches!(
+            inbox.send(&echo, "hello"),
+            Err(BusanError::SystemShutdown)
+        ));
+    }
+
+    #[test]
+    fn test_inbox_address_after_drop() {
+        let system = ActorSystem::init(ActorSystemConfig::default());
+        let handle = system.handle();
+        let inbox = handle.inbox().unwrap();
+        let address = inbox.address().clone();
+        assert!(handle.runtime_manager.resolve_address(&address).is_some());
+
+        drop(inbox);
+        assert!(handle.runtime_manager.resolve_address(&address).is_none());
+        system.shutdown();
+    }
+
+    fn init_with_groups(groups: Vec<ExecutorGroupConfig>) -> ActorSystem {
+        ActorSystem::init(ActorSystemConfig {
+            executor_config: ExecutorConfig {
+                num_executors: 1,
+                groups,
+                ..ExecutorConfig::default()
+            },
+            ..ActorSystemConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_pinned_group() {
+        let system = init_with_groups(vec![ExecutorGroupConfig::pinned("pinned")]);
+        let handle = system.handle();
+        let inbox = handle.inbox().unwrap();
+        assert!(handle.executors("pinned").unwrap().is_empty());
+
+        let options = || SpawnOptions::new().with_group("pinned");
+        let a = handle
+            .spawn_actor_with::<Echo, _, _>("a", "", options())
+            .unwrap();
+        handle
+            .spawn_actor_with::<Echo, _, _>("b", "", options())
+            .unwrap();
+        let pinned = handle.executors("pinned").unwrap();
+        assert_eq!(pinned.len(), 2);
+        let default = handle.executors(DEFAULT_EXECUTOR_GROUP).unwrap();
+        assert!(pinned.iter().all(|executor| !default.contains(executor)));
+
+        inbox.send(&a, "hello").unwrap();
+        assert_eq!(string_value(inbox.receive(TIMEOUT).unwrap()), "hello");
+
+        // The executor of `a` (started first) stops once `a` has stopped
+        inbox.send(&a, PoisonPill {}).unwrap();
+        let deadline = Instant::now() + TIMEOUT;
+        while handle.executors("pinned").unwrap().len() > 1 {
+            assert!(Instant::now() < deadline, "executor of a did not stop");
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(handle.executors("pinned").unwrap(), pinned[1..]);
+        assert!(handle.runtime_manager.resolve_address(&a).is_none());
+        system.shutdown();
+    }
+
+    #[test]
+    fn test_unknown_group() {
+        let system = init_with_groups(Vec::new());
+        let handle = system.handle();
+        assert!(matches!(
+            handle.spawn_actor_with::<Echo, _, _>(
+                "echo",
+                "",
+                SpawnOptions::new().with_group("missing")
+            ),
+            Err(BusanError::UnassignableActor(_))
+        ));
+        assert!(matches!(
+            handle.executors("missing"),
+            Err(BusanError::InvalidExecutor(_))
+        ));
+        system.shutdown();
+    }
+
+    #[test]
+    fn test_blocking_group_does_not_stall_default_group() {
+        let system = init_with_groups(vec![ExecutorGroupConfig::shared("blocking", 1)]);
+        let handle = system.handle();
+        let inbox = handle.inbox().unwrap();
+        let block = handle
+            .spawn_actor_with::<Block, _, _>(
+                "block",
+                "",
+                SpawnOptions::new().with_group("blocking"),
+            )
+            .unwrap();
+        let echo = handle.spawn_actor::<Echo, _, _>("echo", "").unwrap();
+
+        // `block` replies before blocking its executor, so the default group is asked while the
+        // blocking group is stalled
+        inbox.send(&block, "block").unwrap();
+        assert_eq!(string_value(inbox.receive(TIMEOUT).unwrap()), "block");
+        let reply = handle
+            .ask(&echo, "ping", Duration::from_millis(100))
+            .unwrap();
+        assert_eq!(string_value(reply), "ping");
+        system.shutdown();
+    }
+
+    /// Checks that it receives consecutive numbers and counts how often it was started and its
+    /// timer fired. Replies to a `StringWrapper` with `<next number> <starts> <timers>`.
+    struct Counter {
+        next: u32,
+        in_order: bool,
+        starts: u32,
+        timers: u32,
+    }
+
+    impl ActorInit for Counter {
+        type Init = StringWrapper;
+
+        fn init(_init_msg: Self::Init) -> Self {
+            Counter {
+                next: 0,
+                in_order: true,
+                starts: 0,
+                timers: 0,
+            }
+        }
+    }
+
+    impl Actor for Counter {
+        fn before_start(&mut self, ctx: Context) {
+            self.starts += 1;
+            ctx.send_after(ctx.address(), "timer", Duration::from_millis(300));
+        }
+
+        fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+            if let Some(n) = msg.as_any().downcast_ref::<U32Wrapper>() {
+                self.in_order &= n.value == self.next;
+                self.next = n.value + 1;
+            } else if string_value(msg) == "timer" {
+                self.timers += 1;
+            } else {
+                let next = if self.in_order { self.next } else { u32::MAX };
+                ctx.send(
+                    ctx.sender(),
+                    format!("{} {} {}", next, self.starts, self.timers),
+                );
+            }
+        }
+    }
+
+    fn init_with_executors(executor_type: ExecutorType) -> ActorSystem {
+        ActorSystem::init(ActorSystemConfig {
+            executor_config: ExecutorConfig {
+                num_executors: 2,
+                executor_type,
+                ..ExecutorConfig::default()
+            },
+            ..ActorSystemConfig::default()
+        })
+    }
+
+    fn spawn_counters(handle: &SystemHandle) -> Vec<ActorAddress> {
+        (0..4)
+            .map(|i| {
+                handle
+                    .spawn_actor::<Counter, _, _>(&format!("c{}", i), "")
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    /// Remove executors while messages are sent to actors on them and their timers are pending
+    fn test_remove_executors(executor_type: ExecutorType) {
+        let count: u32 = 2000;
+        let system = init_with_executors(executor_type);
+        let handle = system.handle();
+        let counters = spawn_counters(&handle);
+        let sender = {
+            let (handle, counters) = (handle.clone(), counters.clone());
+            thread::spawn(move || {
+                for n in 0..count {
+                    for counter in &counters {
+                        handle.send(counter, n).unwrap();
+                    }
+                }
+            })
+        };
+
+        let added = handle.add_executor(DEFAULT_EXECUTOR_GROUP).unwrap();
+        handle.remove_executor("executor-0").unwrap();
+        handle.remove_executor("executor-1").unwrap();
+        assert_eq!(
+            handle.executors(DEFAULT_EXECUTOR_GROUP).unwrap(),
+            vec![added]
+        );
+        sender.join().unwrap();
+
+        // Every actor got every message in order, was started once and its timer fired
+        let inbox = handle.inbox().unwrap();
+        let expected = format!("{} 1 1", count);
+        for counter in &counters {
+            let deadline = Instant::now() + TIMEOUT;
+            loop {
+                inbox.send(counter, "report").unwrap();
+                let report = string_value(inbox.receive(TIMEOUT).unwrap());
+                if report == expected {
+                    break;
+                }
+                assert!(Instant::now() < deadline, "{} reported {}", counter, report);
+                thread::sleep(Duration::from_millis(50));
+            }
+        }
+        system.shutdown();
+    }
+
+    #[test]
+    fn test_remove_thread_executors() {
+        test_remove_executors(ExecutorType::Thread);
+    }
+
+    #[test]
+    fn test_remove_work_stealing_executors() {
+        test_remove_executors(ExecutorType::WorkStealing);
+    }
+
+    #[test]
+    fn test_remove_deterministic_executors() {
+        let system = init_with_executors(ExecutorType::Deterministic { seed: 3 });
+        let handle = system.handle();
+        let counters = spawn_counters(&handle);
+        let send = |range: std::ops::Range<u32>| {
+            for n in range {
+                for counter in &counters {
+                    handle.send(counter, n).unwrap();
+                }
+            }
+        };
+
+        send(0..100);
+        let added = handle.add_executor(DEFAULT_EXECUTOR_GROUP).unwrap();
+        handle.remove_executor("executor-0").unwrap();
+        system.run_until_idle();
+        send(100..200);
+        handle.remove_executor(&added).unwrap();
+        system.run_until_idle();
+        assert_eq!(
+            handle.executors(DEFAULT_EXECUTOR_GROUP).unwrap(),
+            vec!["executor-1".to_string()]
+        );
+
+        system.advance_time(Duration::from_secs(1));
+        let inbox = handle.inbox().unwrap();
+        for counter in &counters {
+            inbox.send(counter, "report").unwrap();
+            assert_eq!(string_value(inbox.receive(TIMEOUT).unwrap()), "200 1 1");
+        }
+        system.shutdown();
+    }
+
+    #[test]
+    fn test_remove_executor_errors() {
+        let system = init_with_groups(vec![ExecutorGroupConfig::pinned("pinned")]);
+        let handle = system.handle();
+        handle
+            .spawn_actor_with::<Sink, _, _>("sink", "", SpawnOptions::new().with_group("pinned"))
+            .unwrap();
+
+        let executors = handle.executors(DEFAULT_EXECUTOR_GROUP).unwrap();
+        assert!(matches!(
+            handle.remove_executor(&executors[0]),
+            Err(BusanError::InvalidExecutor(_))
+        ));
+        let pinned = handle.executors("pinned").unwrap();
+        assert!(matches!(
+            handle.remove_executor(&pinned[0]),
+            Err(BusanError::InvalidExecutor(_))
+        ));
+        assert!(matches!(
+            handle.add_executor("pinned"),
+            Err(BusanError::InvalidExecutor(_))
+        ));
+        assert!(matches!(
+            handle.remove_executor("missing"),
+            Err(BusanError::InvalidExecutor(_))
+        ));
+        assert_eq!(handle.executors(DEFAULT_EXECUTOR_GROUP).unwrap(), executors);
+        system.shutdown();
+    }
+
+    /// Spawns a child that stops right away on `"spawn"`, and a child that fails to start on
+    /// `"fail"`. Replies to other messages with the number of its children.
+    struct Parent;
+
+    impl ActorInit for Parent {
+        type Init = StringWrapper;
+
+        fn init(_init_msg: Self::Init) -> Self {
+            Parent
+        }
+    }
+
+    impl Actor for Parent {
+        fn receive(&mut self, mut ctx: Context, msg: Box<dyn Message>) {
+            match string_value(msg).as_str() {
+                "spawn" => {
+                    let child = ctx.spawn_child::<Sink, _, _>("child", "").await_unwrap();
+                    ctx.send(&child, PoisonPill {});
+                }
+                "fail" => {
+                    let options = SpawnOptions::new().with_group("missing");
+                    let child = ctx.spawn_child_with::<Sink, _, _>("child", "", options);
+                    assert!(child.await_ready().is_err());
+                }
+                _ => ctx.send(ctx.sender(), ctx.children().len().to_string()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_stopped_children_removed() {
+        let system = ActorSystem::init(ActorSystemConfig::default());
+        let handle = system.handle();
+        let parent = handle.spawn_actor::<Parent, _, _>("parent", "").unwrap();
+        for _ in 0..3 {
+            handle.send(&parent, "spawn").unwrap();
+        }
+        handle.send(&parent, "fail").unwrap();
+
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            let children = string_value(handle.ask(&parent, "count", TIMEOUT).unwrap());
+            if children == "0" {
+                break;
+            }
+            assert!(Instant::now() < deadline, "{} children left", children);
+            thread::sleep(Duration::from_millis(10));
+        }
+        system.shutdown();
+    }
+
+    /// Send through an address shared with another thread while the actor behind it is stopped
+    /// and spawned again, so the resolution of the address is cleared while the other thread is
+    /// sending.
+    #[test]
+    fn test_shared_address_across_restart() {
+        let system = ActorSystem::init(ActorSystemConfig::default());
+        let handle = system.handle();
+        let inbox = handle.inbox().unwrap();
+        // Keeps the system running while no forward actor is
+        handle.spawn_actor::<Sink, _, _>("sink", "").unwrap();
+        let init = inbox.address().to_string();
+        let forward = handle
+            .spawn_actor::<Forward, _, _>("forward", init.clone())
+            .unwrap();
+        // Without an incarnation, the address resolves to whichever actor runs at the path
+        let shared: ActorAddress = forward.uri.to_string().parse().unwrap();
+        let sending = AtomicBool::new(true);
+
+        thread::scope(|scope| {
+            for _ in 0..2 {
+                scope.spawn(|| {
+                    while sending.load(Ordering::Relaxed) {
+                        handle.send(&shared, "ping").unwrap();
+                        thread::yield_now();
+                    }
+                });
+            }
+            for _ in 0..100 {
+                handle.send(&shared, PoisonPill {}).unwrap();
+                // The path can be reused once the previous actor has stopped
+                while handle
+                    .spawn_actor::<Forward, _, _>("forward", init.clone())
+                    .is_err()
+                {
+                    thread::yield_now();
+                }
+            }
+            sending.store(false, Ordering::Relaxed);
+        });
+
+        handle.send(&shared, "last").unwrap();
+        while string_value(inbox.receive(TIMEOUT).unwrap()) != "last" {}
+        system.shutdown();
+    }
+}
@@ -3,24 +3,56 @@
 //! points within busan and the execution model is guaranteed to work with the underlying channel
 //! implementation.
 
+use crate::util::signal::Signal;
 use crossbeam_channel::{Receiver, Sender};
+use std::sync::Arc;
+use std::time::Duration;
 
 /// A simple wrapper for sending and receiving objects/commands over a channel. This object
 /// represents both a sender and receiver for a channel.
+///
+/// Sending on the channel wakes the thread blocked in [`wait`](CommandChannel::wait), which
+/// lets a receiver wait for commands and other work (such as messages for an executor's actors)
+/// at the same time.
 pub struct CommandChannel<T> {
     pub(self) sender: Sender<T>,
     pub(self) receiver: Receiver<T>,
+    pub(self) signal: Arc<Signal>,
 }
 
 impl<T> CommandChannel<T> {
     pub fn new() -> CommandChannel<T> {
         let (sender, receiver) = crossbeam_channel::unbounded();
-        CommandChannel { sender, receiver }
+        CommandChannel {
+            sender,
+            receiver,
+            signal: Arc::new(Signal::default()),
+        }
     }
 
     // TODO: Wrap the error type to avoid exposing the underlying channel implementation
     pub fn send(&self, command: T) -> Result<(), crossbeam_channel::SendError<T>> {
-        self.sender.send(command)
+        self.sender.send(command)?;
+        self.signal.notify();
+        Ok(())
+    }
+
+    /// Block until a command is sent on the channel, the channel is otherwise notified, or
+    /// `timeout` has elapsed. Waits may return early, so callers should check for work in a loop.
+    /// Only a single thread (the receiver) may wait on a channel.
+    pub fn wait(&self, timeout: Option<Duration>) {
+        if self.recv_is_empty() {
+            self.signal.wait(timeout);
+        }
+    }
+
+    /// Wake the thread waiting on the channel without sending a command.
+    pub(crate) fn notify(&self) {
+        self.signal.notify();
+    }
+
+    pub fn try_recv(&self) -> Option<T> {
+        self.receiver.try_recv().ok()
     }
 
     // TODO: Wrap the error type to avoid exposing the underlying channel implementation
@@ -38,6 +70,7 @@ impl<T> Clone for CommandChannel<T> {
         CommandChannel {
             sender: self.sender.clone(),
             receiver: self.receiver.clone(),
+            signal: self.signal.clone(),
         }
     }
 }
@@ -0,0 +1,10 @@
+//! Hashing utilities for internal use, where hashes must be stable (unlike the std hasher).
+
+/// 64-bit FNV-1a hash. Used over the std hasher so that hashes are stable across builds.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes
+        .iter()
+        .fold(OFFSET, |hash, b| (hash ^ *b as u64).wrapping_mul(PRIME))
+}
@@ -19,20 +19,4 @@ macro_rules! channel_send {
     }};
 }
 
-macro_rules! channel_must_recv {
-    ($e:expr) => {{
-        let res = ($e).recv();
-        // Emit a useful error log
-        if res.is_err() {
-            ::log::error!(
-                "Unable to receive message from channel, {}",
-                res.as_ref().err().unwrap()
-            );
-        }
-        // Unwrap the result, possibly panic
-        res.unwrap()
-    }};
-}
-
-pub(crate) use channel_must_recv;
 pub(crate) use channel_send;
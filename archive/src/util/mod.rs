@@ -2,6 +2,11 @@
 //! extending Busan, and internal utilities for Busan itself.
 
 pub mod command_channel;
+pub(crate) mod hash;
 pub(crate) mod lib_macros;
+pub(crate) mod rng;
+pub(crate) mod signal;
+#[cfg(test)]
+pub(crate) mod testing;
 
 pub use command_channel::CommandChannel;
@@ -0,0 +1,67 @@
+//! A small, non-cryptographic pseudo-random number generator for internal use (such as random
+//! routing). This avoids a dependency on a full-featured random crate for the few places that
+//! require randomness.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// xorshift64* generator. Not suitable for anything security related.
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Create a generator with a fixed seed. The same seed always produces the same sequence.
+    pub(crate) fn with_seed(seed: u64) -> Self {
+        // A zero state would only ever produce zeros, so mix the seed (splitmix64) first.
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        Self {
+            state: if z == 0 { 1 } else { z },
+        }
+    }
+
+    /// Create a generator seeded from the process' random hash-map keys.
+    pub(crate) fn from_entropy() -> Self {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(0);
+        Self::with_seed(hasher.finish())
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a value in the range `[0, upper)`. `upper` must be greater than zero.
+    pub(crate) fn gen_range(&mut self, upper: usize) -> usize {
+        debug_assert!(upper > 0, "upper bound must be greater than zero");
+        (self.next_u64() % upper as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_sequence_is_repeatable() {
+        let mut a = Rng::with_seed(42);
+        let mut b = Rng::with_seed(42);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn test_gen_range() {
+        let mut rng = Rng::with_seed(0);
+        for _ in 0..1000 {
+            assert!(rng.gen_range(3) < 3);
+        }
+    }
+}
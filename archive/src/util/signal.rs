@@ -0,0 +1,94 @@
+//! Wake-up signal for a thread that waits for work, such as an executor waiting for messages.
+
+use std::sync::OnceLock;
+use std::thread::{self, Thread};
+use std::time::Duration;
+
+/// A signal that wakes a single waiting thread. The thread that first waits on the signal is
+/// registered as the waiter, after which [`notify`](Signal::notify) unparks that thread.
+/// Notifications are not lost: a notification sent while the thread is not waiting causes the
+/// next wait to return immediately.
+#[derive(Debug, Default)]
+pub(crate) struct Signal {
+    waiter: OnceLock<Thread>,
+}
+
+impl Signal {
+    /// Wake the waiting thread, if there is one.
+    pub(crate) fn notify(&self) {
+        if let Some(waiter) = self.waiter.get() {
+            waiter.unpark();
+        }
+    }
+
+    /// Block the current thread until notified or until `timeout` has elapsed. Callers must
+    /// re-check for work after waking, since waits may also return early (e.g. on the first
+    /// wait, which only registers the thread).
+    pub(crate) fn wait(&self, timeout: Option<Duration>) {
+        let mut registered = false;
+        let waiter = self.waiter.get_or_init(|| {
+            registered = true;
+            thread::current()
+        });
+        // Notifications sent before the thread was registered were dropped, so return to let
+        // the caller check for work before actually waiting.
+        if registered {
+            return;
+        }
+        debug_assert_eq!(
+            waiter.id(),
+            thread::current().id(),
+            "only a single thread may wait on a signal"
+        );
+        match timeout {
+            Some(timeout) => thread::park_timeout(timeout),
+            None => thread::park(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+
+    #[test]
+    fn test_wake_after_park() {
+        let signal = Arc::new(Signal::default());
+        let work = Arc::new(AtomicBool::new(false));
+        let (done_sender, done) = mpsc::channel();
+        {
+            let (signal, work) = (signal.clone(), work.clone());
+            thread::spawn(move || {
+                // Waits without a timeout, so only a notification wakes the thread (apart from
+                // spurious wake-ups, which the loop handles)
+                while !work.load(Ordering::SeqCst) {
+                    signal.wait(None);
+                }
+                done_sender.send(()).unwrap();
+            });
+        }
+
+        // Give the thread time to register and park
+        thread::sleep(Duration::from_millis(100));
+        assert!(done.try_recv().is_err());
+        work.store(true, Ordering::SeqCst);
+        signal.notify();
+        done.recv_timeout(Duration::from_secs(5))
+            .expect("parked thread was not woken");
+    }
+
+    #[test]
+    fn test_notify_before_wait() {
+        let signal = Signal::default();
+        // The first wait only registers the thread
+        signal.wait(None);
+
+        // A notification sent while the thread is not waiting is not lost
+        signal.notify();
+        let start = std::time::Instant::now();
+        signal.wait(Some(Duration::from_secs(5)));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
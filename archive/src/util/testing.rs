@@ -0,0 +1,88 @@
+//! Actors shared by the tests that run an actor system.
+
+use crate::actor::{Actor, ActorAddress, ActorInit, Context};
+use crate::message::common_types::StringWrapper;
+use crate::message::Message;
+use std::thread;
+use std::time::Duration;
+
+/// Replies to every message with the message itself
+pub(crate) struct Echo;
+
+impl ActorInit for Echo {
+    type Init = StringWrapper;
+
+    fn init(_init_msg: Self::Init) -> Self {
+        Echo
+    }
+}
+
+impl Actor for Echo {
+    fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+        ctx.send_message(ctx.sender(), msg, None);
+    }
+}
+
+/// Forwards every message, with its original sender, to the address it is initialized with
+/// (such as the address of an `Inbox`).
+pub(crate) struct Forward {
+    to: ActorAddress,
+}
+
+impl ActorInit for Forward {
+    type Init = StringWrapper;
+
+    fn init(init_msg: Self::Init) -> Self {
+        Forward {
+            to: init_msg.value.parse().unwrap(),
+        }
+    }
+}
+
+impl Actor for Forward {
+    fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+        ctx.forward(&self.to, msg);
+    }
+}
+
+/// Ignores every message
+pub(crate) struct Sink;
+
+impl ActorInit for Sink {
+    type Init = StringWrapper;
+
+    fn init(_init_msg: Self::Init) -> Self {
+        Sink
+    }
+}
+
+impl Actor for Sink {
+    fn receive(&mut self, _ctx: Context, _msg: Box<dyn Message>) {}
+}
+
+/// Replies to every message with the message itself and then blocks its executor for a while
+pub(crate) struct Block;
+
+impl ActorInit for Block {
+    type Init = StringWrapper;
+
+    fn init(_init_msg: Self::Init) -> Self {
+        Block
+    }
+}
+
+impl Actor for Block {
+    fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+        ctx.send_message(ctx.sender(), msg, None);
+        thread::sleep(Duration::from_millis(300));
+    }
+}
+
+/// The value of a received `StringWrapper`
+pub(crate) fn string_value(msg: Box<dyn Message>) -> String {
+    msg.as_any()
+        .downcast_ref::<StringWrapper>()
+        .expect("expected a StringWrapper")
+        .value
+        .clone()
+}
@@ -0,0 +1,235 @@
+//! Tests running actors on a custom executor (`ExecutorType::Custom`). These live outside of the
+//! crate so that the executor is built on the public API only.
+
+use busan::actor::{Actor, ActorAddress, ActorCell, ActorInit, Context};
+use busan::config::{ActorSystemConfig, ExecutorConfig, ExecutorType, DEFAULT_EXECUTOR_GROUP};
+use busan::executor::{
+    Executor, ExecutorCommands, ExecutorFactory, ExecutorHandle, RunQueue, ScheduledMessage,
+};
+use busan::message::common_types::StringWrapper;
+use busan::message::system::PoisonPill;
+use busan::message::Message;
+use busan::system::RuntimeManagerRef;
+use busan::util::CommandChannel;
+use busan::ActorSystem;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Starts [`TestExecutor`]s, counting the executors it started
+#[derive(Default)]
+struct TestExecutorFactory {
+    started: AtomicUsize,
+}
+
+impl ExecutorFactory for TestExecutorFactory {
+    fn spawn_executor(
+        &self,
+        name: String,
+        command_channel: CommandChannel<ExecutorCommands>,
+        manager_ref: RuntimeManagerRef,
+    ) -> ExecutorHandle {
+        self.started.fetch_add(1, Ordering::SeqCst);
+        let executor = TestExecutor {
+            name,
+            cells: HashMap::new(),
+            timers: Vec::new(),
+            command_channel,
+            manager_ref,
+        };
+        let t = thread::spawn(move || executor.run());
+        ExecutorHandle::new(move || t.join().unwrap())
+    }
+}
+
+/// Runs its actors on a single thread, following the protocol described by `ExecutorFactory`
+struct TestExecutor {
+    name: String,
+    cells: HashMap<u64, ActorCell>,
+    timers: Vec<ScheduledMessage>,
+    command_channel: CommandChannel<ExecutorCommands>,
+    manager_ref: RuntimeManagerRef,
+}
+
+impl TestExecutor {
+    /// Handle a command. Returns false if the executor should shut down.
+    fn handle(&mut self, command: ExecutorCommands) -> bool {
+        let (rm, cc) = (&self.manager_ref, &self.command_channel);
+        match command {
+            ExecutorCommands::AssignActor(mut cell) => {
+                cell.start(rm, cc);
+                let id = cell.address().incarnation();
+                self.cells.insert(id, cell);
+                self.cells[&id].schedule_pending();
+            }
+            ExecutorCommands::RunActor(id) => {
+                if let Some(cell) = self.cells.get_mut(&id) {
+                    if cell.run_turn(rm, cc, 5) {
+                        cc.schedule(id);
+                    }
+                }
+            }
+            ExecutorCommands::ShutdownActor(address) => {
+                if let Some(cell) = self.cells.get_mut(&address.incarnation()) {
+                    cell.stop(rm, cc);
+                }
+            }
+            ExecutorCommands::ShutdownActorComplete(address) => {
+                if let Some(cell) = self.cells.remove(&address.incarnation()) {
+                    cell.complete_stop(rm);
+                }
+            }
+            ExecutorCommands::ScheduleMessage(message) => self.timers.push(message),
+            ExecutorCommands::Drain => {
+                while let Some(command) = cc.try_recv() {
+                    if let ExecutorCommands::ScheduleMessage(message) = command {
+                        self.timers.push(message);
+                    }
+                }
+                let cells = self.cells.drain().map(|(_, cell)| cell).collect();
+                let timers = std::mem::take(&mut self.timers);
+                rm.executor_drained(self.name.clone(), cells, timers);
+                return false;
+            }
+            ExecutorCommands::Shutdown => return false,
+        }
+        true
+    }
+}
+
+impl Executor for TestExecutor {
+    fn run(mut self) {
+        loop {
+            if let Some(command) = self.command_channel.try_recv() {
+                if !self.handle(command) {
+                    break;
+                }
+                continue;
+            }
+            let now = Instant::now();
+            let (due, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.timers)
+                .into_iter()
+                .partition(|message| message.deadline() <= now);
+            self.timers = pending;
+            if !due.is_empty() {
+                due.into_iter().for_each(ScheduledMessage::deliver);
+                continue;
+            }
+            let next_deadline = self.timers.iter().map(ScheduledMessage::deadline).min();
+            self.command_channel
+                .wait(next_deadline.map(|deadline| deadline.saturating_duration_since(now)));
+        }
+        self.manager_ref.notify_shutdown(self.name);
+    }
+}
+
+fn string_value(msg: Box<dyn Message>) -> String {
+    msg.as_any()
+        .downcast_ref::<StringWrapper>()
+        .unwrap()
+        .value
+        .clone()
+}
+
+/// Replies to every message with the message itself
+struct Echo;
+
+impl ActorInit for Echo {
+    type Init = StringWrapper;
+
+    fn init(_init_msg: Self::Init) -> Self {
+        Echo
+    }
+}
+
+impl Actor for Echo {
+    fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+        ctx.send_message(ctx.sender(), msg, None);
+    }
+}
+
+/// Forwards every message to an inbox, and tells the inbox when it stops
+struct Announce {
+    to: ActorAddress,
+}
+
+impl ActorInit for Announce {
+    type Init = StringWrapper;
+
+    fn init(init_msg: Self::Init) -> Self {
+        Announce {
+            to: init_msg.value.parse().unwrap(),
+        }
+    }
+}
+
+impl Actor for Announce {
+    fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+        ctx.forward(&self.to, msg);
+    }
+
+    fn before_stop(&mut self, ctx: Context) {
+        ctx.send(&self.to, "stopping");
+    }
+}
+
+/// Replies to every message after a delay
+struct Delay;
+
+impl ActorInit for Delay {
+    type Init = StringWrapper;
+
+    fn init(_init_msg: Self::Init) -> Self {
+        Delay
+    }
+}
+
+impl Actor for Delay {
+    fn receive(&mut self, ctx: Context, msg: Box<dyn Message>) {
+        ctx.send_after(ctx.sender(), string_value(msg), Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn test_custom_executor() {
+    let factory = Arc::new(TestExecutorFactory::default());
+    let system = ActorSystem::init(ActorSystemConfig {
+        executor_config: ExecutorConfig {
+            num_executors: 2,
+            executor_type: ExecutorType::Custom(factory.clone()),
+            ..ExecutorConfig::default()
+        },
+        ..ActorSystemConfig::default()
+    });
+    assert_eq!(factory.started.load(Ordering::SeqCst), 2);
+    let handle = system.handle();
+    let inbox = handle.inbox().unwrap();
+    let echo = handle.spawn_actor::<Echo, _, _>("echo", "").unwrap();
+    let delay = handle.spawn_actor::<Delay, _, _>("delay", "").unwrap();
+    let announce = handle
+        .spawn_actor::<Announce, _, _>("announce", inbox.address().to_string())
+        .unwrap();
+
+    let reply = handle.ask(&echo, "ping", TIMEOUT).unwrap();
+    assert_eq!(string_value(reply), "ping");
+    let reply = handle.ask(&delay, "later", TIMEOUT).unwrap();
+    assert_eq!(string_value(reply), "later");
+
+    // Actors of a removed executor are moved to the remaining one
+    let executors = handle.executors(DEFAULT_EXECUTOR_GROUP).unwrap();
+    handle.remove_executor(&executors[0]).unwrap();
+    for i in 0..10 {
+        handle.send(&announce, i.to_string()).unwrap();
+    }
+    for i in 0..10 {
+        assert_eq!(string_value(inbox.receive(TIMEOUT).unwrap()), i.to_string());
+    }
+
+    inbox.send(&announce, PoisonPill {}).unwrap();
+    assert_eq!(string_value(inbox.receive(TIMEOUT).unwrap()), "stopping");
+    system.shutdown();
+}